@@ -1,4 +1,10 @@
-use crate::{bitboards, castling, r#move::{move_record::MoveRecord, Move, MoveType}, piece::{self, *}, zobrist};
+#[cfg(feature = "incremental_eval")]
+use crate::eval::eval_state::EvalState;
+#[cfg(feature = "nnue")]
+use crate::eval::nnue::{Accumulator, Network};
+#[cfg(feature = "nnue")]
+use std::sync::Arc;
+use crate::{bitboards, castling, r#move::{move_record::{MoveRecord, NullMoveRecord}, Move, MoveType}, piece::{self, *}, zobrist};
 use rustc_hash::FxHashMap;
 
 // constants
@@ -70,6 +76,7 @@ const EMPTY_PIECES: [[u64; 7]; 2] = unsafe { std::mem::zeroed() };
 const DEFAULT_CASTLING_STATES: [u8; 2] = [0b11, 0b11];
 
 /// Contains everything about the current position
+#[derive(Clone)]
 pub struct Board {
     /// Contains bitboards for every piece type for each color
     /// 
@@ -90,6 +97,12 @@ pub struct Board {
     position_history: FxHashMap<u64, u8>,
     halfmoves_50_rule_counter: u8,
     move_counter: u16,
+    /// Material, piece-square tables and game phase updated on every piece creation and deletion
+    #[cfg(feature = "incremental_eval")]
+    eval_state: EvalState,
+    /// First layer of the NNUE updated on every piece creation and deletion, *None* without a network
+    #[cfg(feature = "nnue")]
+    accumulator: Option<Accumulator>,
 }
 
 impl Board {
@@ -107,6 +120,10 @@ impl Board {
             position_history: FxHashMap::default(),
             halfmoves_50_rule_counter: 0,
             move_counter: 0,
+            #[cfg(feature = "incremental_eval")]
+            eval_state: EvalState::EMPTY,
+            #[cfg(feature = "nnue")]
+            accumulator: None,
         };
         
         board
@@ -202,6 +219,22 @@ impl Board {
         self.move_counter
     }
 
+    #[cfg(feature = "incremental_eval")]
+    pub fn get_eval_state(&self) -> &EvalState {
+        &self.eval_state
+    }
+
+    #[cfg(feature = "nnue")]
+    pub fn get_accumulator(&self) -> Option<&Accumulator> {
+        self.accumulator.as_ref()
+    }
+
+    /// Sets the network evaluating the position (or removes it) and calculates its accumulator from scratch
+    #[cfg(feature = "nnue")]
+    pub fn set_network(&mut self, network: Option<Arc<Network>>) {
+        self.accumulator = network.map(|network| Accumulator::from_board(network, self));
+    }
+
     /// Updates the en passant state based on the provided square
     fn update_en_passant_state(&mut self, possible: bool, pawn_square: usize, capture_square: usize) {
         self.is_en_passant_possible = possible;
@@ -224,6 +257,13 @@ impl Board {
 
         unsafe {
             let (color, piece_type) = self.squares.get_unchecked_mut(square);
+
+            #[cfg(feature = "incremental_eval")]
+            self.eval_state.remove_piece(*color, *piece_type, square);
+            #[cfg(feature = "nnue")]
+            if let Some(accumulator) = &mut self.accumulator {
+                accumulator.remove_piece(*color, *piece_type, square);
+            }
         
             *self.pieces.get_unchecked_mut(*color).get_unchecked_mut(0) &= inverted_bit;
             *self.pieces.get_unchecked_mut(*color).get_unchecked_mut(*piece_type) &= inverted_bit;
@@ -247,6 +287,13 @@ impl Board {
             *self.pieces.get_unchecked_mut(color).get_unchecked_mut(0) |= bit;
             *self.pieces.get_unchecked_mut(color).get_unchecked_mut(piece_type) |= bit;
         }
+
+        #[cfg(feature = "incremental_eval")]
+        self.eval_state.add_piece(color, piece_type, square);
+        #[cfg(feature = "nnue")]
+        if let Some(accumulator) = &mut self.accumulator {
+            accumulator.add_piece(color, piece_type, square);
+        }
     }
 
     pub fn make_move(&mut self, move_to_make: Move) -> MoveRecord {    
@@ -255,6 +302,11 @@ impl Board {
             
         let old_hash = self.zobrist_hash;
         let old_castling_states = self.castling_states;
+        let old_is_en_passant_possible = self.is_en_passant_possible;
+        let old_en_passant_pawn_square = self.en_passant_pawn_square;
+        let old_en_passant_capture_square = self.en_passant_capture_square;
+        #[cfg(feature = "incremental_eval")]
+        let old_eval_state = self.eval_state;
         let old_halfmoves = self.halfmoves_50_rule_counter;
         let old_move_counter = self.move_counter;
         self.move_counter += 1 * opposite_color as u16;
@@ -335,17 +387,26 @@ impl Board {
         // Add new position to history
         *self.position_history.entry(self.zobrist_hash).or_insert(0) += 1;
 
-        MoveRecord::new(
+        let move_record = MoveRecord::new(
             move_to_make,
             captured_piece_type,
-            self.is_en_passant_possible,
-            self.en_passant_pawn_square,
-            self.en_passant_capture_square,
+            old_is_en_passant_possible,
+            old_en_passant_pawn_square,
+            old_en_passant_capture_square,
             old_castling_states,
             old_hash,
             old_halfmoves,
             old_move_counter
-        )
+        );
+
+        #[cfg(feature = "incremental_eval")]
+        let move_record = MoveRecord { old_eval_state, ..move_record };
+        #[cfg(feature = "incremental_eval")]
+        debug_assert!(self.eval_state == EvalState::from_board(self), "Incremental evaluation state diverged after making a move");
+        #[cfg(feature = "nnue")]
+        debug_assert!(self.accumulator.as_ref().is_none_or(|accumulator| *accumulator == Accumulator::from_board(Arc::clone(accumulator.get_network()), self)), "NNUE accumulator diverged after making a move");
+
+        move_record
     }
     
     /// Undoes the move from the provided MoveInfo object
@@ -405,14 +466,48 @@ impl Board {
 
         self.create_piece(start_square, current_color, moved_piece_type);
 
-        if captured_piece_type == piece::NONE {
-            return;
+        if captured_piece_type != piece::NONE {
+            self.create_piece(captured_square, self.get_opposite_color(), captured_piece_type, );
         }
 
-        self.create_piece(captured_square, self.get_opposite_color(), captured_piece_type, );
-    
+        #[cfg(feature = "incremental_eval")]
+        {
+            // the pieces have already updated the state on their way back, restoring it only makes sure it's exact
+            self.eval_state = move_record.old_eval_state;
+            debug_assert!(self.eval_state == EvalState::from_board(self), "Incremental evaluation state diverged after undoing a move");
+        }
+        #[cfg(feature = "nnue")]
+        debug_assert!(self.accumulator.as_ref().is_none_or(|accumulator| *accumulator == Accumulator::from_board(Arc::clone(accumulator.get_network()), self)), "NNUE accumulator diverged after undoing a move");
     }
     
+    /// Passes the turn to the opponent without moving anything, used by the null move pruning
+    ///
+    /// En passant is no longer possible after it. The position isn't added to the history,
+    /// since a position reached by passing can't be repeated in a real game
+    pub fn make_null_move(&mut self) -> NullMoveRecord {
+        let null_move_record = NullMoveRecord {
+            is_en_passant_possible: self.is_en_passant_possible,
+            en_passant_pawn_square: self.en_passant_pawn_square,
+            en_passant_capture_square: self.en_passant_capture_square,
+            old_hash: self.zobrist_hash,
+        };
+
+        self.update_en_passant_state(false, INVALID_SQUARE, INVALID_SQUARE);
+        self.switch_color();
+        self.zobrist_hash = zobrist::calculate_hash(self);
+
+        null_move_record
+    }
+
+    /// Undoes the null move from the provided record
+    pub fn undo_null_move(&mut self, null_move_record: NullMoveRecord) {
+        self.switch_color();
+        self.is_en_passant_possible = null_move_record.is_en_passant_possible;
+        self.en_passant_pawn_square = null_move_record.en_passant_pawn_square;
+        self.en_passant_capture_square = null_move_record.en_passant_capture_square;
+        self.zobrist_hash = null_move_record.old_hash;
+    }
+
     /// Checks if the current position has occurred three or more times
     #[inline]
     pub fn is_draw_by_repetition(&self) -> bool {
@@ -476,4 +571,95 @@ impl Board {
     pub fn get_position_history_mut(&mut self) -> &mut FxHashMap<u64, u8> {
         &mut self.position_history
     }
-}
\ No newline at end of file
+}
+/// Position setup for the tests of this crate, the full FEN API lives in the WASM crate which depends on this one
+#[cfg(test)]
+impl Board {
+    /// Returns the square of a name like "e4"
+    pub(crate) fn get_square_from_name(name: &str) -> usize {
+        let name = name.as_bytes();
+        let (file, rank) = ((name[0] - b'a') as usize, (name[1] - b'1') as usize);
+
+        rank * 8 + 7 - file
+    }
+
+    /// Loads every field of the FEN
+    pub(crate) fn from_fen(fen: &str) -> Self {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        let mut board = Self::empty();
+
+        for (rank_index, rank) in fields[0].split('/').enumerate() {
+            let mut file = 0;
+            for symbol in rank.chars() {
+                if let Some(empty_squares) = symbol.to_digit(10) {
+                    file += empty_squares as usize;
+                    continue;
+                }
+
+                let color = if symbol.is_ascii_uppercase() { WHITE } else { BLACK };
+                let piece_type = match symbol.to_ascii_lowercase() {
+                    'k' => KING,
+                    'p' => PAWN,
+                    'n' => KNIGHT,
+                    'b' => BISHOP,
+                    'r' => ROOK,
+                    'q' => QUEEN,
+                    _ => panic!("Invalid piece in the FEN: {}", symbol),
+                };
+                board.create_piece((7 - rank_index) * 8 + 7 - file, color, piece_type);
+                file += 1;
+            }
+        }
+
+        if fields.get(1) == Some(&"b") {
+            board.switch_color();
+        }
+
+        let castling_rights = fields.get(2).copied().unwrap_or("-");
+        for (color, king_side, queen_side) in [(WHITE, 'K', 'Q'), (BLACK, 'k', 'q')] {
+            board.castling_states[color] = castling::BOTH_SIDES;
+            if !castling_rights.contains(king_side) {
+                castling::annul_king_side(&mut board.castling_states[color]);
+            }
+            if !castling_rights.contains(queen_side) {
+                castling::annul_queen_side(&mut board.castling_states[color]);
+            }
+        }
+
+        if let Some(&square_name) = fields.get(3).filter(|&&square_name| square_name != "-") {
+            let capture_square = Self::get_square_from_name(square_name);
+            let pawn_square = if board.current_color == WHITE { capture_square - 8 } else { capture_square + 8 };
+            board.update_en_passant_state(true, pawn_square, capture_square);
+        }
+
+        board.halfmoves_50_rule_counter = fields.get(4).and_then(|count| count.parse().ok()).unwrap_or(0);
+        board.move_counter = fields.get(5).and_then(|count| count.parse().ok()).unwrap_or(1);
+        board.zobrist_hash = zobrist::calculate_hash(&board);
+        board.position_history.insert(board.zobrist_hash, 1);
+
+        board
+    }
+
+    /// Returns the legal move written in the UCI notation (like "e2e4" or "e7e8q")
+    pub(crate) fn find_move(&self, uci_move: &str) -> Move {
+        use crate::move_generation::{attack_calculator::AttackCalculator, move_gen};
+
+        let start_square = Self::get_square_from_name(&uci_move[0..2]);
+        let target_square = Self::get_square_from_name(&uci_move[2..4]);
+        let move_type = match uci_move.chars().nth(4) {
+            Some('q') => Some(MoveType::PromotionQueen),
+            Some('n') => Some(MoveType::PromotionKnight),
+            Some('r') => Some(MoveType::PromotionRook),
+            Some('b') => Some(MoveType::PromotionBishop),
+            _ => None,
+        };
+
+        let mut moves = move_gen::create_empty_move_buffer();
+        move_gen::generate_moves(&mut moves, self, &AttackCalculator::new(self));
+        moves.into_iter()
+            .find(|mov| mov.start_square == start_square
+                && mov.target_square == target_square
+                && move_type.is_none_or(|move_type| mov.move_type == move_type))
+            .unwrap_or_else(|| panic!("Illegal move: {}", uci_move))
+    }
+}
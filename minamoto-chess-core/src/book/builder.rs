@@ -0,0 +1,134 @@
+use rustc_hash::FxHashMap;
+
+use crate::{board::Board, r#move::Move, piece};
+
+use super::{polyglot, Book, BookEntry};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameResult {
+    WhiteWon,
+    BlackWon,
+    Draw,
+}
+
+/// Results of the games a move was played in, from the perspective of the side that played it
+#[derive(Clone, Copy, Default, Debug)]
+pub struct MoveStats {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MoveStats {
+    pub fn get_game_count(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Half points scored with the move, which is the weight before scaling
+    pub fn get_half_points(&self) -> u64 {
+        self.wins as u64 * 2 + self.draws as u64
+    }
+
+    fn add_result(&mut self, result: GameResult, color: usize) {
+        match (result, color) {
+            (GameResult::Draw, _) => self.draws += 1,
+            (GameResult::WhiteWon, piece::WHITE) | (GameResult::BlackWon, piece::BLACK) => self.wins += 1,
+            _ => self.losses += 1,
+        }
+    }
+}
+
+/// Collects the moves played in the opening of many games and turns them into a Polyglot book
+///
+/// The weight of a move is the amount of half points it scored (2 per win, 1 per draw),
+/// scaled down when it doesn't fit into the 16 bits of the entry
+pub struct BookBuilder {
+    /// Only the moves up to this ply of every game are collected
+    max_ply: usize,
+    /// Stats of the Polyglot moves by the Polyglot hash of the position they were played in
+    positions: FxHashMap<u64, FxHashMap<u16, MoveStats>>,
+    game_count: usize,
+}
+
+impl BookBuilder {
+    pub fn new(max_ply: usize) -> Self {
+        Self {
+            max_ply,
+            positions: FxHashMap::default(),
+            game_count: 0,
+        }
+    }
+
+    pub fn get_max_ply(&self) -> usize {
+        self.max_ply
+    }
+
+    pub fn get_game_count(&self) -> usize {
+        self.game_count
+    }
+
+    pub fn get_position_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Replays the legal moves of a game from the provided position and counts the result for every move up to the ply limit.
+    /// The board is back in the provided position afterwards
+    pub fn add_game(&mut self, board: &mut Board, moves: &[Move], result: GameResult) {
+        let mut move_records = Vec::with_capacity(self.max_ply);
+
+        for mov in moves.iter().take(self.max_ply) {
+            let color = board.get_current_color();
+            self.positions.entry(polyglot::calculate_hash(board))
+                .or_default()
+                .entry(polyglot::encode_move(mov))
+                .or_default()
+                .add_result(result, color);
+
+            move_records.push(board.make_move(*mov));
+        }
+
+        for move_record in move_records.into_iter().rev() {
+            board.undo_move(move_record);
+        }
+
+        self.game_count += 1;
+    }
+
+    /// Builds the book out of the moves played in at least *min_games* games.
+    /// The moves that never scored are left out since they would never be played
+    pub fn build(&self, min_games: u32) -> Book {
+        let mut entries = Vec::new();
+
+        for (&key, moves) in &self.positions {
+            let mut position_moves: Vec<(u16, u64)> = moves.iter()
+                .filter(|(_, stats)| stats.get_game_count() >= min_games.max(1))
+                .map(|(&encoded_move, stats)| (encoded_move, stats.get_half_points()))
+                .filter(|&(_, half_points)| half_points > 0)
+                .collect();
+
+            let Some(max_half_points) = position_moves.iter().map(|&(_, half_points)| half_points).max() else {
+                continue;
+            };
+
+            // Most books (and the readers of other programs) expect the moves of a position from the best to the worst
+            position_moves.sort_by_key(|&(encoded_move, half_points)| (std::cmp::Reverse(half_points), encoded_move));
+
+            for (encoded_move, half_points) in position_moves {
+                let weight = if max_half_points > u16::MAX as u64 {
+                    (half_points * u16::MAX as u64 / max_half_points).max(1)
+                } else {
+                    half_points
+                };
+
+                entries.push(BookEntry {
+                    key,
+                    encoded_move,
+                    weight: weight as u16,
+                    learn: 0,
+                });
+            }
+        }
+
+        Book::from_entries(entries)
+    }
+}
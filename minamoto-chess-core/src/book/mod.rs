@@ -0,0 +1,201 @@
+/*Opening books in the Polyglot format.
+
+A book file is a list of 16 byte entries sorted by the position hash, every number is big endian:
+    u64  Polyglot hash of the position
+    u16  move (see *polyglot*)
+    u16  weight, the moves with bigger weights are played more often
+    u32  learn data, unused
+
+A position has one entry per book move, so all its moves lie next to each other*/
+
+pub mod builder;
+pub mod polyglot;
+mod polyglot_keys;
+
+use std::fmt;
+
+use rand::Rng;
+
+use crate::{
+    board::Board,
+    r#move::Move,
+    move_generation::{attack_calculator::AttackCalculator, move_gen},
+};
+
+// constants
+pub const ENTRY_SIZE: usize = 16;
+
+#[derive(Debug)]
+pub enum BookError {
+    Io(std::io::Error),
+    /// The size of the file isn't a multiple of the entry size
+    InvalidSize(usize),
+}
+
+impl fmt::Display for BookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BookError::Io(error) => write!(f, "Failed to read the book: {}", error),
+            BookError::InvalidSize(size) => write!(f, "Invalid book size: {} bytes is not a multiple of {}", size, ENTRY_SIZE),
+        }
+    }
+}
+
+impl std::error::Error for BookError {}
+
+impl From<std::io::Error> for BookError {
+    fn from(error: std::io::Error) -> Self {
+        BookError::Io(error)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BookEntry {
+    pub key: u64,
+    pub encoded_move: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+impl BookEntry {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            key: u64::from_be_bytes(bytes[0..8].try_into().expect("Entry is 16 bytes long")),
+            encoded_move: u16::from_be_bytes(bytes[8..10].try_into().expect("Entry is 16 bytes long")),
+            weight: u16::from_be_bytes(bytes[10..12].try_into().expect("Entry is 16 bytes long")),
+            learn: u32::from_be_bytes(bytes[12..16].try_into().expect("Entry is 16 bytes long")),
+        }
+    }
+
+    fn to_bytes(self) -> [u8; ENTRY_SIZE] {
+        let mut bytes = [0; ENTRY_SIZE];
+        bytes[0..8].copy_from_slice(&self.key.to_be_bytes());
+        bytes[8..10].copy_from_slice(&self.encoded_move.to_be_bytes());
+        bytes[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        bytes[12..16].copy_from_slice(&self.learn.to_be_bytes());
+
+        bytes
+    }
+}
+
+/// Legal move found in the book
+#[derive(Clone, Copy)]
+pub struct BookMove {
+    pub mov: Move,
+    pub weight: u16,
+}
+
+pub struct Book {
+    /// Sorted by the key
+    entries: Vec<BookEntry>,
+}
+
+impl Book {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BookError> {
+        if !bytes.len().is_multiple_of(ENTRY_SIZE) {
+            return Err(BookError::InvalidSize(bytes.len()));
+        }
+
+        let entries = bytes.chunks_exact(ENTRY_SIZE)
+            .map(BookEntry::from_bytes)
+            .collect();
+
+        Ok(Self::from_entries(entries))
+    }
+
+    /// The format requires the entries sorted by the key, but some tools don't bother.
+    /// The order of the moves of every position is kept
+    pub fn from_entries(mut entries: Vec<BookEntry>) -> Self {
+        if !entries.is_sorted_by_key(|entry| entry.key) {
+            entries.sort_by_key(|entry| entry.key);
+        }
+
+        Self { entries }
+    }
+
+    /// Reads the book from a file. Not available in the browser, where the bytes come from JS
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self, BookError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    /// Writes the book in the Polyglot format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.entries.iter()
+            .flat_map(|entry| entry.to_bytes())
+            .collect()
+    }
+
+    /// Writes the book into a file. Not available in the browser, where the bytes go to JS
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn to_file(&self, path: impl AsRef<std::path::Path>) -> Result<(), BookError> {
+        Ok(std::fs::write(path, self.to_bytes())?)
+    }
+
+    /// Amount of entries (positions times their moves)
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Every entry of the position, found with a binary search by its Polyglot hash
+    pub fn get_entries(&self, board: &Board) -> &[BookEntry] {
+        let key = polyglot::calculate_hash(board);
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let end = start + self.entries[start..].partition_point(|entry| entry.key == key);
+
+        &self.entries[start..end]
+    }
+
+    /// Every legal book move of the position in the order of the book (usually from the biggest weight to the smallest).
+    /// The entries with illegal moves come from hash collisions and are skipped
+    pub fn get_moves(&self, board: &Board) -> Vec<BookMove> {
+        let entries = self.get_entries(board);
+        if entries.is_empty() {
+            return Vec::new();
+        }
+
+        let attack_calc = AttackCalculator::new(board);
+        let mut legal_moves = move_gen::create_empty_move_buffer();
+        move_gen::generate_moves(&mut legal_moves, board, &attack_calc);
+
+        entries.iter()
+            .filter_map(|entry| {
+                polyglot::decode_move(entry.encoded_move, &legal_moves)
+                    .map(|mov| BookMove { mov, weight: entry.weight })
+            })
+            .collect()
+    }
+
+    /// Returns the book move with the biggest weight, the first one wins a tie.
+    /// The moves with zero weight are never played
+    pub fn get_best_move(&self, board: &Board) -> Option<Move> {
+        self.get_moves(board).into_iter()
+            .filter(|book_move| book_move.weight > 0)
+            .reduce(|best, book_move| if book_move.weight > best.weight { book_move } else { best })
+            .map(|book_move| book_move.mov)
+    }
+
+    /// Returns a random book move, picked with a probability proportional to its weight.
+    /// The moves with zero weight are never played
+    pub fn get_weighted_random_move(&self, board: &Board, rng: &mut impl Rng) -> Option<Move> {
+        let book_moves = self.get_moves(board);
+        let total_weight: u32 = book_moves.iter().map(|book_move| book_move.weight as u32).sum();
+        if total_weight == 0 {
+            return None;
+        }
+
+        let mut roll = rng.random_range(0..total_weight);
+        for book_move in book_moves {
+            if roll < book_move.weight as u32 {
+                return Some(book_move.mov);
+            }
+            roll -= book_move.weight as u32;
+        }
+
+        unreachable!("Roll is below the total weight")
+    }
+}
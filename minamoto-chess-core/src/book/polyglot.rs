@@ -0,0 +1,164 @@
+/*Position hash and move encoding of the Polyglot book format.
+
+Polyglot numbers the squares from a1 (0) to h8 (63), which is the mirror of the board numbering (h1 is 0)
+along the vertical axis, so every square goes through *to_polyglot_square* on the way in and out.
+
+A move takes 16 bits:
+    bits 0-2    target file
+    bits 3-5    target rank
+    bits 6-8    start file
+    bits 9-11   start rank
+    bits 12-14  promotion piece (0 none, 1 knight, 2 bishop, 3 rook, 4 queen)
+
+Castling is stored as the king capturing its own rook (e1h1, e1a1, e8h8, e8a8)*/
+
+use crate::{
+    board::Board,
+    castling,
+    piece,
+    r#move::{Move, MoveType},
+};
+
+use super::polyglot_keys::POLYGLOT_RANDOM;
+
+// constants
+const CASTLING_KEY_OFFSET: usize = 768;
+const EN_PASSANT_KEY_OFFSET: usize = 772;
+const TURN_KEY_OFFSET: usize = 780;
+
+/// Converts a board square (h1 is 0) to a Polyglot square (a1 is 0) and back
+pub fn to_polyglot_square(square: usize) -> usize {
+    square ^ 7
+}
+
+/// Index of the piece among the 12 Polyglot pieces: black pawn, white pawn, black knight, ... white king
+fn get_piece_kind(color: usize, piece_type: usize) -> usize {
+    let type_index = match piece_type {
+        piece::PAWN => 0,
+        piece::KNIGHT => 1,
+        piece::BISHOP => 2,
+        piece::ROOK => 3,
+        piece::QUEEN => 4,
+        _ => 5,
+    };
+
+    type_index * 2 + (color == piece::WHITE) as usize
+}
+
+/// The en passant file only counts if a pawn of the side to move stands next to the pawn that just moved,
+/// even if the capture itself would be illegal
+fn is_en_passant_capturable(board: &Board) -> bool {
+    if !board.is_en_passant_possible() {
+        return false;
+    }
+
+    let pawn_square = board.en_passant_pawn_square();
+    let file = pawn_square & 7;
+    let neighbours = [
+        (file > 0).then(|| pawn_square - 1),
+        (file < 7).then(|| pawn_square + 1),
+    ];
+
+    neighbours.into_iter().flatten()
+        .any(|square| board.get_piece_on_square(square) == (board.get_current_color(), piece::PAWN))
+}
+
+/// Calculates the Polyglot hash of the position, which is the key of its entries in a book
+pub fn calculate_hash(board: &Board) -> u64 {
+    let mut hash = 0;
+
+    for square in 0..64 {
+        let (color, piece_type) = board.get_piece_on_square(square);
+        if piece_type != piece::NONE {
+            hash ^= POLYGLOT_RANDOM[get_piece_kind(color, piece_type) * 64 + to_polyglot_square(square)];
+        }
+    }
+
+    for color in [piece::WHITE, piece::BLACK] {
+        let castling_state = board.get_castling_state(color);
+        if castling::can_king_side(castling_state) {
+            hash ^= POLYGLOT_RANDOM[CASTLING_KEY_OFFSET + color * 2];
+        }
+        if castling::can_queen_side(castling_state) {
+            hash ^= POLYGLOT_RANDOM[CASTLING_KEY_OFFSET + color * 2 + 1];
+        }
+    }
+
+    if is_en_passant_capturable(board) {
+        hash ^= POLYGLOT_RANDOM[EN_PASSANT_KEY_OFFSET + 7 - (board.en_passant_pawn_square() & 7)];
+    }
+
+    if board.is_white_to_move() {
+        hash ^= POLYGLOT_RANDOM[TURN_KEY_OFFSET];
+    }
+
+    hash
+}
+
+/// Square of the rook the king "captures" in the Polyglot encoding of the castling move
+fn get_castling_rook_square(mov: &Move) -> usize {
+    let rank_start = mov.start_square & !7;
+
+    match mov.move_type {
+        MoveType::CastlingKingSide => rank_start,
+        _ => rank_start + 7,
+    }
+}
+
+fn get_promotion_code(move_type: MoveType) -> u16 {
+    match move_type {
+        MoveType::PromotionKnight => 1,
+        MoveType::PromotionBishop => 2,
+        MoveType::PromotionRook => 3,
+        MoveType::PromotionQueen => 4,
+        _ => 0,
+    }
+}
+
+/// Encodes the move the way Polyglot stores it
+pub fn encode_move(mov: &Move) -> u16 {
+    let target_square = if mov.is_castling() {
+        get_castling_rook_square(mov)
+    } else {
+        mov.target_square
+    };
+
+    to_polyglot_square(target_square) as u16
+    | (to_polyglot_square(mov.start_square) as u16) << 6
+    | get_promotion_code(mov.move_type) << 12
+}
+
+/// Finds the legal move matching the Polyglot move among the provided ones.
+/// Returns *None* if the move isn't legal, which happens with hash collisions and broken books
+pub fn decode_move(encoded_move: u16, legal_moves: &[Move]) -> Option<Move> {
+    legal_moves.iter()
+        .find(|mov| encode_move(mov) == encoded_move)
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    /// Plays the UCI moves from the starting position and returns the hash of the reached position
+    fn get_hash_after(uci_moves: &[&str]) -> u64 {
+        let mut board = Board::from_fen(START_FEN);
+        for uci_move in uci_moves {
+            let mov = board.find_move(uci_move);
+            board.make_move(mov);
+        }
+
+        calculate_hash(&board)
+    }
+
+    // Keys from the Polyglot format description
+    #[test]
+    fn hash_matches_the_reference_keys() {
+        assert_eq!(get_hash_after(&[]), 0x463b96181691fc9c);
+        assert_eq!(get_hash_after(&["e2e4"]), 0x823c9b50fd114196);
+        // the en passant key only counts with a pawn able to capture, here the one on e5
+        assert_eq!(get_hash_after(&["e2e4", "d7d5", "e4e5", "f7f5"]), 0x22a48b5a8e47ff78);
+    }
+}
@@ -0,0 +1,79 @@
+use crate::{bitboards, board::Board, piece};
+
+use super::{piece_square_tables, tapered_score::TaperedScore};
+
+/// Evaluation terms that depend only on the piece placement
+///
+/// The board keeps it updated on every piece creation and deletion
+/// (with the *incremental_eval* feature) so the evaluation doesn't have to loop over every piece
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct EvalState {
+    /// Sum of the piece values for each color
+    material: [TaperedScore; 2],
+    /// Sum of the piece-square table scores for each color
+    psqt: [TaperedScore; 2],
+    /// Game phase before clamping, may go over *MAX_PHASE* after early promotions
+    phase: i32,
+}
+
+impl EvalState {
+    pub const EMPTY: EvalState = EvalState {
+        material: [TaperedScore::ZERO; 2],
+        psqt: [TaperedScore::ZERO; 2],
+        phase: 0,
+    };
+
+    /// Calculates the state from scratch
+    pub fn from_board(board: &Board) -> Self {
+        let mut eval_state = Self::EMPTY;
+
+        for color in [piece::WHITE, piece::BLACK] {
+            for piece_type in piece::KING..=piece::QUEEN {
+                let mut piece_bb = board.get_piece_bitboard(color, piece_type);
+
+                while piece_bb != 0 {
+                    let square = bitboards::get_ls1b(piece_bb);
+                    piece_bb &= piece_bb - 1;
+
+                    eval_state.add_piece(color, piece_type, square);
+                }
+            }
+        }
+
+        eval_state
+    }
+
+    pub fn add_piece(&mut self, color: usize, piece_type: usize, square: usize) {
+        debug_assert!(color < 2, "Color is out of bounds");
+        self.material[color] += super::get_piece_value_tapered(piece_type);
+        self.psqt[color] += piece_square_tables::get_psqt_score(color, piece_type, square);
+        self.phase += super::get_phase_weight(piece_type);
+    }
+
+    pub fn remove_piece(&mut self, color: usize, piece_type: usize, square: usize) {
+        debug_assert!(color < 2, "Color is out of bounds");
+        self.material[color] -= super::get_piece_value_tapered(piece_type);
+        self.psqt[color] -= piece_square_tables::get_psqt_score(color, piece_type, square);
+        self.phase -= super::get_phase_weight(piece_type);
+    }
+
+    pub fn get_material(&self, color: usize) -> TaperedScore {
+        debug_assert!(color < 2, "Color is out of bounds");
+        self.material[color]
+    }
+
+    pub fn get_psqt(&self, color: usize) -> TaperedScore {
+        debug_assert!(color < 2, "Color is out of bounds");
+        self.psqt[color]
+    }
+
+    /// Returns the game phase from 0 (pawn endgame) to *MAX_PHASE* (opening)
+    pub fn get_phase(&self) -> i32 {
+        self.phase.min(super::MAX_PHASE)
+    }
+
+    /// Returns material and piece-square tables combined. Positive if white is better
+    pub fn get_material_and_psqt(&self) -> TaperedScore {
+        self.material[piece::WHITE] + self.psqt[piece::WHITE] - self.material[piece::BLACK] - self.psqt[piece::BLACK]
+    }
+}
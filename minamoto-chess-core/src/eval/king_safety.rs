@@ -0,0 +1,89 @@
+use crate::{bitboards, board::Board, piece, precomputed_data::{self, square_data}};
+
+use super::{mobility, pawn_structure, tapered_score::TaperedScore};
+
+// constants
+/// Bonus for every friendly pawn right in front of the king
+pub(super) const PAWN_SHIELD_CLOSE_BONUS: TaperedScore = TaperedScore::new(12, 0);
+/// Bonus for every friendly pawn two ranks in front of the king
+pub(super) const PAWN_SHIELD_FAR_BONUS: TaperedScore = TaperedScore::new(6, 0);
+/// Penalty for every file next to the king without friendly pawns
+pub(super) const SEMI_OPEN_FILE_PENALTY: TaperedScore = TaperedScore::new(-15, 0);
+/// Penalty for every file next to the king without any pawns
+pub(super) const OPEN_FILE_PENALTY: TaperedScore = TaperedScore::new(-25, 0);
+/// How dangerous an attack on a single square around the king is by piece type
+const KING_ATTACK_WEIGHTS: [i32; 7] = [0, 0, 0, 2, 2, 3, 5];
+const MAX_KING_ATTACK_PENALTY: i32 = 500;
+
+/// Scores the pawn shield, open files and enemy attacks around the kings. Positive if the white king is safer
+pub fn evaluate_king_safety(board: &Board) -> TaperedScore {
+    evaluate_color(board, piece::WHITE) - evaluate_color(board, piece::BLACK)
+}
+
+fn evaluate_color(board: &Board, color: usize) -> TaperedScore {
+    let king_square = board.get_king_square(color);
+    let king_file = square_data::get_file_from_square(king_square);
+    let king_rank = square_data::get_rank_from_square(king_square);
+    let pawn_bb = board.get_piece_bitboard(color, piece::PAWN);
+    let all_pawns_bb = pawn_bb | board.get_piece_bitboard(1 - color, piece::PAWN);
+    let king_files_mask = pawn_structure::get_file_mask(king_file) | pawn_structure::get_adjacent_files_mask(king_file);
+    let mut score = TaperedScore::ZERO;
+
+    if pawn_structure::get_relative_rank(color, king_square) < 6 {
+        let close_rank = if color == piece::WHITE { king_rank + 1 } else { king_rank - 1 };
+        let far_rank = if color == piece::WHITE { king_rank + 2 } else { king_rank - 2 };
+        let close_shield = king_files_mask & (bitboards::FIRST_RANK_MASK << (close_rank * 8));
+        let far_shield = king_files_mask & (bitboards::FIRST_RANK_MASK << (far_rank * 8));
+
+        score += PAWN_SHIELD_CLOSE_BONUS * (pawn_bb & close_shield).count_ones() as i32;
+        score += PAWN_SHIELD_FAR_BONUS * (pawn_bb & far_shield).count_ones() as i32;
+    }
+
+    let first_file = king_file.saturating_sub(1);
+    let last_file = (king_file + 1).min(7);
+    for file in first_file..=last_file {
+        let file_mask = pawn_structure::get_file_mask(file);
+        if all_pawns_bb & file_mask == 0 {
+            score += OPEN_FILE_PENALTY;
+        } else if pawn_bb & file_mask == 0 {
+            score += SEMI_OPEN_FILE_PENALTY;
+        }
+    }
+
+    score.mg -= get_king_attack_penalty(board, color);
+
+    score
+}
+
+/// Middlegame penalty for the enemy pieces attacking the squares around the king of the provided color
+///
+/// Grows with the square of the attack, so unlike the other terms it isn't a plain sum of weights
+pub fn get_king_attack_penalty(board: &Board, color: usize) -> i32 {
+    let king_square = board.get_king_square(color);
+    let king_zone = precomputed_data::SQUARE_DATA.get_bb_for_king(king_square) | bitboards::get_bit_from_square(king_square);
+    let all_occ = board.get_all_occupied_squares();
+    let mut attackers = 0;
+    let mut attack_units = 0;
+
+    for (piece_type, attack_weight) in KING_ATTACK_WEIGHTS.iter().enumerate().skip(piece::KNIGHT) {
+        let mut piece_bb = board.get_piece_bitboard(1 - color, piece_type);
+
+        while piece_bb != 0 {
+            let square = bitboards::get_ls1b(piece_bb);
+            piece_bb &= piece_bb - 1;
+
+            let attacked_zone = mobility::get_piece_attacks(piece_type, square, all_occ) & king_zone;
+            if attacked_zone != 0 {
+                attackers += 1;
+                attack_units += attack_weight * attacked_zone.count_ones() as i32;
+            }
+        }
+    }
+
+    // a single attacker can hardly make a mating attack
+    if attackers > 1 {
+        (attack_units * attack_units / 4).min(MAX_KING_ATTACK_PENALTY)
+    } else {
+        0
+    }
+}
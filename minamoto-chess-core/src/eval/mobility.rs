@@ -0,0 +1,58 @@
+use crate::{bitboards, board::Board, piece, precomputed_data};
+
+use super::{pawn_structure, tapered_score::TaperedScore};
+
+// constants
+/// Bonus for every available square by piece type
+pub(super) const MOBILITY_WEIGHTS: [TaperedScore; 7] = [
+    TaperedScore::new(0, 0),
+    TaperedScore::new(0, 0),
+    TaperedScore::new(0, 0),
+    TaperedScore::new(4, 4),
+    TaperedScore::new(5, 5),
+    TaperedScore::new(2, 4),
+    TaperedScore::new(1, 2),
+];
+/// Amount of available squares considered normal for a piece type. 
+/// Pieces with less squares get penalized
+pub(super) const MOBILITY_OFFSETS: [i32; 7] = [0, 0, 0, 4, 6, 7, 13];
+
+/// Returns every square the provided piece attacks on the board with the provided occupancy
+pub fn get_piece_attacks(piece_type: usize, square: usize, all_occ: u64) -> u64 {
+    match piece_type {
+        piece::KNIGHT => precomputed_data::SQUARE_DATA.get_bb_for_knight(square),
+        piece::BISHOP => precomputed_data::BISHOP_MAGIC_LOOKUP_TABLE.get_pseudo_legal_move_bb(square, all_occ),
+        piece::ROOK => precomputed_data::ROOK_MAGIC_LOOKUP_TABLE.get_pseudo_legal_move_bb(square, all_occ),
+        piece::QUEEN => precomputed_data::ROOK_MAGIC_LOOKUP_TABLE.get_pseudo_legal_move_bb(square, all_occ)
+            | precomputed_data::BISHOP_MAGIC_LOOKUP_TABLE.get_pseudo_legal_move_bb(square, all_occ),
+        piece::KING => precomputed_data::SQUARE_DATA.get_bb_for_king(square),
+        _ => panic!("Invalid piece type: {}", piece_type),
+    }
+}
+
+/// Scores the amount of squares the pieces can safely go to. Positive if white is more mobile
+pub fn evaluate_mobility(board: &Board) -> TaperedScore {
+    evaluate_color(board, piece::WHITE) - evaluate_color(board, piece::BLACK)
+}
+
+fn evaluate_color(board: &Board, color: usize) -> TaperedScore {
+    let all_occ = board.get_all_occupied_squares();
+    // squares attacked by enemy pawns are not counted since going there just loses the piece
+    let safe_squares = !board.get_all_occupied_squares_for_color(color) 
+        & !pawn_structure::get_pawn_attacks(board.get_piece_bitboard(1 - color, piece::PAWN), 1 - color);
+    let mut score = TaperedScore::ZERO;
+
+    for piece_type in piece::KNIGHT..=piece::QUEEN {
+        let mut piece_bb = board.get_piece_bitboard(color, piece_type);
+
+        while piece_bb != 0 {
+            let square = bitboards::get_ls1b(piece_bb);
+            piece_bb &= piece_bb - 1;
+
+            let mobility = (get_piece_attacks(piece_type, square, all_occ) & safe_squares).count_ones() as i32;
+            score += MOBILITY_WEIGHTS[piece_type] * (mobility - MOBILITY_OFFSETS[piece_type]);
+        }
+    }
+
+    score
+}
@@ -0,0 +1,110 @@
+pub mod tapered_score;
+pub mod piece_square_tables;
+pub mod pawn_structure;
+pub mod mobility;
+pub mod king_safety;
+pub mod eval_state;
+pub mod nnue;
+pub mod tuning;
+
+use crate::{board::Board, piece};
+
+use eval_state::EvalState;
+use tapered_score::TaperedScore;
+
+// constants
+/// Game phase with every piece on the board. Goes down to 0 as the pieces get traded
+pub const MAX_PHASE: i32 = 24;
+/// How much every piece type contributes to the game phase
+const PHASE_WEIGHTS: [i32; 7] = [0, 0, 0, 1, 1, 2, 4];
+
+pub(super) const PIECE_VALUES: [TaperedScore; 7] = [
+    TaperedScore::new(0, 0),
+    TaperedScore::new(0, 0),
+    TaperedScore::new(100, 120),
+    TaperedScore::new(320, 300),
+    TaperedScore::new(330, 320),
+    TaperedScore::new(500, 520),
+    TaperedScore::new(900, 920),
+];
+
+pub(super) const BISHOP_PAIR_BONUS: TaperedScore = TaperedScore::new(30, 50);
+
+/// Returns the middlegame and endgame values of the provided piece type
+pub fn get_piece_value_tapered(piece_type: usize) -> TaperedScore {
+    debug_assert!(piece_type < 7, "Piece type is out of bounds");
+    unsafe {
+        *PIECE_VALUES.get_unchecked(piece_type)
+    }
+}
+
+/// Returns the middlegame value of the provided piece type in centipawns
+/// 
+/// Used where a single rough value is enough, like move ordering or pruning margins
+pub fn get_piece_value(piece_type: usize) -> i32 {
+    get_piece_value_tapered(piece_type).mg
+}
+
+pub fn get_phase_weight(piece_type: usize) -> i32 {
+    debug_assert!(piece_type < 7, "Piece type is out of bounds");
+    unsafe {
+        *PHASE_WEIGHTS.get_unchecked(piece_type)
+    }
+}
+
+/// Returns the material, piece-square tables and game phase of the position
+/// 
+/// Taken from the board when the *incremental_eval* feature is enabled, calculated from scratch otherwise
+pub fn get_eval_state(board: &Board) -> EvalState {
+    #[cfg(feature = "incremental_eval")]
+    {
+        *board.get_eval_state()
+    }
+    #[cfg(not(feature = "incremental_eval"))]
+    {
+        EvalState::from_board(board)
+    }
+}
+
+/// Returns the game phase from 0 (pawn endgame) to *MAX_PHASE* (opening)
+pub fn get_game_phase(board: &Board) -> i32 {
+    get_eval_state(board).get_phase()
+}
+
+fn evaluate_bishop_pair(board: &Board) -> TaperedScore {
+    let mut score = TaperedScore::ZERO;
+
+    if board.get_piece_bitboard(piece::WHITE, piece::BISHOP).count_ones() > 1 {
+        score += BISHOP_PAIR_BONUS;
+    }
+    if board.get_piece_bitboard(piece::BLACK, piece::BISHOP).count_ones() > 1 {
+        score -= BISHOP_PAIR_BONUS;
+    }
+
+    score
+}
+
+/// Static evaluation of the position in centipawns from the perspective of the side to move
+///
+/// Uses the NNUE when the board has a network (with the *nnue* feature), the handcrafted evaluation otherwise
+pub fn evaluate(board: &Board) -> i32 {
+    #[cfg(feature = "nnue")]
+    if let Some(accumulator) = board.get_accumulator() {
+        return accumulator.evaluate(board.get_current_color());
+    }
+
+    let eval_state = get_eval_state(board);
+    let score = eval_state.get_material_and_psqt()
+        + evaluate_bishop_pair(board)
+        + pawn_structure::evaluate_pawn_structure(board)
+        + mobility::evaluate_mobility(board)
+        + king_safety::evaluate_king_safety(board);
+
+    let white_score = score.taper(eval_state.get_phase());
+
+    if board.is_white_to_move() {
+        white_score
+    } else {
+        -white_score
+    }
+}
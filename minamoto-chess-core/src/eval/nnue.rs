@@ -0,0 +1,416 @@
+/*Efficiently updatable neural network evaluation with a single hidden layer:
+(768 inputs -> N hidden) for each perspective -> 1 output.
+
+The inputs are one feature per piece type, color and square, seen from the perspective of each side
+(the black perspective mirrors the board vertically and swaps the colors). The hidden layer is the sum
+of the weights of the active features, so it only changes by a few rows when a piece moves.
+That sum (the accumulator) is kept updated by the board, so the evaluation only runs the output layer.
+
+Network file format, every number is little endian:
+    4 bytes      magic "MNNU"
+    u32          version
+    u32          hidden layer size N
+    i16[768][N]  feature weights quantized by QA
+    i16[N]       feature biases quantized by QA
+    i16[2N]      output weights quantized by QB, side to move first
+    i32          output bias quantized by QA * QB
+
+Feature index is color * 384 + piece * 64 + square with the color relative to the perspective
+(0 is the own pieces), pieces ordered pawn, knight, bishop, rook, queen, king and squares going
+from a1 (0) to h8 (63), which is the layout most trainers use*/
+
+use std::{fmt, sync::Arc};
+
+use crate::{bitboards, board::Board, piece};
+
+// constants
+pub const INPUT_SIZE: usize = 768;
+/// Quantization of the first layer, the clipped ReLU clamps the accumulator to *0..=QA*
+pub const QA: i32 = 255;
+/// Quantization of the output weights
+pub const QB: i32 = 64;
+/// Output of the network multiplied by this gives centipawns
+pub const SCALE: i32 = 400;
+/// Big networks are way too slow for the search and the WASM memory anyway
+pub const MAX_HIDDEN_SIZE: usize = 4096;
+/// Keeps the evaluation away from the mate scores no matter what the network outputs
+const MAX_EVALUATION: i32 = 20_000;
+
+const MAGIC: &[u8; 4] = b"MNNU";
+const VERSION: u32 = 1;
+const HEADER_SIZE: usize = 12;
+const FEATURES_PER_COLOR: usize = 384;
+/// Index of every piece type of this crate in the feature layout (pawn, knight, bishop, rook, queen, king)
+const FEATURE_PIECE_INDICES: [usize; 7] = [usize::MAX, 5, 0, 1, 2, 3, 4];
+
+/// Returns the index of the input feature for the piece seen from the provided perspective
+pub fn get_feature_index(perspective: usize, color: usize, piece_type: usize, square: usize) -> usize {
+    debug_assert!(piece_type != piece::NONE && piece_type < 7, "Piece type is out of bounds");
+    debug_assert!(square < 64, "Square is out of bounds");
+
+    let relative_color = (color != perspective) as usize;
+    // the squares of this crate go from h1 to a8, the features from a1 to h8
+    let feature_square = square ^ 7;
+    let relative_square = if perspective == piece::WHITE { feature_square } else { feature_square ^ 56 };
+
+    relative_color * FEATURES_PER_COLOR + FEATURE_PIECE_INDICES[piece_type] * 64 + relative_square
+}
+
+#[derive(Debug)]
+pub enum NetworkError {
+    Io(std::io::Error),
+    InvalidMagic,
+    UnsupportedVersion(u32),
+    InvalidHiddenSize(usize),
+    /// The amount of bytes doesn't match the hidden layer size from the header
+    InvalidSize { expected: usize, actual: usize },
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkError::Io(error) => write!(f, "Failed to read the network: {}", error),
+            NetworkError::InvalidMagic => write!(f, "Not a network file"),
+            NetworkError::UnsupportedVersion(version) => write!(f, "Unsupported network version: {}", version),
+            NetworkError::InvalidHiddenSize(size) => write!(f, "Invalid hidden layer size: {} (max {})", size, MAX_HIDDEN_SIZE),
+            NetworkError::InvalidSize { expected, actual } => write!(f, "Invalid network size: expected {} bytes, got {}", expected, actual),
+        }
+    }
+}
+
+impl std::error::Error for NetworkError {}
+
+impl From<std::io::Error> for NetworkError {
+    fn from(error: std::io::Error) -> Self {
+        NetworkError::Io(error)
+    }
+}
+
+fn read_i16s(bytes: &[u8], count: usize) -> (Vec<i16>, &[u8]) {
+    let (values, rest) = bytes.split_at(count * 2);
+    let values = values.chunks_exact(2)
+        .map(|chunk| i16::from_le_bytes([chunk[0], chunk[1]]))
+        .collect();
+
+    (values, rest)
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// Clipped ReLU of the quantized accumulator
+fn crelu(value: i16) -> i64 {
+    (value as i64).clamp(0, QA as i64)
+}
+
+/// Quantized weights of the network, shared between every board using it
+pub struct Network {
+    hidden_size: usize,
+    /// *hidden_size* weights for every input feature
+    feature_weights: Vec<i16>,
+    feature_biases: Vec<i16>,
+    /// Weights of the side to move accumulator followed by the weights of the opponent one
+    output_weights: Vec<i16>,
+    output_bias: i32,
+}
+
+impl Network {
+    /// Parses the network from the bytes of a network file
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, NetworkError> {
+        if bytes.len() < HEADER_SIZE || &bytes[..4] != MAGIC {
+            return Err(NetworkError::InvalidMagic);
+        }
+
+        let version = read_u32(&bytes[4..]);
+        if version != VERSION {
+            return Err(NetworkError::UnsupportedVersion(version));
+        }
+
+        let hidden_size = read_u32(&bytes[8..]) as usize;
+        if hidden_size == 0 || hidden_size > MAX_HIDDEN_SIZE {
+            return Err(NetworkError::InvalidHiddenSize(hidden_size));
+        }
+
+        let expected = HEADER_SIZE + (INPUT_SIZE * hidden_size + hidden_size + 2 * hidden_size) * 2 + 4;
+        if bytes.len() != expected {
+            return Err(NetworkError::InvalidSize { expected, actual: bytes.len() });
+        }
+
+        let (feature_weights, rest) = read_i16s(&bytes[HEADER_SIZE..], INPUT_SIZE * hidden_size);
+        let (feature_biases, rest) = read_i16s(rest, hidden_size);
+        let (output_weights, rest) = read_i16s(rest, 2 * hidden_size);
+        let output_bias = i32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]);
+
+        Ok(Self {
+            hidden_size,
+            feature_weights,
+            feature_biases,
+            output_weights,
+            output_bias,
+        })
+    }
+
+    /// Reads the network from a file. Not available in the browser, where the bytes come from JS
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self, NetworkError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    pub fn get_hidden_size(&self) -> usize {
+        self.hidden_size
+    }
+
+    fn get_feature_weights(&self, feature_index: usize) -> &[i16] {
+        let start = feature_index * self.hidden_size;
+        &self.feature_weights[start..start + self.hidden_size]
+    }
+
+    /// Reference implementation evaluating the position from scratch with the weights
+    /// converted back to floats, so the quantized evaluation can be checked against it
+    ///
+    /// Returns centipawns from the perspective of the side to move, without rounding or clamping
+    pub fn evaluate_float(&self, board: &Board) -> f32 {
+        let qa = QA as f32;
+        let qb = QB as f32;
+        let mut accumulators = [Vec::new(), Vec::new()];
+
+        for (perspective, accumulator) in accumulators.iter_mut().enumerate() {
+            *accumulator = self.feature_biases.iter().map(|&bias| bias as f32 / qa).collect::<Vec<f32>>();
+
+            for_each_piece(board, |color, piece_type, square| {
+                let weights = self.get_feature_weights(get_feature_index(perspective, color, piece_type, square));
+                for (value, &weight) in accumulator.iter_mut().zip(weights) {
+                    *value += weight as f32 / qa;
+                }
+            });
+        }
+
+        let color = board.get_current_color();
+        let (us_weights, them_weights) = self.output_weights.split_at(self.hidden_size);
+        let activate = |accumulator: &[f32], weights: &[i16]| -> f32 {
+            accumulator.iter().zip(weights)
+                .map(|(&value, &weight)| value.clamp(0.0, 1.0) * weight as f32 / qb)
+                .sum()
+        };
+
+        let output = activate(&accumulators[color], us_weights)
+            + activate(&accumulators[1 - color], them_weights)
+            + self.output_bias as f32 / (qa * qb);
+
+        output * SCALE as f32
+    }
+}
+
+fn for_each_piece<F: FnMut(usize, usize, usize)>(board: &Board, mut f: F) {
+    for color in [piece::WHITE, piece::BLACK] {
+        for piece_type in piece::KING..=piece::QUEEN {
+            let mut piece_bb = board.get_piece_bitboard(color, piece_type);
+
+            while piece_bb != 0 {
+                let square = bitboards::get_ls1b(piece_bb);
+                piece_bb &= piece_bb - 1;
+
+                f(color, piece_type, square);
+            }
+        }
+    }
+}
+
+/// Hidden layer of the network for both perspectives
+///
+/// The board keeps it updated on every piece creation and deletion (with the *nnue* feature).
+/// Adding and removing the same piece cancel out exactly, so undoing a move restores it without a copy
+#[derive(Clone)]
+pub struct Accumulator {
+    network: Arc<Network>,
+    /// Indexed by perspective
+    values: [Vec<i16>; 2],
+}
+
+impl Accumulator {
+    /// Creates the accumulator of an empty board
+    pub fn new(network: Arc<Network>) -> Self {
+        let values = [network.feature_biases.clone(), network.feature_biases.clone()];
+
+        Self { network, values }
+    }
+
+    /// Calculates the accumulator from scratch
+    pub fn from_board(network: Arc<Network>, board: &Board) -> Self {
+        let mut accumulator = Self::new(network);
+        for_each_piece(board, |color, piece_type, square| accumulator.add_piece(color, piece_type, square));
+
+        accumulator
+    }
+
+    pub fn get_network(&self) -> &Arc<Network> {
+        &self.network
+    }
+
+    pub fn add_piece(&mut self, color: usize, piece_type: usize, square: usize) {
+        for perspective in [piece::WHITE, piece::BLACK] {
+            let weights = self.network.get_feature_weights(get_feature_index(perspective, color, piece_type, square));
+            for (value, &weight) in self.values[perspective].iter_mut().zip(weights) {
+                *value = value.wrapping_add(weight);
+            }
+        }
+    }
+
+    pub fn remove_piece(&mut self, color: usize, piece_type: usize, square: usize) {
+        for perspective in [piece::WHITE, piece::BLACK] {
+            let weights = self.network.get_feature_weights(get_feature_index(perspective, color, piece_type, square));
+            for (value, &weight) in self.values[perspective].iter_mut().zip(weights) {
+                *value = value.wrapping_sub(weight);
+            }
+        }
+    }
+
+    /// Runs the output layer with integer arithmetic and returns centipawns from the perspective of the provided color
+    pub fn evaluate(&self, color_to_move: usize) -> i32 {
+        debug_assert!(color_to_move < 2, "Color is out of bounds");
+        let network = &self.network;
+        let (us_weights, them_weights) = network.output_weights.split_at(network.hidden_size);
+
+        let activate = |values: &[i16], weights: &[i16]| -> i64 {
+            values.iter().zip(weights)
+                .map(|(&value, &weight)| crelu(value) * weight as i64)
+                .sum()
+        };
+
+        let output = activate(&self.values[color_to_move], us_weights)
+            + activate(&self.values[1 - color_to_move], them_weights)
+            + network.output_bias as i64;
+
+        (output * SCALE as i64 / (QA * QB) as i64).clamp(-MAX_EVALUATION as i64, MAX_EVALUATION as i64) as i32
+    }
+}
+
+impl PartialEq for Accumulator {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.network, &other.network) && self.values == other.values
+    }
+}
+
+#[cfg(all(test, feature = "nnue"))]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{move_generation::{attack_calculator::AttackCalculator, move_gen}, r#move::move_record::MoveRecord};
+
+    const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    /// The integer division of the quantized output truncates up to a centipawn, the float rounding adds next to nothing
+    const FLOAT_TOLERANCE: f32 = 1.5;
+
+    /// Xorshift, so the weights and the played moves are the same on every run
+    fn next_random(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    /// Writes a network file with random weights small enough to never saturate the accumulator
+    fn create_network(hidden_size: usize) -> Arc<Network> {
+        let mut state = 0x2545F4914F6CDD1D;
+        let mut next_value = |limit: i64| (next_random(&mut state) % (2 * limit as u64 + 1)) as i64 - limit;
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(VERSION.to_le_bytes());
+        bytes.extend((hidden_size as u32).to_le_bytes());
+        for _ in 0..INPUT_SIZE * hidden_size {
+            bytes.extend((next_value(60) as i16).to_le_bytes());
+        }
+        for _ in 0..hidden_size {
+            bytes.extend((next_value(100) as i16).to_le_bytes());
+        }
+        for _ in 0..2 * hidden_size {
+            bytes.extend((next_value(40) as i16).to_le_bytes());
+        }
+        bytes.extend((next_value(QA as i64 * QB as i64) as i32).to_le_bytes());
+
+        Arc::new(Network::from_bytes(&bytes).expect("Invalid test network"))
+    }
+
+    fn create_board(fen: &str, network: &Arc<Network>) -> Board {
+        let mut board = Board::from_fen(fen);
+        board.set_network(Some(Arc::clone(network)));
+
+        board
+    }
+
+    fn assert_evaluation(board: &Board) {
+        let accumulator = board.get_accumulator().expect("The board has no network");
+        assert!(*accumulator == Accumulator::from_board(Arc::clone(accumulator.get_network()), board),
+            "The incremental accumulator differs from the one calculated from scratch");
+
+        let evaluation = accumulator.evaluate(board.get_current_color()) as f32;
+        let reference = accumulator.get_network().evaluate_float(board);
+        assert!((evaluation - reference).abs() <= FLOAT_TOLERANCE,
+            "The quantized evaluation {} differs from the float reference {}", evaluation, reference);
+    }
+
+    #[test]
+    fn rejects_malformed_networks() {
+        let network = create_network(8);
+        assert!(matches!(Network::from_bytes(b"ABCD"), Err(NetworkError::InvalidMagic)));
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(VERSION.to_le_bytes());
+        bytes.extend(0u32.to_le_bytes());
+        assert!(matches!(Network::from_bytes(&bytes), Err(NetworkError::InvalidHiddenSize(0))));
+
+        bytes.truncate(8);
+        bytes.extend((network.get_hidden_size() as u32).to_le_bytes());
+        assert!(matches!(Network::from_bytes(&bytes), Err(NetworkError::InvalidSize { actual: HEADER_SIZE, .. })));
+    }
+
+    #[test]
+    fn special_moves_keep_the_accumulator_exact() {
+        let network = create_network(16);
+        let mut board = create_board("r3k2r/pPp2ppp/8/3pP3/8/8/PPP2PPP/R3K2R w KQkq d6 0 1", &network);
+        assert_evaluation(&board);
+
+        // en passant, castling on both sides, a capturing promotion and a recapture
+        let mut move_records: Vec<MoveRecord> = Vec::new();
+        for uci_move in ["e5d6", "e8g8", "b7a8q", "c7d6", "e1c1"] {
+            let mov = board.find_move(uci_move);
+            move_records.push(board.make_move(mov));
+            assert_evaluation(&board);
+        }
+
+        while let Some(move_record) = move_records.pop() {
+            board.undo_move(move_record);
+            assert_evaluation(&board);
+        }
+    }
+
+    #[test]
+    fn random_games_keep_the_accumulator_exact() {
+        let network = create_network(32);
+        let mut state = 0x9E3779B97F4A7C15;
+
+        for _ in 0..8 {
+            let mut board = create_board(START_FEN, &network);
+            let mut move_records: Vec<MoveRecord> = Vec::new();
+
+            for _ in 0..80 {
+                let mut moves = move_gen::create_empty_move_buffer();
+                move_gen::generate_moves(&mut moves, &board, &AttackCalculator::new(&board));
+                if moves.is_empty() {
+                    break;
+                }
+
+                let mov = moves[next_random(&mut state) as usize % moves.len()];
+                move_records.push(board.make_move(mov));
+                assert_evaluation(&board);
+            }
+
+            while let Some(move_record) = move_records.pop() {
+                board.undo_move(move_record);
+                assert_evaluation(&board);
+            }
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use crate::{bitboards, board::Board, piece, precomputed_data::square_data};
+
+use super::tapered_score::TaperedScore;
+
+// constants
+const H_FILE_MASK: u64 = 0x0101010101010101;
+
+pub(super) const DOUBLED_PAWN_PENALTY: TaperedScore = TaperedScore::new(-10, -20);
+pub(super) const ISOLATED_PAWN_PENALTY: TaperedScore = TaperedScore::new(-10, -15);
+/// Bonus for a passed pawn by its rank relative to its color (so for black the first rank is the eighth)
+pub(super) const PASSED_PAWN_BONUS: [TaperedScore; 8] = [
+    TaperedScore::new(0, 0),
+    TaperedScore::new(5, 10),
+    TaperedScore::new(10, 20),
+    TaperedScore::new(15, 35),
+    TaperedScore::new(25, 60),
+    TaperedScore::new(40, 90),
+    TaperedScore::new(60, 130),
+    TaperedScore::new(0, 0),
+];
+
+/// Returns bitboard with every square on the provided file (0 is the h file)
+pub fn get_file_mask(file: usize) -> u64 {
+    debug_assert!(file < 8, "File is out of bounds");
+    H_FILE_MASK << file
+}
+
+/// Returns bitboard with the files to the left and to the right of the provided one
+pub fn get_adjacent_files_mask(file: usize) -> u64 {
+    debug_assert!(file < 8, "File is out of bounds");
+    let mut mask = 0;
+    if file > 0 {
+        mask |= get_file_mask(file - 1);
+    }
+    if file < 7 {
+        mask |= get_file_mask(file + 1);
+    }
+
+    mask
+}
+
+/// Returns bitboard with every rank in front of the provided one from the perspective of the provided color
+pub fn get_forward_ranks_mask(color: usize, rank: usize) -> u64 {
+    debug_assert!(rank < 8, "Rank is out of bounds");
+    if color == piece::WHITE {
+        if rank == 7 { 0 } else { u64::MAX << ((rank + 1) * 8) }
+    } else {
+        (1u64 << (rank * 8)) - 1
+    }
+}
+
+/// Returns the rank of the square counted from the side of the provided color
+pub fn get_relative_rank(color: usize, square: usize) -> usize {
+    let rank = square_data::get_rank_from_square(square);
+    if color == piece::WHITE { rank } else { 7 - rank }
+}
+
+/// Returns every square attacked by the provided pawns
+pub fn get_pawn_attacks(pawn_bb: u64, color: usize) -> u64 {
+    if color == piece::WHITE {
+        ((pawn_bb & bitboards::NOT_FIRST_FILE_MASK) << 9) | ((pawn_bb & bitboards::NOT_EIGHTH_FILE_MASK) << 7)
+    } else {
+        ((pawn_bb & bitboards::NOT_FIRST_FILE_MASK) >> 7) | ((pawn_bb & bitboards::NOT_EIGHTH_FILE_MASK) >> 9)
+    }
+}
+
+/// Returns true if no enemy pawn can stop or capture the pawn on its way to promotion
+pub fn is_passed_pawn(color: usize, square: usize, opposite_pawn_bb: u64) -> bool {
+    let file = square_data::get_file_from_square(square);
+    let rank = square_data::get_rank_from_square(square);
+    let front_span = (get_file_mask(file) | get_adjacent_files_mask(file)) & get_forward_ranks_mask(color, rank);
+
+    front_span & opposite_pawn_bb == 0
+}
+
+/// Scores doubled, isolated and passed pawns. Positive if white has the better structure
+pub fn evaluate_pawn_structure(board: &Board) -> TaperedScore {
+    evaluate_color(board, piece::WHITE) - evaluate_color(board, piece::BLACK)
+}
+
+fn evaluate_color(board: &Board, color: usize) -> TaperedScore {
+    let pawn_bb = board.get_piece_bitboard(color, piece::PAWN);
+    let opposite_pawn_bb = board.get_piece_bitboard(1 - color, piece::PAWN);
+    let mut score = TaperedScore::ZERO;
+
+    for file in 0..8 {
+        let pawns_on_file = (pawn_bb & get_file_mask(file)).count_ones() as i32;
+        if pawns_on_file == 0 {
+            continue;
+        }
+
+        if pawns_on_file > 1 {
+            score += DOUBLED_PAWN_PENALTY * (pawns_on_file - 1);
+        }
+
+        if pawn_bb & get_adjacent_files_mask(file) == 0 {
+            score += ISOLATED_PAWN_PENALTY * pawns_on_file;
+        }
+    }
+
+    let mut pawns = pawn_bb;
+    while pawns != 0 {
+        let square = bitboards::get_ls1b(pawns);
+        pawns &= pawns - 1;
+
+        if is_passed_pawn(color, square, opposite_pawn_bb) {
+            score += PASSED_PAWN_BONUS[get_relative_rank(color, square)];
+        }
+    }
+
+    score
+}
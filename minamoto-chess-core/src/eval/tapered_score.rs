@@ -0,0 +1,70 @@
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+/// Pair of middlegame and endgame scores which get interpolated by the game phase
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct TaperedScore {
+    pub mg: i32,
+    pub eg: i32,
+}
+
+impl TaperedScore {
+    pub const ZERO: TaperedScore = TaperedScore { mg: 0, eg: 0 };
+
+    pub const fn new(mg: i32, eg: i32) -> Self {
+        Self { mg, eg }
+    }
+
+    /// Interpolates between the middlegame and the endgame scores
+    /// 
+    /// *phase* goes from 0 (only kings and pawns left) to *MAX_PHASE* (every piece is on the board)
+    pub fn taper(&self, phase: i32) -> i32 {
+        debug_assert!((0..=super::MAX_PHASE).contains(&phase), "Phase is out of bounds");
+        (self.mg * phase + self.eg * (super::MAX_PHASE - phase)) / super::MAX_PHASE
+    }
+}
+
+impl Add for TaperedScore {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.mg + other.mg, self.eg + other.eg)
+    }
+}
+
+impl Sub for TaperedScore {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.mg - other.mg, self.eg - other.eg)
+    }
+}
+
+impl Neg for TaperedScore {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(-self.mg, -self.eg)
+    }
+}
+
+impl Mul<i32> for TaperedScore {
+    type Output = Self;
+
+    fn mul(self, multiplier: i32) -> Self {
+        Self::new(self.mg * multiplier, self.eg * multiplier)
+    }
+}
+
+impl AddAssign for TaperedScore {
+    fn add_assign(&mut self, other: Self) {
+        self.mg += other.mg;
+        self.eg += other.eg;
+    }
+}
+
+impl SubAssign for TaperedScore {
+    fn sub_assign(&mut self, other: Self) {
+        self.mg -= other.mg;
+        self.eg -= other.eg;
+    }
+}
@@ -0,0 +1,274 @@
+/*Texel tuning of the handcrafted evaluation weights.
+
+Every position labelled with the result of its game gets resolved by a small quiescence search first,
+so the weights are fitted to the quiet position the search would actually evaluate. The evaluation is
+mapped to an expected result with a sigmoid, and the weights are moved with gradient descent (Adam) to
+minimize the squared difference between the expected and the real results over all positions.
+
+Before tuning, the scale of the sigmoid is fitted to the current weights, otherwise the tuner would rather
+stretch every weight than fix the wrong ones*/
+
+pub mod params;
+pub mod trace;
+
+use crate::{board::Board, r#move::Move, move_generation::{attack_calculator::AttackCalculator, move_gen}, piece, search::see};
+
+use params::EvalParams;
+use trace::EvalTrace;
+
+use super::tapered_score::TaperedScore;
+
+// constants
+/// How deep the quiescence search goes when resolving a position, the rare longer exchanges are cut off
+const MAX_QUIESCENCE_PLY: usize = 8;
+/// Range the scale of the sigmoid is looked for in
+const MIN_SCALING: f64 = 0.1;
+const MAX_SCALING: f64 = 4.0;
+const SCALING_SEARCH_ITERATIONS: usize = 60;
+const ADAM_BETA1: f64 = 0.9;
+const ADAM_BETA2: f64 = 0.999;
+const ADAM_EPSILON: f64 = 1e-8;
+
+/// Position resolved to a quiet one with the result of its game from white's perspective
+struct TuningPosition {
+    trace: EvalTrace,
+    result: f64,
+}
+
+pub struct Tuner {
+    positions: Vec<TuningPosition>,
+    /// [mg, eg] pair of every parameter
+    params: Vec<[f64; 2]>,
+    /// Scale of the sigmoid mapping centipawns to the expected result
+    scaling: f64,
+    first_moments: Vec<[f64; 2]>,
+    second_moments: Vec<[f64; 2]>,
+    epoch: i32,
+}
+
+impl Tuner {
+    /// Starts the tuning from the provided weights
+    pub fn new(params: &EvalParams) -> Self {
+        let params: Vec<[f64; 2]> = params.to_vec().iter().map(|score| [score.mg as f64, score.eg as f64]).collect();
+
+        Self {
+            positions: Vec::new(),
+            first_moments: vec![[0.0; 2]; params.len()],
+            second_moments: vec![[0.0; 2]; params.len()],
+            params,
+            scaling: 1.0,
+            epoch: 0,
+        }
+    }
+
+    /// Adds the position with the result of its game (1 if white won, 0.5 for a draw, 0 if black won)
+    ///
+    /// Returns false if the position is skipped, which happens when the side to move is in check or has no moves
+    pub fn add_position(&mut self, board: &Board, result: f64) -> bool {
+        let attack_calc = AttackCalculator::new(board);
+        let mut moves = move_gen::create_empty_move_buffer();
+        move_gen::generate_moves(&mut moves, board, &attack_calc);
+
+        if attack_calc.in_check() || moves.is_empty() {
+            return false;
+        }
+
+        let mut quiet_board = board.clone();
+        let mut line = Vec::new();
+        quiescence(&mut quiet_board, -i32::MAX, i32::MAX, 0, &mut line);
+        for mov in line {
+            quiet_board.make_move(mov);
+        }
+
+        self.positions.push(TuningPosition {
+            trace: EvalTrace::from_board(&quiet_board),
+            result: result.clamp(0.0, 1.0),
+        });
+        true
+    }
+
+    pub fn get_position_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn get_scaling(&self) -> f64 {
+        self.scaling
+    }
+
+    pub fn get_epoch(&self) -> i32 {
+        self.epoch
+    }
+
+    /// Finds the scale of the sigmoid which fits the current weights best, returns it
+    pub fn fit_scaling(&mut self) -> f64 {
+        let (mut low, mut high) = (MIN_SCALING, MAX_SCALING);
+
+        // the error is convex in the scale, so a ternary search is enough
+        for _ in 0..SCALING_SEARCH_ITERATIONS {
+            let left = low + (high - low) / 3.0;
+            let right = high - (high - low) / 3.0;
+
+            if self.get_error_with_scaling(left) < self.get_error_with_scaling(right) {
+                high = right;
+            } else {
+                low = left;
+            }
+        }
+
+        self.scaling = (low + high) / 2.0;
+        self.scaling
+    }
+
+    /// Mean squared error of the current weights over all positions
+    pub fn get_error(&self) -> f64 {
+        self.get_error_with_scaling(self.scaling)
+    }
+
+    /// Moves every weight once against the gradient of the error, returns the new error
+    pub fn run_epoch(&mut self, learning_rate: f64) -> f64 {
+        if self.positions.is_empty() {
+            return 0.0;
+        }
+
+        let mut gradients = vec![[0.0; 2]; self.params.len()];
+        let sigmoid_slope = self.scaling * std::f64::consts::LN_10 / 400.0;
+
+        for position in &self.positions {
+            let expected = sigmoid(position.trace.evaluate(&self.params), self.scaling);
+            let error_slope = (expected - position.result) * expected * (1.0 - expected) * sigmoid_slope;
+            let mg_share = position.trace.get_phase() as f64 / super::MAX_PHASE as f64;
+
+            for &(index, count) in position.trace.get_coefficients() {
+                let gradient = &mut gradients[index as usize];
+                gradient[0] += error_slope * count as f64 * mg_share;
+                gradient[1] += error_slope * count as f64 * (1.0 - mg_share);
+            }
+        }
+
+        self.epoch += 1;
+        let first_correction = 1.0 - ADAM_BETA1.powi(self.epoch);
+        let second_correction = 1.0 - ADAM_BETA2.powi(self.epoch);
+        let position_count = self.positions.len() as f64;
+
+        for (index, gradient) in gradients.iter().enumerate() {
+            for phase in 0..2 {
+                let gradient = 2.0 * gradient[phase] / position_count;
+                let first_moment = &mut self.first_moments[index][phase];
+                let second_moment = &mut self.second_moments[index][phase];
+
+                *first_moment = ADAM_BETA1 * *first_moment + (1.0 - ADAM_BETA1) * gradient;
+                *second_moment = ADAM_BETA2 * *second_moment + (1.0 - ADAM_BETA2) * gradient * gradient;
+
+                let step = (*first_moment / first_correction) / ((*second_moment / second_correction).sqrt() + ADAM_EPSILON);
+                self.params[index][phase] -= learning_rate * step;
+            }
+        }
+
+        self.get_error()
+    }
+
+    /// Current weights rounded to whole centipawns
+    pub fn get_params(&self) -> EvalParams {
+        let params: Vec<TaperedScore> = self.params.iter()
+            .map(|[mg, eg]| TaperedScore::new(mg.round() as i32, eg.round() as i32))
+            .collect();
+
+        EvalParams::from_slice(&params)
+    }
+
+    fn get_error_with_scaling(&self, scaling: f64) -> f64 {
+        if self.positions.is_empty() {
+            return 0.0;
+        }
+
+        let total_error: f64 = self.positions.iter()
+            .map(|position| (position.result - sigmoid(position.trace.evaluate(&self.params), scaling)).powi(2))
+            .sum();
+
+        total_error / self.positions.len() as f64
+    }
+}
+
+/// Expected result (from 0 to 1) of a position evaluated with the provided centipawns
+fn sigmoid(score: f64, scaling: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-scaling * score / 400.0))
+}
+
+/// Converts a game result label to the score of white. Accepts the PGN results ("1-0", "1/2-1/2", "0-1")
+/// and plain numbers from 0 to 1 ("1.0", "0.5", "0")
+pub fn parse_result(label: &str) -> Option<f64> {
+    match label {
+        "1-0" => Some(1.0),
+        "0-1" => Some(0.0),
+        "1/2-1/2" | "1/2" => Some(0.5),
+        _ => label.parse::<f64>().ok().filter(|result| (0.0..=1.0).contains(result)),
+    }
+}
+
+/// Splits a line of a labelled positions file into the FEN and the result. Accepts the result
+/// in square brackets ("<fen> [0.5]"), as the EPD c9 opcode ("<fen> c9 \"1-0\";") or as the last word ("<fen> 1-0")
+pub fn parse_labelled_position(line: &str) -> Option<(&str, f64)> {
+    let line = line.trim();
+
+    let (fen, label) = if let Some(start) = line.rfind('[') {
+        (&line[..start], line[start + 1..].trim_end_matches(']'))
+    } else if let Some(start) = line.find("c9 ") {
+        (&line[..start], line[start + 3..].trim_end_matches(';').trim_matches('"'))
+    } else {
+        line.rsplit_once(char::is_whitespace)?
+    };
+
+    let fen = fen.trim().trim_end_matches(';').trim();
+    if fen.is_empty() {
+        return None;
+    }
+
+    Some((fen, parse_result(label.trim())?))
+}
+
+/// Fail-hard quiescence search through the winning and equal captures and the promotions,
+/// fills the line leading to the position it evaluated
+fn quiescence(board: &mut Board, mut alpha: i32, beta: i32, ply: usize, line: &mut Vec<Move>) -> i32 {
+    line.clear();
+
+    let stand_pat = super::evaluate(board);
+    if stand_pat >= beta {
+        return beta;
+    }
+    alpha = alpha.max(stand_pat);
+
+    if ply >= MAX_QUIESCENCE_PLY {
+        return alpha;
+    }
+
+    let attack_calc = AttackCalculator::new(board);
+    let mut moves = move_gen::create_empty_move_buffer();
+    move_gen::generate_moves(&mut moves, board, &attack_calc);
+
+    let mut child_line = Vec::new();
+    for mov in moves {
+        let (_, captured_piece_type) = board.get_piece_on_square(mov.capture_square);
+        let is_capture = captured_piece_type != piece::NONE;
+
+        if !(is_capture || mov.is_promotion()) || (is_capture && !see::see_ge(board, &mov, 0)) {
+            continue;
+        }
+
+        let move_record = board.make_move(mov);
+        let score = -quiescence(board, -beta, -alpha, ply + 1, &mut child_line);
+        board.undo_move(move_record);
+
+        if score > alpha {
+            alpha = score;
+            line.clear();
+            line.push(mov);
+            line.extend_from_slice(&child_line);
+
+            if alpha >= beta {
+                break;
+            }
+        }
+    }
+
+    alpha
+}
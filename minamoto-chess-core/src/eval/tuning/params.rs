@@ -0,0 +1,199 @@
+use std::fmt::Write;
+
+use crate::piece;
+
+use super::super::{king_safety, mobility, pawn_structure, piece_square_tables, tapered_score::TaperedScore};
+
+// constants
+/// Positions of every group of parameters in the flat parameter list
+pub(super) const PIECE_VALUES_OFFSET: usize = 0;
+pub(super) const PSQT_OFFSET: usize = PIECE_VALUES_OFFSET + 7;
+pub(super) const BISHOP_PAIR_INDEX: usize = PSQT_OFFSET + 7 * 64;
+pub(super) const DOUBLED_PAWN_INDEX: usize = BISHOP_PAIR_INDEX + 1;
+pub(super) const ISOLATED_PAWN_INDEX: usize = DOUBLED_PAWN_INDEX + 1;
+pub(super) const PASSED_PAWN_OFFSET: usize = ISOLATED_PAWN_INDEX + 1;
+pub(super) const MOBILITY_OFFSET: usize = PASSED_PAWN_OFFSET + 8;
+pub(super) const PAWN_SHIELD_CLOSE_INDEX: usize = MOBILITY_OFFSET + 7;
+pub(super) const PAWN_SHIELD_FAR_INDEX: usize = PAWN_SHIELD_CLOSE_INDEX + 1;
+pub(super) const SEMI_OPEN_FILE_INDEX: usize = PAWN_SHIELD_FAR_INDEX + 1;
+pub(super) const OPEN_FILE_INDEX: usize = SEMI_OPEN_FILE_INDEX + 1;
+pub const PARAM_COUNT: usize = OPEN_FILE_INDEX + 1;
+
+/// Names of the piece-square tables in the source by piece type
+const PSQT_NAMES: [&str; 7] = ["", "KING", "PAWN", "KNIGHT", "BISHOP", "ROOK", "QUEEN"];
+
+/// Every linear weight of the handcrafted evaluation
+///
+/// The king attack penalty grows with the square of the attack, so it can't be tuned this way and stays as it is
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct EvalParams {
+    pub piece_values: [TaperedScore; 7],
+    /// Piece-square tables by piece type, laid out like in the source (a8 is the first element, h1 is the last one)
+    pub psqt: [[TaperedScore; 64]; 7],
+    pub bishop_pair: TaperedScore,
+    pub doubled_pawn: TaperedScore,
+    pub isolated_pawn: TaperedScore,
+    pub passed_pawn: [TaperedScore; 8],
+    pub mobility: [TaperedScore; 7],
+    pub pawn_shield_close: TaperedScore,
+    pub pawn_shield_far: TaperedScore,
+    pub semi_open_file: TaperedScore,
+    pub open_file: TaperedScore,
+}
+
+impl EvalParams {
+    /// The weights the evaluation currently uses
+    pub fn current() -> Self {
+        let mut psqt = [[TaperedScore::ZERO; 64]; 7];
+        for (piece_type, table) in psqt.iter_mut().enumerate() {
+            for (index, score) in table.iter_mut().enumerate() {
+                *score = TaperedScore::new(
+                    piece_square_tables::MG_TABLES[piece_type][index],
+                    piece_square_tables::EG_TABLES[piece_type][index],
+                );
+            }
+        }
+
+        Self {
+            piece_values: super::super::PIECE_VALUES,
+            psqt,
+            bishop_pair: super::super::BISHOP_PAIR_BONUS,
+            doubled_pawn: pawn_structure::DOUBLED_PAWN_PENALTY,
+            isolated_pawn: pawn_structure::ISOLATED_PAWN_PENALTY,
+            passed_pawn: pawn_structure::PASSED_PAWN_BONUS,
+            mobility: mobility::MOBILITY_WEIGHTS,
+            pawn_shield_close: king_safety::PAWN_SHIELD_CLOSE_BONUS,
+            pawn_shield_far: king_safety::PAWN_SHIELD_FAR_BONUS,
+            semi_open_file: king_safety::SEMI_OPEN_FILE_PENALTY,
+            open_file: king_safety::OPEN_FILE_PENALTY,
+        }
+    }
+
+    /// Flattens the weights in the order of the parameter indices
+    pub fn to_vec(&self) -> Vec<TaperedScore> {
+        let mut params = Vec::with_capacity(PARAM_COUNT);
+        params.extend_from_slice(&self.piece_values);
+        for table in &self.psqt {
+            params.extend_from_slice(table);
+        }
+        params.extend_from_slice(&[self.bishop_pair, self.doubled_pawn, self.isolated_pawn]);
+        params.extend_from_slice(&self.passed_pawn);
+        params.extend_from_slice(&self.mobility);
+        params.extend_from_slice(&[self.pawn_shield_close, self.pawn_shield_far, self.semi_open_file, self.open_file]);
+
+        debug_assert_eq!(params.len(), PARAM_COUNT);
+        params
+    }
+
+    /// Reverse of *to_vec*
+    pub fn from_slice(params: &[TaperedScore]) -> Self {
+        assert_eq!(params.len(), PARAM_COUNT, "Wrong amount of parameters");
+
+        let mut psqt = [[TaperedScore::ZERO; 64]; 7];
+        for (piece_type, table) in psqt.iter_mut().enumerate() {
+            let offset = PSQT_OFFSET + piece_type * 64;
+            table.copy_from_slice(&params[offset..offset + 64]);
+        }
+
+        Self {
+            piece_values: params[PIECE_VALUES_OFFSET..PIECE_VALUES_OFFSET + 7].try_into().unwrap(),
+            psqt,
+            bishop_pair: params[BISHOP_PAIR_INDEX],
+            doubled_pawn: params[DOUBLED_PAWN_INDEX],
+            isolated_pawn: params[ISOLATED_PAWN_INDEX],
+            passed_pawn: params[PASSED_PAWN_OFFSET..PASSED_PAWN_OFFSET + 8].try_into().unwrap(),
+            mobility: params[MOBILITY_OFFSET..MOBILITY_OFFSET + 7].try_into().unwrap(),
+            pawn_shield_close: params[PAWN_SHIELD_CLOSE_INDEX],
+            pawn_shield_far: params[PAWN_SHIELD_FAR_INDEX],
+            semi_open_file: params[SEMI_OPEN_FILE_INDEX],
+            open_file: params[OPEN_FILE_INDEX],
+        }
+    }
+
+    /// Writes the weights as the Rust constants of the evaluation modules, ready to be pasted over the old ones
+    pub fn to_rust_source(&self) -> String {
+        let mut source = String::new();
+
+        source.push_str("// eval/mod.rs\n");
+        write_score_array(&mut source, "PIECE_VALUES", &self.piece_values);
+        write_score(&mut source, "BISHOP_PAIR_BONUS", self.bishop_pair);
+
+        source.push_str("\n// eval/piece_square_tables.rs\n");
+        // same order as in the source
+        for piece_type in [piece::PAWN, piece::KNIGHT, piece::BISHOP, piece::ROOK, piece::QUEEN, piece::KING] {
+            write_table(&mut source, &format!("{}_MG", PSQT_NAMES[piece_type]), &self.psqt[piece_type], |score| score.mg);
+            write_table(&mut source, &format!("{}_EG", PSQT_NAMES[piece_type]), &self.psqt[piece_type], |score| score.eg);
+        }
+
+        source.push_str("// eval/pawn_structure.rs\n");
+        write_score(&mut source, "DOUBLED_PAWN_PENALTY", self.doubled_pawn);
+        write_score(&mut source, "ISOLATED_PAWN_PENALTY", self.isolated_pawn);
+        write_score_array(&mut source, "PASSED_PAWN_BONUS", &self.passed_pawn);
+
+        source.push_str("\n// eval/mobility.rs\n");
+        write_score_array(&mut source, "MOBILITY_WEIGHTS", &self.mobility);
+
+        source.push_str("\n// eval/king_safety.rs\n");
+        write_score(&mut source, "PAWN_SHIELD_CLOSE_BONUS", self.pawn_shield_close);
+        write_score(&mut source, "PAWN_SHIELD_FAR_BONUS", self.pawn_shield_far);
+        write_score(&mut source, "SEMI_OPEN_FILE_PENALTY", self.semi_open_file);
+        write_score(&mut source, "OPEN_FILE_PENALTY", self.open_file);
+
+        source
+    }
+
+    /// Writes the weights as a JSON object, every score is a [mg, eg] pair
+    /// and the piece-square tables are keyed by the lowercase piece name
+    pub fn to_json(&self) -> String {
+        let pair = |score: &TaperedScore| format!("[{}, {}]", score.mg, score.eg);
+        let list = |scores: &[TaperedScore]| format!("[{}]", scores.iter().map(pair).collect::<Vec<_>>().join(", "));
+
+        let psqt = (piece::KING..=piece::QUEEN)
+            .map(|piece_type| format!("    \"{}\": {}", PSQT_NAMES[piece_type].to_lowercase(), list(&self.psqt[piece_type])))
+            .collect::<Vec<_>>()
+            .join(",\n");
+
+        let fields = [
+            format!("  \"piece_values\": {}", list(&self.piece_values)),
+            format!("  \"psqt\": {{\n{}\n  }}", psqt),
+            format!("  \"bishop_pair\": {}", pair(&self.bishop_pair)),
+            format!("  \"doubled_pawn\": {}", pair(&self.doubled_pawn)),
+            format!("  \"isolated_pawn\": {}", pair(&self.isolated_pawn)),
+            format!("  \"passed_pawn\": {}", list(&self.passed_pawn)),
+            format!("  \"mobility\": {}", list(&self.mobility)),
+            format!("  \"pawn_shield_close\": {}", pair(&self.pawn_shield_close)),
+            format!("  \"pawn_shield_far\": {}", pair(&self.pawn_shield_far)),
+            format!("  \"semi_open_file\": {}", pair(&self.semi_open_file)),
+            format!("  \"open_file\": {}", pair(&self.open_file)),
+        ];
+
+        format!("{{\n{}\n}}\n", fields.join(",\n"))
+    }
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        Self::current()
+    }
+}
+
+fn write_score(source: &mut String, name: &str, score: TaperedScore) {
+    writeln!(source, "pub(super) const {}: TaperedScore = TaperedScore::new({}, {});", name, score.mg, score.eg).unwrap();
+}
+
+fn write_score_array(source: &mut String, name: &str, scores: &[TaperedScore]) {
+    writeln!(source, "pub(super) const {}: [TaperedScore; {}] = [", name, scores.len()).unwrap();
+    for score in scores {
+        writeln!(source, "    TaperedScore::new({}, {}),", score.mg, score.eg).unwrap();
+    }
+    source.push_str("];\n");
+}
+
+fn write_table(source: &mut String, name: &str, table: &[TaperedScore; 64], get_value: impl Fn(&TaperedScore) -> i32) {
+    writeln!(source, "#[rustfmt::skip]\nconst {}: [i32; 64] = [", name).unwrap();
+    for row in table.chunks(8) {
+        let values: Vec<String> = row.iter().map(|score| format!("{:>3}", get_value(score))).collect();
+        writeln!(source, "    {},", values.join(", ")).unwrap();
+    }
+    source.push_str("];\n\n");
+}
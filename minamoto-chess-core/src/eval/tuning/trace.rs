@@ -0,0 +1,200 @@
+use crate::{bitboards, board::Board, piece, precomputed_data::square_data};
+
+use super::{params::*, super::{eval_state::EvalState, king_safety, mobility, pawn_structure, piece_square_tables, tapered_score::TaperedScore, MAX_PHASE}};
+
+/// The position broken down into how many times every parameter counts for white minus for black,
+/// so the evaluation can be recalculated for any parameters without looking at the board again
+#[derive(Clone, Debug)]
+pub struct EvalTrace {
+    /// Parameter indices with their non-zero counts
+    coefficients: Vec<(u16, i16)>,
+    phase: i32,
+    /// Part of the evaluation that isn't tuned, positive if white is better
+    fixed_score: TaperedScore,
+}
+
+impl EvalTrace {
+    pub fn from_board(board: &Board) -> Self {
+        let mut counts = [0i16; PARAM_COUNT];
+
+        for color in [piece::WHITE, piece::BLACK] {
+            let sign = if color == piece::WHITE { 1 } else { -1 };
+            trace_pieces(board, color, sign, &mut counts);
+            trace_pawn_structure(board, color, sign, &mut counts);
+            trace_mobility(board, color, sign, &mut counts);
+            trace_king_shelter(board, color, sign, &mut counts);
+        }
+
+        let king_attack = king_safety::get_king_attack_penalty(board, piece::BLACK) - king_safety::get_king_attack_penalty(board, piece::WHITE);
+
+        Self {
+            coefficients: counts.iter().enumerate()
+                .filter(|(_, count)| **count != 0)
+                .map(|(index, count)| (index as u16, *count))
+                .collect(),
+            phase: EvalState::from_board(board).get_phase(),
+            fixed_score: TaperedScore::new(king_attack, 0),
+        }
+    }
+
+    pub fn get_phase(&self) -> i32 {
+        self.phase
+    }
+
+    pub fn get_coefficients(&self) -> &[(u16, i16)] {
+        &self.coefficients
+    }
+
+    /// Evaluation for the provided parameters (as [mg, eg] pairs), positive if white is better
+    ///
+    /// Doesn't round anything, so it may differ from the real evaluation by a centipawn or two
+    pub fn evaluate(&self, params: &[[f64; 2]]) -> f64 {
+        let (mut mg, mut eg) = (self.fixed_score.mg as f64, self.fixed_score.eg as f64);
+        for &(index, count) in &self.coefficients {
+            let [param_mg, param_eg] = params[index as usize];
+            mg += param_mg * count as f64;
+            eg += param_eg * count as f64;
+        }
+
+        (mg * self.phase as f64 + eg * (MAX_PHASE - self.phase) as f64) / MAX_PHASE as f64
+    }
+}
+
+fn trace_pieces(board: &Board, color: usize, sign: i16, counts: &mut [i16; PARAM_COUNT]) {
+    for piece_type in piece::KING..=piece::QUEEN {
+        let mut piece_bb = board.get_piece_bitboard(color, piece_type);
+
+        while piece_bb != 0 {
+            let square = bitboards::get_ls1b(piece_bb);
+            piece_bb &= piece_bb - 1;
+
+            counts[PIECE_VALUES_OFFSET + piece_type] += sign;
+            counts[PSQT_OFFSET + piece_type * 64 + piece_square_tables::get_table_index(color, square)] += sign;
+        }
+    }
+
+    if board.get_piece_bitboard(color, piece::BISHOP).count_ones() > 1 {
+        counts[BISHOP_PAIR_INDEX] += sign;
+    }
+}
+
+fn trace_pawn_structure(board: &Board, color: usize, sign: i16, counts: &mut [i16; PARAM_COUNT]) {
+    let pawn_bb = board.get_piece_bitboard(color, piece::PAWN);
+    let opposite_pawn_bb = board.get_piece_bitboard(1 - color, piece::PAWN);
+
+    for file in 0..8 {
+        let pawns_on_file = (pawn_bb & pawn_structure::get_file_mask(file)).count_ones() as i16;
+        if pawns_on_file == 0 {
+            continue;
+        }
+
+        if pawns_on_file > 1 {
+            counts[DOUBLED_PAWN_INDEX] += sign * (pawns_on_file - 1);
+        }
+
+        if pawn_bb & pawn_structure::get_adjacent_files_mask(file) == 0 {
+            counts[ISOLATED_PAWN_INDEX] += sign * pawns_on_file;
+        }
+    }
+
+    let mut pawns = pawn_bb;
+    while pawns != 0 {
+        let square = bitboards::get_ls1b(pawns);
+        pawns &= pawns - 1;
+
+        if pawn_structure::is_passed_pawn(color, square, opposite_pawn_bb) {
+            counts[PASSED_PAWN_OFFSET + pawn_structure::get_relative_rank(color, square)] += sign;
+        }
+    }
+}
+
+fn trace_mobility(board: &Board, color: usize, sign: i16, counts: &mut [i16; PARAM_COUNT]) {
+    let all_occ = board.get_all_occupied_squares();
+    let safe_squares = !board.get_all_occupied_squares_for_color(color)
+        & !pawn_structure::get_pawn_attacks(board.get_piece_bitboard(1 - color, piece::PAWN), 1 - color);
+
+    for piece_type in piece::KNIGHT..=piece::QUEEN {
+        let mut piece_bb = board.get_piece_bitboard(color, piece_type);
+
+        while piece_bb != 0 {
+            let square = bitboards::get_ls1b(piece_bb);
+            piece_bb &= piece_bb - 1;
+
+            let mobility = (mobility::get_piece_attacks(piece_type, square, all_occ) & safe_squares).count_ones() as i16;
+            counts[MOBILITY_OFFSET + piece_type] += sign * (mobility - mobility::MOBILITY_OFFSETS[piece_type] as i16);
+        }
+    }
+}
+
+fn trace_king_shelter(board: &Board, color: usize, sign: i16, counts: &mut [i16; PARAM_COUNT]) {
+    let king_square = board.get_king_square(color);
+    let king_file = square_data::get_file_from_square(king_square);
+    let king_rank = square_data::get_rank_from_square(king_square);
+    let pawn_bb = board.get_piece_bitboard(color, piece::PAWN);
+    let all_pawns_bb = pawn_bb | board.get_piece_bitboard(1 - color, piece::PAWN);
+    let king_files_mask = pawn_structure::get_file_mask(king_file) | pawn_structure::get_adjacent_files_mask(king_file);
+
+    if pawn_structure::get_relative_rank(color, king_square) < 6 {
+        let close_rank = if color == piece::WHITE { king_rank + 1 } else { king_rank - 1 };
+        let far_rank = if color == piece::WHITE { king_rank + 2 } else { king_rank - 2 };
+        let close_shield = king_files_mask & (bitboards::FIRST_RANK_MASK << (close_rank * 8));
+        let far_shield = king_files_mask & (bitboards::FIRST_RANK_MASK << (far_rank * 8));
+
+        counts[PAWN_SHIELD_CLOSE_INDEX] += sign * (pawn_bb & close_shield).count_ones() as i16;
+        counts[PAWN_SHIELD_FAR_INDEX] += sign * (pawn_bb & far_shield).count_ones() as i16;
+    }
+
+    let first_file = king_file.saturating_sub(1);
+    let last_file = (king_file + 1).min(7);
+    for file in first_file..=last_file {
+        let file_mask = pawn_structure::get_file_mask(file);
+        if all_pawns_bb & file_mask == 0 {
+            counts[OPEN_FILE_INDEX] += sign;
+        } else if pawn_bb & file_mask == 0 {
+            counts[SEMI_OPEN_FILE_INDEX] += sign;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::{self, tuning::params::EvalParams};
+
+    /// Middlegames, endgames and positions with passed pawns, open kings and bishop pairs, with both sides to move
+    const FENS: [&str; 8] = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+        "r2q1rk1/pp2bppp/2np1n2/2p1p3/4P1b1/2PP1NP1/PP1N1PBP/R1BQ1RK1 b - - 0 9",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "6k1/5ppp/8/3P4/8/8/5PPP/6K1 b - - 0 1",
+        "2kr3r/ppp2ppp/2n5/2b1p3/4P1q1/2NP4/PPP2PPP/R1BQ1RK1 w - - 0 12",
+        "8/8/4k3/8/2B5/2B1K3/8/8 b - - 0 1",
+    ];
+
+    /// The trace keeps the fractions the real evaluation truncates when tapering
+    const TAPER_TOLERANCE: f64 = 1.0;
+
+    #[test]
+    fn trace_matches_the_evaluation() {
+        let params: Vec<[f64; 2]> = EvalParams::current().to_vec().iter()
+            .map(|score| [score.mg as f64, score.eg as f64])
+            .collect();
+
+        for fen in FENS {
+            let board = Board::from_fen(fen);
+            let evaluation = eval::evaluate(&board);
+            let white_evaluation = if board.is_white_to_move() { evaluation } else { -evaluation };
+            let traced_evaluation = EvalTrace::from_board(&board).evaluate(&params);
+
+            assert!(
+                (traced_evaluation - white_evaluation as f64).abs() < TAPER_TOLERANCE,
+                "{}: traced {}, evaluated {}",
+                fen,
+                traced_evaluation,
+                white_evaluation
+            );
+        }
+    }
+}
@@ -5,4 +5,8 @@ pub mod castling;
 pub mod precomputed_data;
 pub mod bitboards;
 pub mod move_generation;
-pub mod zobrist;
\ No newline at end of file
+pub mod zobrist;
+pub mod search;
+pub mod eval;
+pub mod syzygy;
+pub mod book;
\ No newline at end of file
@@ -1,6 +1,17 @@
 pub mod move_record;
 
-#[derive(Clone, Copy)]
+const MOVE_TYPES: [MoveType; 8] = [
+    MoveType::Regular,
+    MoveType::PawnDoubleMove,
+    MoveType::PromotionQueen,
+    MoveType::PromotionKnight,
+    MoveType::PromotionRook,
+    MoveType::PromotionBishop,
+    MoveType::CastlingKingSide,
+    MoveType::CastlingQueenSide,
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum MoveType {
     Regular,
     PawnDoubleMove,
@@ -12,7 +23,7 @@ pub enum MoveType {
     CastlingQueenSide
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub struct Move {
     pub start_square: usize,
     pub target_square: usize,
@@ -48,4 +59,25 @@ impl Move {
             _ => false   
         }
     }
+
+    /// Packs the move into 21 bits (start, target and capture squares take 6 bits each, move type takes 3)
+    /// 
+    /// Used to store moves compactly in tables. Never returns 0 since no move starts and ends on the same square
+    pub fn pack(&self) -> u32 {
+        self.start_square as u32
+        | (self.target_square as u32) << 6
+        | (self.capture_square as u32) << 12
+        | (self.move_type as u32) << 18
+    }
+
+    /// Restores the move packed by *pack*
+    pub fn unpack(packed: u32) -> Self {
+        debug_assert!(packed >> 21 == 0, "Packed move is out of bounds");
+        Self {
+            start_square: (packed & 0x3F) as usize,
+            target_square: ((packed >> 6) & 0x3F) as usize,
+            capture_square: ((packed >> 12) & 0x3F) as usize,
+            move_type: MOVE_TYPES[(packed >> 18) as usize],
+        }
+    }
 }
\ No newline at end of file
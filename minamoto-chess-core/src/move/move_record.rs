@@ -1,3 +1,5 @@
+#[cfg(feature = "incremental_eval")]
+use crate::eval::eval_state::EvalState;
 use crate::r#move::Move;
 
 /// Contains all information needed to undo a move
@@ -13,6 +15,9 @@ pub struct MoveRecord {
     pub old_hash: u64,  // Store the old Zobrist hash
     pub old_halfmoves : u8,
     pub old_move_counter : u16,
+    /// The evaluation state before the move, set by *Board::make_move*
+    #[cfg(feature = "incremental_eval")]
+    pub old_eval_state: EvalState,
 }
 
 impl MoveRecord {
@@ -37,6 +42,16 @@ impl MoveRecord {
             old_hash,
             old_halfmoves,
             old_move_counter,
+            #[cfg(feature = "incremental_eval")]
+            old_eval_state: EvalState::EMPTY,
         }
     }
-}
\ No newline at end of file
+}
+/// Contains all information needed to undo a null move (passing the turn without moving)
+pub struct NullMoveRecord {
+    /// The previous en passant state
+    pub is_en_passant_possible: bool,
+    pub en_passant_pawn_square: usize,
+    pub en_passant_capture_square: usize,
+    pub old_hash: u64,
+}
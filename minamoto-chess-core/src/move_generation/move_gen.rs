@@ -557,6 +557,8 @@ fn generate_queens(moves: &mut Vec<Move>, board: &Board, current_color: usize, a
     }
 }
 
+/// Copies the moves that change the position drastically (captures, promotions, checks and discovered checks) 
+/// into *filtered_moves*
 pub fn filter_loud_moves(legal_moves: &[Move], filtered_moves: &mut Vec<Move>, attack_calc: &AttackCalculator, board: &Board) {
     let move_count = legal_moves.len();
     for i in 0..move_count {
@@ -574,7 +576,7 @@ pub fn filter_loud_moves(legal_moves: &[Move], filtered_moves: &mut Vec<Move>, a
         }
 
         let (_, capture_square_piece_type) = board.get_piece_on_square(mov.capture_square);
-        if capture_square_piece_type != piece::NONE {
+        if capture_square_piece_type != piece::NONE || mov.is_promotion() {
             filtered_moves.push(mov);
             continue;
         }
@@ -583,7 +585,8 @@ pub fn filter_loud_moves(legal_moves: &[Move], filtered_moves: &mut Vec<Move>, a
             let pin_revealer_bb = unsafe { std::ptr::read(attack_calc.pin_revealer_bbs.as_ptr().add(direction_index)) };
             let line_mask = precomputed_data::SQUARE_DATA.get_file_rank_diagonal_mask(mov.start_square, direction_index);
 
-            if (pin_revealer_bb & start_square_bb) != 0 && (line_mask & target_square_bb) != 0 {
+            // the check is revealed only if the piece leaves the line between the slider and the king
+            if (pin_revealer_bb & start_square_bb) != 0 && (line_mask & target_square_bb) == 0 {
                 filtered_moves.push(mov);
                 break;
             }
@@ -0,0 +1,218 @@
+/*Proves forced mates instead of estimating them like the regular search.
+
+The attacker (the side to move at the root) needs a move after which every reply of the defender
+still loses within the remaining moves, so the defender tries all its legal moves while the attacker
+only tries the moves that can lead to a mate. In the checks only mode (the default, which solves
+the "mate with checks" problems and most practical mates the fastest) those are the checking moves,
+otherwise every move is tried with the checks first. The last move of a mate is always a check,
+so it is the only kind of move tried there in both modes.
+
+Checks are found with the check squares and pin revealers of the attack calculator, without making the move.
+Promotions, castling and en passant change more than the moved piece, so they are made to be sure*/
+
+use std::sync::{atomic::{AtomicBool, Ordering}, Arc};
+
+use rustc_hash::FxHashSet;
+
+use crate::{
+    bitboards,
+    board::Board,
+    r#move::Move,
+    move_generation::{attack_calculator::AttackCalculator, move_gen},
+    precomputed_data,
+};
+
+// constants
+/// Longest mate (in moves of the attacker) the solver looks for
+pub const MAX_MATE_MOVES: u8 = 16;
+
+pub enum MateResult {
+    /// The attacker and defender moves of the mate, starting and ending with an attacker move.
+    /// The defender always plays the reply that survives the longest
+    Mate(Vec<Move>),
+    /// There is no mate within the provided amount of moves (using only checks in the checks only mode)
+    NoMate,
+    /// The node limit or the stop flag ended the search before anything was proven
+    Aborted,
+}
+
+pub struct MateSolver {
+    nodes: u64,
+    node_limit: Option<u64>,
+    /// Raised from the outside to abort the search
+    stop_flag: Arc<AtomicBool>,
+    aborted: bool,
+    checks_only: bool,
+    /// Positions (by hash) where the attacker can't mate within the amount of moves
+    refuted: FxHashSet<(u64, u8)>,
+}
+
+impl MateSolver {
+    pub fn new() -> Self {
+        Self {
+            nodes: 0,
+            node_limit: None,
+            stop_flag: Arc::new(AtomicBool::new(false)),
+            aborted: false,
+            checks_only: true,
+            refuted: FxHashSet::default(),
+        }
+    }
+
+    /// Only allows checking moves for the attacker when enabled, which is much faster but misses the mates with a quiet move
+    pub fn set_checks_only(&mut self, checks_only: bool) {
+        self.checks_only = checks_only;
+    }
+
+    pub fn is_checks_only(&self) -> bool {
+        self.checks_only
+    }
+
+    /// Aborts the search after the provided amount of nodes, *None* searches until the end
+    pub fn set_node_limit(&mut self, node_limit: Option<u64>) {
+        self.node_limit = node_limit;
+    }
+
+    pub fn set_stop_flag(&mut self, stop_flag: Arc<AtomicBool>) {
+        self.stop_flag = stop_flag;
+    }
+
+    /// Nodes visited by the last search
+    pub fn get_nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    /// Looks for the shortest forced mate of the side to move within *max_moves* of its moves
+    /// (clamped to *MAX_MATE_MOVES*)
+    ///
+    /// The board is left in the same state it was provided in
+    pub fn solve(&mut self, board: &mut Board, max_moves: u8) -> MateResult {
+        self.nodes = 0;
+        self.aborted = false;
+        self.refuted.clear();
+
+        for moves in 1..=max_moves.min(MAX_MATE_MOVES) {
+            if let Some(line) = self.attack(board, moves) {
+                return MateResult::Mate(line);
+            }
+
+            if self.aborted {
+                return MateResult::Aborted;
+            }
+        }
+
+        MateResult::NoMate
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if self.node_limit.is_some_and(|node_limit| self.nodes >= node_limit) || self.stop_flag.load(Ordering::Relaxed) {
+            self.aborted = true;
+        }
+
+        self.aborted
+    }
+
+    /// Finds an attacker move mating within *moves_left* moves against any defence, returns the line starting with it
+    fn attack(&mut self, board: &mut Board, moves_left: u8) -> Option<Vec<Move>> {
+        let key = (board.get_zobrist_hash(), moves_left);
+        if self.refuted.contains(&key) || self.should_stop() {
+            return None;
+        }
+        self.nodes += 1;
+
+        let attack_calc = AttackCalculator::new(board);
+        let mut moves = move_gen::create_empty_move_buffer();
+        move_gen::generate_moves(&mut moves, board, &attack_calc);
+
+        let (checks, quiet_moves): (Vec<Move>, Vec<Move>) = moves.into_iter()
+            .partition(|mov| gives_check(board, &attack_calc, mov));
+        let only_checks = self.checks_only || moves_left == 1;
+
+        for mov in checks.into_iter().chain(quiet_moves.into_iter().filter(|_| !only_checks)) {
+            let move_record = board.make_move(mov);
+            let line = self.defend(board, moves_left);
+            board.undo_move(move_record);
+
+            if let Some(mut line) = line {
+                line.insert(0, mov);
+                return Some(line);
+            }
+
+            if self.aborted {
+                return None;
+            }
+        }
+
+        self.refuted.insert(key);
+        None
+    }
+
+    /// Checks that every reply loses within the remaining moves of the attacker,
+    /// returns the line of the reply that takes the longest to mate
+    fn defend(&mut self, board: &mut Board, moves_left: u8) -> Option<Vec<Move>> {
+        self.nodes += 1;
+
+        let attack_calc = AttackCalculator::new(board);
+        let mut moves = move_gen::create_empty_move_buffer();
+        move_gen::generate_moves(&mut moves, board, &attack_calc);
+
+        if moves.is_empty() {
+            // stalemate saves the defender
+            return attack_calc.in_check().then(Vec::new);
+        }
+
+        if moves_left == 1 || board.is_draw_by_repetition() || board.is_draw_by_50_moves_rule() || board.is_draw_by_material() {
+            return None;
+        }
+
+        let mut longest_line: Option<Vec<Move>> = None;
+
+        for reply in moves {
+            let move_record = board.make_move(reply);
+            // the shortest mate after the reply, so the line isn't padded with pointless moves
+            let line = (1..moves_left).find_map(|moves| {
+                if self.aborted { None } else { self.attack(board, moves) }
+            });
+            board.undo_move(move_record);
+
+            let mut line = line?;
+            if longest_line.as_ref().is_none_or(|longest_line| line.len() + 1 > longest_line.len()) {
+                line.insert(0, reply);
+                longest_line = Some(line);
+            }
+        }
+
+        longest_line
+    }
+}
+
+impl Default for MateSolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns true if the legal move checks the opposite king
+fn gives_check(board: &mut Board, attack_calc: &AttackCalculator, mov: &Move) -> bool {
+    if mov.is_promotion() || mov.is_castling() || mov.capture_square != mov.target_square {
+        let move_record = board.make_move(*mov);
+        let gives_check = AttackCalculator::new(board).in_check();
+        board.undo_move(move_record);
+
+        return gives_check;
+    }
+
+    let (_, piece_type) = board.get_piece_on_square(mov.start_square);
+    let start_square_bb = bitboards::get_bit_from_square(mov.start_square);
+    let target_square_bb = bitboards::get_bit_from_square(mov.target_square);
+
+    if attack_calc.opposite_check_squares_bbs[piece_type] & target_square_bb != 0 {
+        return true;
+    }
+
+    // the check is revealed only if the piece leaves the line between the slider and the king
+    (0..4).any(|direction_index| {
+        attack_calc.pin_revealer_bbs[direction_index] & start_square_bb != 0
+        && precomputed_data::SQUARE_DATA.get_file_rank_diagonal_mask(mov.start_square, direction_index) & target_square_bb == 0
+    })
+}
@@ -0,0 +1,11 @@
+pub mod searcher;
+pub mod search_result;
+pub mod transposition_table;
+pub mod move_ordering;
+pub mod see;
+pub mod search_limits;
+pub mod time_manager;
+pub mod search_options;
+pub mod skill;
+pub mod mate_solver;
+pub mod stepped_search;
\ No newline at end of file
@@ -0,0 +1,200 @@
+use crate::{board::Board, eval, r#move::{Move, MoveType}, piece};
+
+use super::{searcher::MAX_PLY, see};
+
+// constants
+// Ordering scores of the move categories. The categories never overlap so each of them is searched as a whole
+const HASH_MOVE_SCORE: i32 = 1_000_000;
+const CAPTURE_SCORE: i32 = 200_000;
+const PROMOTION_SCORE: i32 = 100_000;
+const FIRST_KILLER_SCORE: i32 = 90_000;
+const SECOND_KILLER_SCORE: i32 = 80_000;
+/// Captures losing material by the static exchange evaluation go after every quiet move
+const LOSING_CAPTURE_SCORE: i32 = -50_000;
+/// Under-promotions are almost never good so they go after every other move
+const UNDER_PROMOTION_SCORE: i32 = -100_000;
+/// History scores stay within *-MAX_HISTORY..=MAX_HISTORY* so they never reach the killers
+const MAX_HISTORY: i32 = 16_384;
+
+/// Returns true if the move takes an enemy piece (en passant included)
+pub fn is_capture(board: &Board, mov: &Move) -> bool {
+    let (_, captured_piece_type) = board.get_piece_on_square(mov.capture_square);
+    captured_piece_type != piece::NONE
+}
+
+/// Returns true if the move neither captures nor promotes, so only the history can tell how good it is
+pub fn is_quiet(board: &Board, mov: &Move) -> bool {
+    !is_capture(board, mov) && !mov.is_promotion()
+}
+
+/// Most valuable victim - least valuable attacker. Taking a queen with a pawn goes first,
+/// taking a pawn with a queen goes last
+fn get_mvv_lva_score(board: &Board, mov: &Move) -> i32 {
+    let (_, victim) = board.get_piece_on_square(mov.capture_square);
+    let (_, attacker) = board.get_piece_on_square(mov.start_square);
+
+    eval::get_piece_value(victim) * 10 - eval::get_piece_value(attacker) / 10
+}
+
+/// Heuristics remembering which quiet moves caused beta cutoffs, kept between the nodes of a search
+pub struct OrderingHeuristics {
+    /// Two latest quiet moves that caused a cutoff for every ply
+    killers: [[Option<Move>; 2]; MAX_PLY],
+    /// Butterfly table: how often the quiet move from one square to another caused cutoffs.
+    /// Goes like *history\[color]\[start_square]\[target_square]*
+    history: Box<[[[i32; 64]; 64]; 2]>,
+}
+
+impl OrderingHeuristics {
+    pub fn new() -> Self {
+        Self {
+            killers: [[None; 2]; MAX_PLY],
+            history: Box::new([[[0; 64]; 64]; 2]),
+        }
+    }
+
+    /// Forgets the killers and softens the history so the previous search still helps but doesn't dominate
+    pub fn new_search(&mut self) {
+        self.killers = [[None; 2]; MAX_PLY];
+        for score in self.history.iter_mut().flatten().flatten() {
+            *score /= 2;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.killers = [[None; 2]; MAX_PLY];
+        *self.history = [[[0; 64]; 64]; 2];
+    }
+
+    pub fn get_killers(&self, ply: usize) -> &[Option<Move>; 2] {
+        debug_assert!(ply < MAX_PLY, "Ply is out of bounds");
+        &self.killers[ply]
+    }
+
+    pub fn get_history(&self, color: usize, mov: &Move) -> i32 {
+        debug_assert!(color < 2, "Color is out of bounds");
+        self.history[color][mov.start_square][mov.target_square]
+    }
+
+    /// Moves the history score towards the limit by the bonus (or away from it with a negative bonus).
+    /// The closer the score is to the limit, the less it changes, so it never leaves the bounds
+    fn update_history(&mut self, color: usize, mov: &Move, bonus: i32) {
+        let score = &mut self.history[color][mov.start_square][mov.target_square];
+        *score += bonus - *score * bonus.abs() / MAX_HISTORY;
+    }
+
+    /// Must be called when a quiet move causes a beta cutoff with the board in the state the move was made from
+    ///
+    /// *searched_moves* are the moves searched in the node including the cutoff move itself.
+    /// Quiet ones among them get their history lowered since they failed to cause the cutoff first
+    pub fn update_on_cutoff(&mut self, board: &Board, cutoff_move: Move, searched_moves: &[Move], ply: usize, depth: u8) {
+        debug_assert!(is_quiet(board, &cutoff_move), "Only quiet moves are remembered");
+        let color = board.get_current_color();
+        let bonus = (depth as i32 * depth as i32).min(MAX_HISTORY);
+
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(cutoff_move) {
+            killers[1] = killers[0];
+            killers[0] = Some(cutoff_move);
+        }
+
+        self.update_history(color, &cutoff_move, bonus);
+
+        for mov in searched_moves {
+            if *mov != cutoff_move && is_quiet(board, mov) {
+                self.update_history(color, mov, -bonus);
+            }
+        }
+    }
+}
+
+impl Default for OrderingHeuristics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Yields generated moves lazily from the most to the least promising one
+///
+/// Every move gets scored upfront, but the moves are picked one by one with a selection sort,
+/// so after a cutoff on one of the first moves the rest never get sorted
+pub struct MovePicker {
+    moves: Vec<Move>,
+    scores: Vec<i32>,
+    picked_count: usize,
+}
+
+impl MovePicker {
+    /// Takes the buffers with the generated moves and the scores (its content doesn't matter),
+    /// they are given back by *into_buffers*
+    pub fn new(moves: Vec<Move>, mut scores: Vec<i32>, board: &Board, hash_move: Option<Move>, heuristics: &OrderingHeuristics, ply: usize) -> Self {
+        let color = board.get_current_color();
+        let killers = heuristics.get_killers(ply.min(MAX_PLY - 1));
+
+        scores.clear();
+        scores.extend(moves.iter().map(|mov| {
+            if Some(*mov) == hash_move {
+                return HASH_MOVE_SCORE;
+            }
+
+            match mov.move_type {
+                MoveType::PromotionQueen => return PROMOTION_SCORE + get_mvv_lva_score(board, mov),
+                MoveType::PromotionKnight | MoveType::PromotionRook | MoveType::PromotionBishop => return UNDER_PROMOTION_SCORE,
+                _ => (),
+            }
+
+            if is_capture(board, mov) {
+                if see::see_ge(board, mov, 0) {
+                    CAPTURE_SCORE + get_mvv_lva_score(board, mov)
+                } else {
+                    LOSING_CAPTURE_SCORE + get_mvv_lva_score(board, mov)
+                }
+            } else if killers[0] == Some(*mov) {
+                FIRST_KILLER_SCORE
+            } else if killers[1] == Some(*mov) {
+                SECOND_KILLER_SCORE
+            } else {
+                heuristics.get_history(color, mov)
+            }
+        }));
+
+        Self {
+            moves,
+            scores,
+            picked_count: 0,
+        }
+    }
+
+    /// Returns the moves already given out, in the order they were given out
+    pub fn get_picked_moves(&self) -> &[Move] {
+        &self.moves[..self.picked_count]
+    }
+
+    pub fn into_buffers(self) -> (Vec<Move>, Vec<i32>) {
+        (self.moves, self.scores)
+    }
+}
+
+impl Iterator for MovePicker {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        let start = self.picked_count;
+        if start >= self.moves.len() {
+            return None;
+        }
+
+        let mut best_index = start;
+        for index in start + 1..self.scores.len() {
+            if self.scores[index] > self.scores[best_index] {
+                best_index = index;
+            }
+        }
+
+        self.moves.swap(start, best_index);
+        self.scores.swap(start, best_index);
+        self.picked_count += 1;
+
+        Some(self.moves[start])
+    }
+}
@@ -0,0 +1,41 @@
+/// Conditions under which the search stops. Every limit left as *None* is ignored
+///
+/// Times are in milliseconds, clocks and increments are indexed by color
+#[derive(Clone, Default, Debug)]
+pub struct SearchLimits {
+    pub depth: Option<u8>,
+    pub nodes: Option<u64>,
+    /// Exact time to search for
+    pub movetime: Option<u64>,
+    /// Remaining time on the clock of each color
+    pub time: [Option<u64>; 2],
+    /// Time added to the clock of each color after every move
+    pub increment: [Option<u64>; 2],
+    /// Moves left until the next time control, the remaining time is spread over them
+    pub movestogo: Option<u64>,
+    /// Search until stopped from the outside, ignoring every other limit
+    pub infinite: bool,
+}
+
+impl SearchLimits {
+    pub fn from_depth(depth: u8) -> Self {
+        Self {
+            depth: Some(depth),
+            ..Self::default()
+        }
+    }
+
+    pub fn from_movetime(movetime: u64) -> Self {
+        Self {
+            movetime: Some(movetime),
+            ..Self::default()
+        }
+    }
+
+    pub fn from_nodes(nodes: u64) -> Self {
+        Self {
+            nodes: Some(nodes),
+            ..Self::default()
+        }
+    }
+}
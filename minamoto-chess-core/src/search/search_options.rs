@@ -0,0 +1,41 @@
+/// Pruning, reduction and extension techniques the search uses, all of them are enabled by default
+///
+/// Meant for testing: disabling a technique shows how much it is worth in nodes, time and strength
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SearchOptions {
+    /// Skip the nodes where even passing the turn keeps the score above beta
+    pub null_move_pruning: bool,
+    /// Search the late quiet moves to a lower depth, re-searching them only when they raise alpha
+    pub late_move_reductions: bool,
+    /// Search the root with a narrow window around the score of the previous iteration
+    pub aspiration_windows: bool,
+    /// Search a ply deeper when in check
+    pub check_extensions: bool,
+    /// Skip the quiet moves near the leaves that can't raise alpha even with a margin
+    pub futility_pruning: bool,
+}
+
+impl SearchOptions {
+    /// Plain alpha-beta without any of the techniques
+    pub const NONE: Self = Self {
+        null_move_pruning: false,
+        late_move_reductions: false,
+        aspiration_windows: false,
+        check_extensions: false,
+        futility_pruning: false,
+    };
+
+    pub const ALL: Self = Self {
+        null_move_pruning: true,
+        late_move_reductions: true,
+        aspiration_windows: true,
+        check_extensions: true,
+        futility_pruning: true,
+    };
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
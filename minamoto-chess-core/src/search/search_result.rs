@@ -0,0 +1,33 @@
+use crate::r#move::Move;
+
+/// One of the best lines found from the root position
+#[derive(Clone)]
+pub struct PvLine {
+    /// Score in centipawns from the perspective of the side to move
+    pub score: i32,
+    pub depth: u8,
+    /// Principal variation of the line, starting with its root move
+    pub pv: Vec<Move>,
+}
+
+/// Contains the outcome of a finished search
+#[derive(Default)]
+pub struct SearchResult {
+    /// The move the engine would play (or *None* if there are no legal moves in the root position)
+    pub best_move: Option<Move>,
+    /// Score in centipawns from the perspective of the side to move
+    pub score: i32,
+    /// The depth of the last fully completed iteration
+    pub depth: u8,
+    /// The deepest ply reached by the search, including the quiescence search and the extensions
+    pub seldepth: u8,
+    /// Principal variation starting with the best move
+    pub pv: Vec<Move>,
+    /// Best lines ranked from the best to the worst, one per MultiPV slot. The first one matches the fields above
+    pub lines: Vec<PvLine>,
+    pub nodes: u64,
+    /// How full the transposition table is in permille
+    pub hashfull: usize,
+    /// Time the search took in milliseconds
+    pub time_ms: u64,
+}
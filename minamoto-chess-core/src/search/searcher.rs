@@ -0,0 +1,1153 @@
+use std::{cmp::Reverse, sync::{atomic::{AtomicBool, Ordering}, Arc}};
+
+use rand::Rng;
+
+use crate::{board::Board, eval::{self, evaluate}, r#move::Move, move_generation::{attack_calculator::AttackCalculator, move_gen}, piece, syzygy::{Tablebase, Wdl}};
+
+use super::{move_ordering::{self, MovePicker, OrderingHeuristics}, search_limits::SearchLimits, search_options::SearchOptions, search_result::{PvLine, SearchResult}, see, skill::SkillLevel, time_manager::TimeManager, transposition_table::{self, Bound, TranspositionTable}};
+
+// constants
+/// Maximum amount of plies the search can go from the root
+pub const MAX_PLY: usize = 64;
+/// Bigger than any score the search can return, used as the initial alpha-beta window
+pub const INFINITY: i32 = 32000;
+/// Score of being checkmated right in the root position
+///
+/// Mates further from the root get closer to zero so that the search prefers the shortest mate
+pub const MATE_SCORE: i32 = 31000;
+/// Every score with an absolute value above this one is a forced mate
+pub const MATE_THRESHOLD: i32 = MATE_SCORE - MAX_PLY as i32;
+pub const DRAW_SCORE: i32 = 0;
+/// Score of a tablebase win right in the root position, below every mate score
+///
+/// Like the mates, the wins further from the root get closer to zero
+pub const TB_WIN_SCORE: i32 = MATE_THRESHOLD - 1;
+/// Every score with an absolute value above this one is a tablebase win or a forced mate
+pub const TB_WIN_THRESHOLD: i32 = TB_WIN_SCORE - MAX_PLY as i32;
+/// Safety margin of delta pruning. A capture is skipped when even winning the captured piece 
+/// plus this margin can't raise alpha
+const DELTA_MARGIN: i32 = 200;
+/// Amount of quiescence plies where quiet checks are still searched. Deeper only captures 
+/// and promotions are searched, otherwise endless checking sequences would blow the search up
+const QUIESCENCE_CHECK_PLIES: usize = 1;
+/// The clock and the stop flag are checked once per this amount of nodes (must be a power of two)
+const STOP_CHECK_INTERVAL: u64 = 2048;
+/// Half width of the first aspiration window, doubled after every failed search
+const ASPIRATION_WINDOW: i32 = 40;
+/// Depth from which the root is searched with aspiration windows, the shallow scores are too unstable
+const ASPIRATION_MIN_DEPTH: u8 = 4;
+/// Minimum depth for the null move pruning
+const NULL_MOVE_MIN_DEPTH: u8 = 3;
+/// The null move is searched this many plies shallower (plus one ply per *NULL_MOVE_DEPTH_DIVISOR* of depth)
+const NULL_MOVE_REDUCTION: u8 = 2;
+const NULL_MOVE_DEPTH_DIVISOR: u8 = 6;
+/// Futility pruning is only done this close to the leaves
+const FUTILITY_MAX_DEPTH: u8 = 3;
+/// Margin for every ply of the remaining depth. A quiet move is skipped when the static evaluation
+/// plus the margin can't raise alpha
+const FUTILITY_MARGIN: i32 = 120;
+/// Minimum depth for the late move reductions
+const LMR_MIN_DEPTH: u8 = 3;
+/// Amount of moves searched to the full depth before the quiet moves get reduced
+const LMR_FULL_DEPTH_MOVES: usize = 3;
+/// Moves searched after this many moves are reduced by an extra ply
+const LMR_LATE_MOVES: usize = 10;
+/// Depth from which the moves are reduced by an extra ply
+const LMR_DEEP_DEPTH: u8 = 6;
+#[cfg(feature = "smp")]
+pub const MAX_THREADS: usize = 256;
+
+pub fn is_mate_score(score: i32) -> bool {
+    score.abs() >= MATE_THRESHOLD
+}
+
+/// Returns the amount of full moves until mate (negative if the side to move is getting mated)
+pub fn get_mate_distance(score: i32) -> i32 {
+    debug_assert!(is_mate_score(score), "Score is not a mate score");
+    if score > 0 {
+        (MATE_SCORE - score + 1) / 2
+    } else {
+        -(MATE_SCORE + score) / 2
+    }
+}
+
+/// Returns true if the side to move has anything besides the king and the pawns
+///
+/// The null move pruning is unsafe without it: in pawn endgames passing is often the best move (zugzwang)
+fn has_non_pawn_material(board: &Board) -> bool {
+    let color = board.get_current_color();
+    [piece::KNIGHT, piece::BISHOP, piece::ROOK, piece::QUEEN].iter()
+        .any(|&piece_type| board.get_piece_bitboard(color, piece_type) != 0)
+}
+
+/// Returns how many plies shallower a late quiet move is searched
+fn get_late_move_reduction(depth: u8, moves_searched: usize) -> u8 {
+    1 + (depth >= LMR_DEEP_DEPTH) as u8 + (moves_searched >= LMR_LATE_MOVES) as u8
+}
+
+/// Window of the aspiration search of a root, widened on the side the score falls outside of
+struct AspirationWindow {
+    alpha: i32,
+    beta: i32,
+    /// Widening of the next failed search
+    delta: i32,
+    /// Root move searched first
+    hash_move: Option<Move>,
+}
+
+impl AspirationWindow {
+    fn new(options: SearchOptions, depth: u8, hash_move: Option<Move>, previous_score: Option<i32>) -> Self {
+        let delta = ASPIRATION_WINDOW;
+        let (alpha, beta) = match previous_score {
+            Some(score) if options.aspiration_windows && depth >= ASPIRATION_MIN_DEPTH && !is_mate_score(score) => {
+                ((score - delta).max(-INFINITY), (score + delta).min(INFINITY))
+            }
+            _ => (-INFINITY, INFINITY),
+        };
+
+        Self { alpha, beta, delta, hash_move }
+    }
+}
+
+/// Which of the principal variation searches of a move was running
+#[derive(Clone, Copy)]
+enum ChildSearch {
+    /// Null window search at the reduced depth (the first one for every move but the first)
+    Reduced,
+    /// Null window search at the full depth
+    FullDepth,
+    /// Search with the window of the node
+    FullWindow,
+}
+
+/// State of the move loop of a root or a quiescence node
+struct MoveLoopFrame {
+    move_picker: MovePicker,
+    alpha: i32,
+    /// Move searched when the step ended, searched again before the picker goes on
+    pending_move: Option<Move>,
+}
+
+/// State of the move loop of a negamax node
+struct NegamaxMoveLoop {
+    is_futile: bool,
+    move_picker: MovePicker,
+    alpha: i32,
+    best_score: i32,
+    best_move: Option<Move>,
+    moves_searched: usize,
+    /// Move searched when the step ended, with the search it was in
+    pending_move: Option<(Move, ChildSearch)>,
+}
+
+/// What a node on the path of the interrupted node was doing when the step ended,
+/// so the next step can continue it instead of searching it again
+enum NodeFrame {
+    Root(MoveLoopFrame),
+    /// Searching the null move
+    NullMove { depth: u8, in_check: bool, static_eval: Option<i32>, tt_move: Option<Move> },
+    Moves { depth: u8, in_check: bool, move_loop: NegamaxMoveLoop },
+    /// Negamax node at depth 0 whose quiescence search was stopped before it started
+    Leaf,
+    Quiescence { in_check: bool, stand_pat: i32, best_score: i32, move_loop: MoveLoopFrame },
+}
+
+/// Progress of an iteration interrupted by the end of a step
+struct IterationFrame {
+    pv_index: usize,
+    lines: Vec<PvLine>,
+    excluded_moves: Vec<Move>,
+    window: AspirationWindow,
+}
+
+/// Negamax alpha-beta search with iterative deepening
+///
+/// Keeps its buffers between searches, so it is better to create
+/// one searcher and reuse it for the entire game
+pub struct Searcher {
+    nodes: u64,
+    /// Raised from the outside (e.g. another thread) to stop the search as soon as possible
+    stop_flag: Arc<AtomicBool>,
+    /// Node limit of the current search
+    node_limit: Option<u64>,
+    /// Time manager of the current search
+    time_manager: Option<TimeManager>,
+    /// Deepest ply reached by the current search
+    seldepth: usize,
+    /// Set once a stop condition is met, after that every node returns immediately
+    stopped: bool,
+    /// Depth of the last completed iteration of the current search
+    completed_depth: u8,
+    /// Amount of best root moves searched with their own lines
+    multi_pv: usize,
+    options: SearchOptions,
+    /// Amount of threads searching together, including the calling one
+    #[cfg(feature = "smp")]
+    thread_count: usize,
+    /// Move buffer for every ply so no allocations happen during the search
+    move_buffers: Vec<Vec<Move>>,
+    /// Same as *move_buffers* but for the loud moves searched in quiescence
+    loud_move_buffers: Vec<Vec<Move>>,
+    /// Triangular PV table: the line at index *ply* is the best line found from that ply
+    pv_lines: Vec<Vec<Move>>,
+    /// Ordering scores of the moves in the buffers above for every ply
+    score_buffers: Vec<Vec<i32>>,
+    /// Shared with the helper threads of Lazy SMP
+    tt: Arc<TranspositionTable>,
+    heuristics: OrderingHeuristics,
+    /// Syzygy tables probed in the search, shared with the helper threads
+    tablebase: Option<Arc<Tablebase>>,
+    /// Max offset in centipawns added to every static evaluation, 0 keeps the evaluation exact
+    eval_noise: i32,
+    /// Picked again for every search so the same position isn't misjudged the same way every time
+    eval_noise_seed: u64,
+    /// Set for a stepped search: a node interrupted by the end of a step keeps its frame
+    is_resumable: bool,
+    /// Set while a step goes down the path of the node interrupted by the previous step
+    is_resuming: bool,
+    /// Frame of every node on the path of the interrupted node, indexed by ply
+    frames: Vec<Option<NodeFrame>>,
+    iteration_frame: Option<IterationFrame>,
+}
+
+impl Searcher {
+    pub fn new() -> Self {
+        Self::with_hash_size(transposition_table::DEFAULT_SIZE_MB)
+    }
+
+    /// Creates a searcher with the transposition table taking the provided amount of megabytes
+    pub fn with_hash_size(hash_size_mb: usize) -> Self {
+        Self::with_transposition_table(Arc::new(TranspositionTable::new(hash_size_mb)))
+    }
+
+    fn with_transposition_table(tt: Arc<TranspositionTable>) -> Self {
+        Self {
+            nodes: 0,
+            stop_flag: Arc::new(AtomicBool::new(false)),
+            node_limit: None,
+            time_manager: None,
+            seldepth: 0,
+            stopped: false,
+            completed_depth: 0,
+            multi_pv: 1,
+            options: SearchOptions::default(),
+            #[cfg(feature = "smp")]
+            thread_count: 1,
+            move_buffers: (0..MAX_PLY).map(|_| move_gen::create_empty_move_buffer()).collect(),
+            loud_move_buffers: (0..MAX_PLY).map(|_| move_gen::create_empty_move_buffer()).collect(),
+            pv_lines: (0..=MAX_PLY).map(|_| Vec::with_capacity(MAX_PLY)).collect(),
+            score_buffers: (0..MAX_PLY).map(|_| Vec::with_capacity(move_gen::MAX_MOVES_PER_POS)).collect(),
+            tt,
+            heuristics: OrderingHeuristics::new(),
+            tablebase: None,
+            eval_noise: 0,
+            eval_noise_seed: 0,
+            is_resumable: false,
+            is_resuming: false,
+            frames: (0..MAX_PLY).map(|_| None).collect(),
+            iteration_frame: None,
+        }
+    }
+
+    pub fn get_transposition_table(&self) -> &TranspositionTable {
+        &self.tt
+    }
+
+    /// Reallocates the transposition table to take at most the provided amount of megabytes
+    ///
+    /// If the table is shared outside of the search (e.g. with a forked searcher), this searcher gets a new table
+    /// and the other ones keep the old one
+    pub fn resize_transposition_table(&mut self, size_mb: usize) {
+        match Arc::get_mut(&mut self.tt) {
+            Some(tt) => tt.resize(size_mb),
+            None => self.tt = Arc::new(TranspositionTable::new(size_mb)),
+        }
+    }
+
+    /// Replaces the flag that stops the search when raised. The search never lowers it by itself
+    pub fn set_stop_flag(&mut self, stop_flag: Arc<AtomicBool>) {
+        self.stop_flag = stop_flag;
+    }
+
+    pub fn get_stop_flag(&self) -> &Arc<AtomicBool> {
+        &self.stop_flag
+    }
+
+    /// Sets the amount of best lines to search. Every line after the first one
+    /// is searched without the root moves of the previous lines, so each of them slows the search down
+    pub fn set_multi_pv(&mut self, multi_pv: usize) {
+        self.multi_pv = multi_pv.clamp(1, move_gen::MAX_MOVES_PER_POS);
+    }
+
+    pub fn get_multi_pv(&self) -> usize {
+        self.multi_pv
+    }
+
+    /// Enables or disables the pruning, reduction and extension techniques of the search
+    pub fn set_options(&mut self, options: SearchOptions) {
+        self.options = options;
+    }
+
+    pub fn get_options(&self) -> SearchOptions {
+        self.options
+    }
+
+    /// Sets the endgame tablebases probed by the search. The root position is played from the DTZ tables
+    /// when it is in them, the other positions use the WDL tables
+    pub fn set_tablebase(&mut self, tablebase: Option<Arc<Tablebase>>) {
+        self.tablebase = tablebase;
+    }
+
+    pub fn get_tablebase(&self) -> Option<&Arc<Tablebase>> {
+        self.tablebase.as_ref()
+    }
+
+    /// Gives access to the tablebase to add more tables. Returns *None* if there is no tablebase
+    /// or it is shared outside of the search (e.g. with a forked searcher)
+    pub fn get_tablebase_mut(&mut self) -> Option<&mut Tablebase> {
+        self.tablebase.as_mut().and_then(Arc::get_mut)
+    }
+
+    /// Makes the search misjudge the positions by adding up to the provided amount of centipawns (either way)
+    /// to every static evaluation. The offset only depends on the position, so a position keeps its offset
+    /// for the whole search. 0 turns the noise off
+    pub fn set_eval_noise(&mut self, eval_noise: i32) {
+        self.eval_noise = eval_noise.max(0);
+    }
+
+    pub fn get_eval_noise(&self) -> i32 {
+        self.eval_noise
+    }
+
+    /// Sets the amount of threads searching together (Lazy SMP). 1 means the search runs only on the calling thread
+    #[cfg(feature = "smp")]
+    pub fn set_thread_count(&mut self, thread_count: usize) {
+        self.thread_count = thread_count.clamp(1, MAX_THREADS);
+    }
+
+    #[cfg(feature = "smp")]
+    pub fn get_thread_count(&self) -> usize {
+        self.thread_count
+    }
+
+    /// Forgets everything learned in the previous searches (transposition table, killers and history),
+    /// should be called when a new game starts
+    pub fn clear(&mut self) {
+        self.tt.clear();
+        self.heuristics.clear();
+    }
+
+    /// Searches the position up to the provided depth and returns the result of the deepest iteration
+    ///
+    /// The board is left in the same state it was provided in
+    pub fn search(&mut self, board: &mut Board, max_depth: u8) -> SearchResult {
+        self.search_with_limits(board, &SearchLimits::from_depth(max_depth), |_| ())
+    }
+
+    /// Searches the position until one of the limits is reached (or the stop flag is raised)
+    /// and calls the provided function with the result of every completed iteration
+    ///
+    /// An interrupted iteration is thrown away. The first iteration is always completed
+    /// so there is a move to play
+    pub fn search_with_limits<F: FnMut(&SearchResult)>(&mut self, board: &mut Board, limits: &SearchLimits, mut on_iteration: F) -> SearchResult {
+        self.tt.new_search();
+        if self.eval_noise > 0 {
+            self.eval_noise_seed = rand::rng().random();
+        }
+
+        if let Some(result) = self.search_tablebase_root(board, &mut on_iteration) {
+            return result;
+        }
+
+        #[cfg(feature = "smp")]
+        if self.thread_count > 1 {
+            return self.search_with_helpers(board, limits, on_iteration);
+        }
+
+        self.iterative_deepening(board, limits, 1, on_iteration)
+    }
+
+    /// Searches the position the way a player of the skill level would and returns the move it plays
+    /// (or *None* if there are no legal moves). The limits only get tighter, never looser
+    ///
+    /// The MultiPV and the evaluation noise of the searcher are restored afterwards
+    pub fn search_with_skill(&mut self, board: &mut Board, skill: SkillLevel, limits: &SearchLimits, rng: &mut impl Rng) -> Option<Move> {
+        let previous_multi_pv = self.get_multi_pv();
+        let previous_eval_noise = self.get_eval_noise();
+        self.set_multi_pv(skill.get_multi_pv());
+        self.set_eval_noise(skill.get_eval_noise());
+
+        let result = self.search_with_limits(board, &skill.limit(limits), |_| ());
+
+        self.set_multi_pv(previous_multi_pv);
+        self.set_eval_noise(previous_eval_noise);
+
+        skill.pick_move(&result.lines, rng).or(result.best_move)
+    }
+
+    /// Creates a searcher sharing the transposition table, the options and the tablebase of this one,
+    /// so a search running separately (like a stepped search) still benefits from the entries of the previous ones
+    pub fn fork(&self) -> Searcher {
+        let mut searcher = Searcher::with_transposition_table(Arc::clone(&self.tt));
+        searcher.set_multi_pv(self.multi_pv);
+        searcher.set_options(self.options);
+        searcher.set_tablebase(self.tablebase.clone());
+
+        searcher
+    }
+
+    /// Resets the state kept between the iterations, called once before the first step of a stepped search
+    pub(super) fn begin_stepped_search(&mut self) {
+        self.tt.new_search();
+        self.heuristics.new_search();
+        self.seldepth = 0;
+        self.completed_depth = 0;
+        self.is_resumable = true;
+        self.frames.fill_with(|| None);
+        self.iteration_frame = None;
+    }
+
+    /// Starts a step of a stepped search, which stops after the provided amount of nodes or milliseconds.
+    /// Only the limits of the step are reset, everything the iterations need is kept
+    pub(super) fn begin_step(&mut self, node_limit: Option<u64>, time_limit_ms: Option<u64>) {
+        self.node_limit = node_limit;
+        self.time_manager = Some(TimeManager::with_hard_limit(time_limit_ms));
+        self.nodes = 0;
+        self.stopped = false;
+        self.is_resuming = self.iteration_frame.is_some();
+    }
+
+    /// Returns the nodes and the milliseconds of the current step
+    pub(super) fn get_step_usage(&self) -> (u64, u64) {
+        (self.nodes, self.get_elapsed_ms())
+    }
+
+    pub(super) fn end_step(&mut self) {
+        self.time_manager = None;
+    }
+
+    /// Lazy SMP: the helper threads search the same position without any coordination,
+    /// sharing only the transposition table. The main thread benefits from the entries they store
+    /// and its result is the result of the search. The helpers stop once the main thread is done
+    #[cfg(feature = "smp")]
+    fn search_with_helpers<F: FnMut(&SearchResult)>(&mut self, board: &mut Board, limits: &SearchLimits, on_iteration: F) -> SearchResult {
+        let helpers_stop_flag = Arc::new(AtomicBool::new(false));
+        let helper_limits = SearchLimits {
+            infinite: true,
+            ..SearchLimits::default()
+        };
+
+        std::thread::scope(|scope| {
+            let helper_threads: Vec<_> = (1..self.thread_count).map(|helper_index| {
+                let mut helper_board = board.clone();
+                let mut helper = Searcher::with_transposition_table(Arc::clone(&self.tt));
+                helper.set_stop_flag(Arc::clone(&helpers_stop_flag));
+                helper.set_options(self.options);
+                helper.set_tablebase(self.tablebase.clone());
+                helper.eval_noise = self.eval_noise;
+                helper.eval_noise_seed = self.eval_noise_seed;
+                // every second helper starts a ply deeper so the threads don't go through the same depths together
+                let start_depth = 1 + (helper_index % 2) as u8;
+                let helper_limits = &helper_limits;
+
+                scope.spawn(move || helper.iterative_deepening(&mut helper_board, helper_limits, start_depth, |_| ()).nodes)
+            }).collect();
+
+            let mut result = self.iterative_deepening(board, limits, 1, on_iteration);
+            helpers_stop_flag.store(true, Ordering::Relaxed);
+
+            for helper_thread in helper_threads {
+                result.nodes += helper_thread.join().expect("Helper search thread panicked");
+            }
+
+            result
+        })
+    }
+
+    /// When the root position is in the DTZ tables, there is nothing to search: the moves are ranked
+    /// by the tables, which keeps the won positions winning within the 50 moves rule
+    pub(super) fn search_tablebase_root<F: FnMut(&SearchResult)>(&mut self, board: &mut Board, on_iteration: &mut F) -> Option<SearchResult> {
+        let ranked_moves = self.tablebase.as_ref()?.rank_root_moves(board)?;
+        let best_move = ranked_moves.first()?;
+
+        let lines: Vec<PvLine> = ranked_moves.iter()
+            .take(self.multi_pv)
+            .map(|ranked_move| PvLine {
+                score: match ranked_move.wdl {
+                    Wdl::Win => TB_WIN_SCORE - 1,
+                    Wdl::Loss => -TB_WIN_SCORE + 1,
+                    wdl => wdl as i32, // the cursed results are only a centipawn away from the draw
+                },
+                depth: 1,
+                pv: vec![ranked_move.mov],
+            })
+            .collect();
+
+        let result = SearchResult {
+            best_move: Some(best_move.mov),
+            score: lines[0].score,
+            depth: 1,
+            seldepth: 1,
+            pv: lines[0].pv.clone(),
+            lines,
+            nodes: 1,
+            hashfull: self.tt.get_hashfull(),
+            time_ms: 0,
+        };
+
+        on_iteration(&result);
+        Some(result)
+    }
+
+    fn iterative_deepening<F: FnMut(&SearchResult)>(&mut self, board: &mut Board, limits: &SearchLimits, start_depth: u8, mut on_iteration: F) -> SearchResult {
+        let max_depth = match limits.depth {
+            Some(depth) if !limits.infinite => depth.clamp(1, (MAX_PLY - 1) as u8),
+            _ => (MAX_PLY - 1) as u8,
+        };
+        self.node_limit = if limits.infinite { None } else { limits.nodes };
+        self.time_manager = Some(TimeManager::new(limits, board.get_current_color()));
+        self.nodes = 0;
+        self.seldepth = 0;
+        self.stopped = false;
+        self.completed_depth = 0;
+        self.is_resumable = false;
+        self.is_resuming = false;
+        self.iteration_frame = None;
+        self.heuristics.new_search();
+
+        let mut result = SearchResult {
+            best_move: None,
+            score: 0,
+            depth: 0,
+            seldepth: 0,
+            pv: Vec::new(),
+            lines: Vec::new(),
+            nodes: 0,
+            hashfull: 0,
+            time_ms: 0,
+        };
+
+        for depth in start_depth..=max_depth {
+            let is_completed = self.search_iteration(board, depth, &mut result);
+            result.nodes = self.nodes;
+            result.time_ms = self.get_elapsed_ms();
+
+            if !is_completed {
+                break;
+            }
+
+            on_iteration(&result);
+
+            if result.best_move.is_none() {
+                break; // checkmate or stalemate in the root position, nothing to search
+            }
+
+            if self.time_manager.as_ref().is_some_and(|time_manager| time_manager.is_soft_limit_reached()) {
+                break;
+            }
+        }
+
+        self.time_manager = None;
+        result
+    }
+
+    /// Searches every MultiPV slot to the provided depth and puts the lines into the result,
+    /// except for the nodes and the time which depend on the caller
+    ///
+    /// Returns false if the search got stopped, the result is left untouched then.
+    /// In a stepped search the next call with the same depth continues the interrupted iteration
+    pub(super) fn search_iteration(&mut self, board: &mut Board, depth: u8, result: &mut SearchResult) -> bool {
+        let (first_pv_index, mut lines, mut excluded_moves, mut resumed_window) = match self.iteration_frame.take() {
+            Some(frame) => (frame.pv_index, frame.lines, frame.excluded_moves, Some(frame.window)),
+            None => (0, Vec::with_capacity(self.multi_pv), Vec::with_capacity(self.multi_pv), None),
+        };
+        let mut root_score = 0;
+
+        for pv_index in first_pv_index..self.multi_pv {
+            // searching the best move of the same slot in the previous iteration first
+            let mut window = resumed_window.take().unwrap_or_else(|| {
+                let previous_line = result.lines.get(pv_index);
+                let hash_move = previous_line.and_then(|line| line.pv.first().copied());
+                AspirationWindow::new(self.options, depth, hash_move, previous_line.map(|line| line.score))
+            });
+            let score = self.search_root_with_aspiration(board, depth, &excluded_moves, &mut window);
+
+            if self.stopped {
+                if self.is_resumable {
+                    self.iteration_frame = Some(IterationFrame { pv_index, lines, excluded_moves, window });
+                }
+                return false;
+            }
+
+            let Some(&root_move) = self.pv_lines[0].first() else {
+                if pv_index == 0 {
+                    root_score = score; // checkmate or stalemate in the root position
+                }
+                break; // no root moves left
+            };
+
+            excluded_moves.push(root_move);
+            lines.push(PvLine {
+                score,
+                depth,
+                pv: self.pv_lines[0].clone(),
+            });
+        }
+
+        // a later line may score higher than the previous ones when the transposition table gives it a deeper result
+        lines.sort_by_key(|line| Reverse(line.score));
+
+        if let Some(best_line) = lines.first() {
+            result.score = best_line.score;
+            result.pv = best_line.pv.clone();
+        } else {
+            result.score = root_score;
+            result.pv.clear();
+        }
+        result.depth = depth;
+        result.seldepth = self.seldepth as u8;
+        result.best_move = result.pv.first().copied();
+        result.lines = lines;
+        result.hashfull = self.tt.get_hashfull();
+        self.completed_depth = depth;
+
+        true
+    }
+
+    /// Searches the root with a narrow window around the score of the previous iteration, since the score
+    /// rarely changes much between the iterations and a narrow window cuts off way more nodes.
+    /// If the score falls outside, the window is widened on that side and the root is searched again
+    fn search_root_with_aspiration(&mut self, board: &mut Board, depth: u8, excluded_moves: &[Move], window: &mut AspirationWindow) -> i32 {
+        loop {
+            let score = self.search_root(board, depth, excluded_moves, window.hash_move, window.alpha, window.beta);
+
+            if self.stopped {
+                return score;
+            }
+
+            if score <= window.alpha && window.alpha > -INFINITY {
+                window.alpha = (window.alpha - window.delta).max(-INFINITY);
+            } else if score >= window.beta && window.beta < INFINITY {
+                window.beta = (window.beta + window.delta).min(INFINITY);
+                // the move that failed high is the best candidate for the next search
+                window.hash_move = self.pv_lines[0].first().copied().or(window.hash_move);
+            } else {
+                return score;
+            }
+
+            window.delta *= 2;
+        }
+    }
+
+    /// Searches every root move except the excluded ones (root moves of the previous MultiPV lines)
+    /// within the provided window
+    ///
+    /// The best line is left in the PV table, which stays empty if there are no moves to search
+    /// or none of them raises alpha
+    fn search_root(&mut self, board: &mut Board, depth: u8, excluded_moves: &[Move], hash_move: Option<Move>, alpha: i32, beta: i32) -> i32 {
+        let original_alpha = alpha;
+
+        let MoveLoopFrame { mut move_picker, mut alpha, mut pending_move } = match self.take_frame(0) {
+            Some(NodeFrame::Root(move_loop)) => move_loop,
+            _ => {
+                if self.check_stop() {
+                    return alpha;
+                }
+
+                self.nodes += 1;
+                self.pv_lines[0].clear();
+
+                let attack_calc = AttackCalculator::new(board);
+                let mut moves = std::mem::take(&mut self.move_buffers[0]);
+                moves.clear();
+                move_gen::generate_moves(&mut moves, board, &attack_calc);
+
+                if moves.is_empty() {
+                    self.move_buffers[0] = moves;
+                    return if attack_calc.in_check() { -MATE_SCORE } else { DRAW_SCORE };
+                }
+
+                moves.retain(|mov| !excluded_moves.contains(mov));
+
+                // The best move of the previous iteration is searched first so it can't be lost
+                // and gives the best alpha-beta window for the rest of the moves
+                let scores = std::mem::take(&mut self.score_buffers[0]);
+                let move_picker = MovePicker::new(moves, scores, board, hash_move, &self.heuristics, 0);
+
+                MoveLoopFrame { move_picker, alpha, pending_move: None }
+            }
+        };
+
+        while let Some(mov) = pending_move.take().or_else(|| move_picker.next()) {
+            let move_record = board.make_move(mov);
+            let score = -self.negamax(board, depth - 1, 1, -beta, -alpha, true);
+            board.undo_move(move_record);
+
+            if self.stopped {
+                self.suspend(0, NodeFrame::Root(MoveLoopFrame { move_picker, alpha, pending_move: Some(mov) }));
+                return alpha;
+            }
+
+            if score > alpha {
+                alpha = score;
+                self.update_pv(0, mov);
+
+                if alpha >= beta {
+                    break; // fail high, the aspiration window has to be widened
+                }
+            }
+        }
+
+        (self.move_buffers[0], self.score_buffers[0]) = move_picker.into_buffers();
+
+        // with excluded moves the score isn't the score of the position,
+        // outside of the window it is only a bound
+        let is_exact = alpha > original_alpha && alpha < beta;
+        if excluded_moves.is_empty() && is_exact {
+            self.tt.store(board.get_zobrist_hash(), depth, Bound::Exact, transposition_table::score_to_tt(alpha, 0), self.pv_lines[0].first().copied());
+        }
+        alpha
+    }
+
+    /// *allow_null_move* is false right after a null move, passing twice in a row would only waste nodes
+    fn negamax(&mut self, board: &mut Board, depth: u8, ply: usize, alpha: i32, beta: i32, allow_null_move: bool) -> i32 {
+        let hash = board.get_zobrist_hash();
+
+        let (depth, in_check, static_eval, tt_move, resumed_moves) = match self.take_frame(ply) {
+            Some(frame @ (NodeFrame::Leaf | NodeFrame::Quiescence { .. })) => {
+                // the quiescence search of the interrupted leaf continues from the frame
+                self.frames[ply] = Some(frame);
+                return self.quiescence(board, ply, 0, alpha, beta);
+            }
+            Some(NodeFrame::NullMove { depth, in_check, static_eval, tt_move }) => (depth, in_check, static_eval, tt_move, None),
+            Some(NodeFrame::Moves { depth, in_check, move_loop }) => (depth, in_check, None, None, Some(move_loop)),
+            _ => {
+                if self.check_stop() {
+                    return 0;
+                }
+
+                self.nodes += 1;
+                self.seldepth = self.seldepth.max(ply);
+                self.pv_lines[ply].clear();
+
+                if board.is_draw_by_repetition() || board.is_draw_by_50_moves_rule() || board.is_draw_by_material() {
+                    return DRAW_SCORE;
+                }
+
+                if ply >= MAX_PLY - 1 {
+                    return self.get_static_eval(board);
+                }
+
+                let in_check = AttackCalculator::new(board).in_check();
+
+                // the check has to be resolved before the position can be judged, so it doesn't count as a ply
+                let depth = if in_check && self.options.check_extensions { depth + 1 } else { depth };
+
+                if depth == 0 {
+                    let score = self.quiescence(board, ply, 0, alpha, beta);
+                    if self.stopped && self.frames[ply].is_none() {
+                        self.suspend(ply, NodeFrame::Leaf);
+                    }
+                    return score;
+                }
+
+                let mut tt_move = None;
+
+                if let Some(entry) = self.tt.probe(hash) {
+                    tt_move = entry.get_best_move();
+
+                    if entry.get_depth() >= depth {
+                        let tt_score = transposition_table::score_from_tt(entry.get_score(), ply);
+                        let is_cutoff = match entry.get_bound() {
+                            Bound::Exact => true,
+                            Bound::Lower => tt_score >= beta,
+                            Bound::Upper => tt_score <= alpha,
+                        };
+
+                        if is_cutoff {
+                            return tt_score;
+                        }
+                    }
+                }
+
+                // The tables only know about the positions without castling rights, and the results
+                // are only exact right after a zeroing move since the 50 moves counter isn't stored
+                if let Some(tablebase) = &self.tablebase
+                    && board.rule50_count() == 0
+                    && tablebase.can_probe(board)
+                    && let Some(wdl) = tablebase.probe_wdl(board) {
+                    let (score, bound) = match wdl {
+                        Wdl::Win => (TB_WIN_SCORE - ply as i32, Bound::Lower),
+                        Wdl::Loss => (-TB_WIN_SCORE + ply as i32, Bound::Upper),
+                        _ => (DRAW_SCORE, Bound::Exact),
+                    };
+
+                    // a win may still be a mate, which scores higher
+                    let is_cutoff = match bound {
+                        Bound::Exact => true,
+                        Bound::Lower => score >= beta,
+                        Bound::Upper => score <= alpha,
+                    };
+
+                    if is_cutoff {
+                        self.tt.store(hash, depth, bound, transposition_table::score_to_tt(score, ply), None);
+                        return score;
+                    }
+                }
+
+                // only the PV nodes are searched with a window wider than a null window
+                let is_pv = beta - alpha > 1;
+                let static_eval = if in_check || is_pv { None } else { Some(self.get_static_eval(board)) };
+
+                (depth, in_check, static_eval, tt_move, None)
+            }
+        };
+
+        // a node resumed from the null move frame passes all the conditions again
+        if resumed_moves.is_none()
+            && self.options.null_move_pruning
+            && allow_null_move
+            && depth >= NULL_MOVE_MIN_DEPTH
+            && static_eval.is_some_and(|static_eval| static_eval >= beta)
+            && has_non_pawn_material(board) {
+            let reduction = NULL_MOVE_REDUCTION + depth / NULL_MOVE_DEPTH_DIVISOR;
+
+            let null_move_record = board.make_null_move();
+            let score = -self.negamax(board, depth.saturating_sub(1 + reduction), ply + 1, -beta, -beta + 1, false);
+            board.undo_null_move(null_move_record);
+
+            if self.stopped {
+                self.suspend(ply, NodeFrame::NullMove { depth, in_check, static_eval, tt_move });
+                return 0;
+            }
+
+            // Even passing the turn keeps the score above beta, so a real move would too.
+            // Mates found after passing aren't proven, so they aren't returned
+            if score >= beta {
+                return if is_mate_score(score) { beta } else { score };
+            }
+        }
+
+        let original_alpha = alpha;
+        let NegamaxMoveLoop { is_futile, mut move_picker, mut alpha, mut best_score, mut best_move, mut moves_searched, mut pending_move } = match resumed_moves {
+            Some(move_loop) => move_loop,
+            None => {
+                let attack_calc = AttackCalculator::new(board);
+                let mut moves = std::mem::take(&mut self.move_buffers[ply]);
+                moves.clear();
+                move_gen::generate_moves(&mut moves, board, &attack_calc);
+
+                if moves.is_empty() {
+                    self.move_buffers[ply] = moves;
+                    return if in_check { -MATE_SCORE + ply as i32 } else { DRAW_SCORE };
+                }
+
+                let is_futile = self.options.futility_pruning
+                    && depth <= FUTILITY_MAX_DEPTH
+                    && !is_mate_score(alpha)
+                    && static_eval.is_some_and(|static_eval| static_eval + FUTILITY_MARGIN * depth as i32 <= alpha);
+
+                let scores = std::mem::take(&mut self.score_buffers[ply]);
+                NegamaxMoveLoop {
+                    is_futile,
+                    move_picker: MovePicker::new(moves, scores, board, tt_move, &self.heuristics, ply),
+                    alpha,
+                    best_score: -INFINITY,
+                    best_move: None,
+                    moves_searched: 0,
+                    pending_move: None,
+                }
+            }
+        };
+
+        while let Some((mov, pending_search)) = pending_move.take().map(|(mov, search)| (mov, Some(search)))
+            .or_else(|| move_picker.next().map(|mov| (mov, None))) {
+            let is_quiet = move_ordering::is_quiet(board, &mov);
+            let can_reduce = self.options.late_move_reductions
+                && depth >= LMR_MIN_DEPTH
+                && moves_searched >= LMR_FULL_DEPTH_MOVES
+                && !in_check;
+
+            let move_record = board.make_move(mov);
+
+            // the checks are never pruned nor reduced, so they are only looked for when it matters
+            let is_prunable = is_quiet && moves_searched > 0 && (is_futile || can_reduce)
+                && !AttackCalculator::new(board).in_check();
+
+            if is_prunable && is_futile {
+                board.undo_move(move_record);
+                continue; // futility pruning
+            }
+
+            // Principal variation search: only the first move is expected to be the best one, so the rest
+            // are searched with a null window just to prove they are worse, which is way cheaper.
+            // A reduced search saying the move might be good has to be proven at the full depth
+            let reduction = if is_prunable && can_reduce { get_late_move_reduction(depth, moves_searched) } else { 0 };
+            let mut child_search = pending_search.unwrap_or(if moves_searched == 0 { ChildSearch::FullWindow } else { ChildSearch::Reduced });
+            let score = loop {
+                let score = match child_search {
+                    ChildSearch::Reduced => -self.negamax(board, (depth - 1).saturating_sub(reduction), ply + 1, -alpha - 1, -alpha, true),
+                    ChildSearch::FullDepth => -self.negamax(board, depth - 1, ply + 1, -alpha - 1, -alpha, true),
+                    ChildSearch::FullWindow => -self.negamax(board, depth - 1, ply + 1, -beta, -alpha, true),
+                };
+
+                if self.stopped {
+                    break score;
+                }
+
+                child_search = match child_search {
+                    ChildSearch::Reduced if score > alpha && reduction > 0 => ChildSearch::FullDepth,
+                    ChildSearch::Reduced | ChildSearch::FullDepth if score > alpha && score < beta => ChildSearch::FullWindow,
+                    _ => break score,
+                };
+            };
+            board.undo_move(move_record);
+
+            if self.stopped {
+                let move_loop = NegamaxMoveLoop {
+                    is_futile,
+                    move_picker,
+                    alpha,
+                    best_score,
+                    best_move,
+                    moves_searched,
+                    pending_move: Some((mov, child_search)),
+                };
+                self.suspend(ply, NodeFrame::Moves { depth, in_check, move_loop });
+                return 0;
+            }
+            moves_searched += 1;
+
+            if score <= best_score {
+                continue;
+            }
+            best_score = score;
+            best_move = Some(mov);
+
+            if score > alpha {
+                alpha = score;
+                self.update_pv(ply, mov);
+
+                if alpha >= beta {
+                    if move_ordering::is_quiet(board, &mov) {
+                        self.heuristics.update_on_cutoff(board, mov, move_picker.get_picked_moves(), ply, depth);
+                    }
+                    break; // the opponent will never allow this position
+                }
+            }
+        }
+
+        (self.move_buffers[ply], self.score_buffers[ply]) = move_picker.into_buffers();
+
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if best_score > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        // In fail low nodes every move is bad so there is no point remembering any of them
+        let best_move = if bound == Bound::Upper { None } else { best_move };
+        self.tt.store(hash, depth, bound, transposition_table::score_to_tt(best_score, ply), best_move);
+
+        best_score
+    }
+
+    /// Extends the leaf nodes through loud moves until the position becomes quiet, 
+    /// so the evaluation is never taken in the middle of an exchange
+    /// 
+    /// The side to move may "stand pat" (take the static evaluation) instead of capturing, 
+    /// unless it is in check, in which case every evasion is searched
+    fn quiescence(&mut self, board: &mut Board, ply: usize, quiescence_ply: usize, alpha: i32, beta: i32) -> i32 {
+        let (in_check, stand_pat, mut best_score, move_loop) = match self.take_frame(ply) {
+            Some(NodeFrame::Quiescence { in_check, stand_pat, best_score, move_loop }) => (in_check, stand_pat, best_score, move_loop),
+            _ => {
+                // the frame of a leaf interrupted before its quiescence search is the end of the interrupted path
+                self.is_resuming = false;
+
+                if self.check_stop() {
+                    return 0;
+                }
+
+                self.nodes += 1;
+                self.seldepth = self.seldepth.max(ply);
+                self.pv_lines[ply].clear();
+
+                if board.is_draw_by_50_moves_rule() || board.is_draw_by_material() {
+                    return DRAW_SCORE;
+                }
+
+                if ply >= MAX_PLY - 1 {
+                    return self.get_static_eval(board);
+                }
+
+                let attack_calc = AttackCalculator::new(board);
+                let in_check = attack_calc.in_check();
+
+                let mut moves = std::mem::take(&mut self.move_buffers[ply]);
+                moves.clear();
+                move_gen::generate_moves(&mut moves, board, &attack_calc);
+
+                if moves.is_empty() {
+                    self.move_buffers[ply] = moves;
+                    return if in_check { -MATE_SCORE + ply as i32 } else { DRAW_SCORE };
+                }
+
+                let mut loud_moves = std::mem::take(&mut self.loud_move_buffers[ply]);
+                loud_moves.clear();
+
+                let mut alpha = alpha;
+                let mut stand_pat = -INFINITY;
+                if in_check {
+                    loud_moves.extend_from_slice(&moves);
+                } else {
+                    stand_pat = self.get_static_eval(board);
+
+                    if stand_pat >= beta {
+                        self.move_buffers[ply] = moves;
+                        self.loud_move_buffers[ply] = loud_moves;
+                        return stand_pat;
+                    }
+                    alpha = alpha.max(stand_pat);
+
+                    move_gen::filter_loud_moves(&moves, &mut loud_moves, &attack_calc, board);
+                }
+                self.move_buffers[ply] = moves;
+
+                let scores = std::mem::take(&mut self.score_buffers[ply]);
+                let move_picker = MovePicker::new(loud_moves, scores, board, None, &self.heuristics, ply);
+
+                (in_check, stand_pat, stand_pat, MoveLoopFrame { move_picker, alpha, pending_move: None })
+            }
+        };
+        let MoveLoopFrame { mut move_picker, mut alpha, mut pending_move } = move_loop;
+
+        while let Some(mov) = pending_move.take().or_else(|| move_picker.next()) {
+            if !in_check && !mov.is_promotion() {
+                let (_, captured_piece_type) = board.get_piece_on_square(mov.capture_square);
+
+                if captured_piece_type == piece::NONE && quiescence_ply >= QUIESCENCE_CHECK_PLIES {
+                    continue;
+                }
+
+                if stand_pat + eval::get_piece_value(captured_piece_type) + DELTA_MARGIN <= alpha {
+                    continue; // delta pruning
+                }
+
+                if captured_piece_type != piece::NONE && !see::see_ge(board, &mov, 0) {
+                    continue; // the capture loses material, standing pat is better
+                }
+            }
+
+            let move_record = board.make_move(mov);
+            let score = -self.quiescence(board, ply + 1, quiescence_ply + 1, -beta, -alpha);
+            board.undo_move(move_record);
+
+            if self.stopped {
+                let move_loop = MoveLoopFrame { move_picker, alpha, pending_move: Some(mov) };
+                self.suspend(ply, NodeFrame::Quiescence { in_check, stand_pat, best_score, move_loop });
+                return 0;
+            }
+
+            if score <= best_score {
+                continue;
+            }
+            best_score = score;
+
+            if score > alpha {
+                alpha = score;
+
+                if alpha >= beta {
+                    break;
+                }
+            }
+        }
+
+        (self.loud_move_buffers[ply], self.score_buffers[ply]) = move_picker.into_buffers();
+        best_score
+    }
+
+    /// Evaluation of the position with the noise added
+    fn get_static_eval(&self, board: &Board) -> i32 {
+        let score = evaluate(board);
+        if self.eval_noise == 0 {
+            return score;
+        }
+
+        // splitmix64 finalizer, spreads the bits of the hash so close hashes get unrelated offsets
+        let mut mixed = board.get_zobrist_hash() ^ self.eval_noise_seed;
+        mixed = (mixed ^ (mixed >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        mixed = (mixed ^ (mixed >> 27)).wrapping_mul(0x94D049BB133111EB);
+        mixed ^= mixed >> 31;
+
+        score + (mixed % (self.eval_noise as u64 * 2 + 1)) as i32 - self.eval_noise
+    }
+
+    /// Returns true if the search has to be stopped. Only the first iteration can't be interrupted,
+    /// except in a stepped search where the next step continues it
+    fn check_stop(&mut self) -> bool {
+        if self.stopped {
+            return true;
+        }
+
+        if self.completed_depth == 0 && !self.is_resumable {
+            return false;
+        }
+
+        let is_node_limit_reached = self.node_limit.is_some_and(|node_limit| self.nodes >= node_limit);
+        let is_check_due = self.nodes & (STOP_CHECK_INTERVAL - 1) == 0;
+        let is_time_over = is_check_due && self.time_manager.as_ref().is_some_and(|time_manager| time_manager.is_hard_limit_reached());
+        self.stopped = is_node_limit_reached || is_time_over || is_check_due && self.stop_flag.load(Ordering::Relaxed);
+
+        self.stopped
+    }
+
+    fn get_elapsed_ms(&self) -> u64 {
+        self.time_manager.as_ref().map_or(0, |time_manager| time_manager.get_elapsed_ms())
+    }
+
+    /// Makes the provided move followed by the line of the next ply the best line for the current ply
+    fn update_pv(&mut self, ply: usize, mov: Move) {
+        let (current_lines, next_lines) = self.pv_lines.split_at_mut(ply + 1);
+        let line = &mut current_lines[ply];
+        line.clear();
+        line.push(mov);
+        line.extend_from_slice(&next_lines[0]);
+    }
+
+    /// Returns the frame the node at the provided ply left at the end of the previous step, while the step
+    /// is still going down the interrupted path. The first node without a frame ends the path
+    fn take_frame(&mut self, ply: usize) -> Option<NodeFrame> {
+        if !self.is_resuming {
+            return None;
+        }
+
+        let frame = self.frames[ply].take();
+        if frame.is_none() {
+            self.is_resuming = false;
+        }
+        frame
+    }
+
+    /// Keeps the frame of a node interrupted by the end of a step. Outside of a stepped search
+    /// the interrupted node is thrown away, only the buffers of its move picker are given back
+    fn suspend(&mut self, ply: usize, frame: NodeFrame) {
+        if self.is_resumable {
+            self.frames[ply] = Some(frame);
+            return;
+        }
+
+        match frame {
+            NodeFrame::Root(move_loop) => (self.move_buffers[ply], self.score_buffers[ply]) = move_loop.move_picker.into_buffers(),
+            NodeFrame::Moves { move_loop, .. } => (self.move_buffers[ply], self.score_buffers[ply]) = move_loop.move_picker.into_buffers(),
+            NodeFrame::Quiescence { move_loop, .. } => (self.loud_move_buffers[ply], self.score_buffers[ply]) = move_loop.move_picker.into_buffers(),
+            NodeFrame::NullMove { .. } | NodeFrame::Leaf => (),
+        }
+    }
+}
+
+impl Default for Searcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
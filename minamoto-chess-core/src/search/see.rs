@@ -0,0 +1,250 @@
+/*Static exchange evaluation: resolves the sequence of captures on a single square, with both sides
+always capturing with their least valuable piece and stopping whenever continuing would lose material.
+
+Pins and checks are ignored, so the result is an estimation that is good enough for pruning and ordering*/
+
+use crate::{bitboards, board::Board, eval::{self, pawn_structure}, piece, precomputed_data, r#move::{Move, MoveType}};
+
+use super::move_ordering;
+
+// constants
+/// Way bigger than any other piece so capturing the king is never considered a fair trade
+const KING_VALUE: i32 = 20_000;
+/// Max length of a capture sequence on a single square (every piece on the board takes part)
+const MAX_EXCHANGE_LENGTH: usize = 32;
+/// Pieces from the least to the most valuable, in the order they enter the exchange
+const EXCHANGE_ORDER: [usize; 6] = [piece::PAWN, piece::KNIGHT, piece::BISHOP, piece::ROOK, piece::QUEEN, piece::KING];
+
+fn get_see_value(piece_type: usize) -> i32 {
+    if piece_type == piece::KING {
+        KING_VALUE
+    } else {
+        eval::get_piece_value(piece_type)
+    }
+}
+
+fn get_promotion_piece_type(move_type: MoveType) -> usize {
+    match move_type {
+        MoveType::PromotionQueen => piece::QUEEN,
+        MoveType::PromotionKnight => piece::KNIGHT,
+        MoveType::PromotionRook => piece::ROOK,
+        MoveType::PromotionBishop => piece::BISHOP,
+        _ => piece::NONE,
+    }
+}
+
+/// Returns the bishops, rooks and queens of both colors attacking the square with the provided occupancy
+///
+/// Called again after every capture so the sliders standing behind the captured pieces get discovered (x-rays)
+fn get_slider_attackers_to(board: &Board, square: usize, all_occ: u64) -> u64 {
+    let queens = board.get_piece_bitboard(piece::WHITE, piece::QUEEN) | board.get_piece_bitboard(piece::BLACK, piece::QUEEN);
+    let bishops_queens = board.get_piece_bitboard(piece::WHITE, piece::BISHOP) | board.get_piece_bitboard(piece::BLACK, piece::BISHOP) | queens;
+    let rooks_queens = board.get_piece_bitboard(piece::WHITE, piece::ROOK) | board.get_piece_bitboard(piece::BLACK, piece::ROOK) | queens;
+
+    ((precomputed_data::BISHOP_MAGIC_LOOKUP_TABLE.get_pseudo_legal_move_bb(square, all_occ) & bishops_queens)
+        | (precomputed_data::ROOK_MAGIC_LOOKUP_TABLE.get_pseudo_legal_move_bb(square, all_occ) & rooks_queens))
+        & all_occ
+}
+
+/// Returns every piece of both colors attacking the square with the provided occupancy
+fn get_attackers_to(board: &Board, square: usize, all_occ: u64) -> u64 {
+    let square_bb = bitboards::get_bit_from_square(square);
+    let knights = board.get_piece_bitboard(piece::WHITE, piece::KNIGHT) | board.get_piece_bitboard(piece::BLACK, piece::KNIGHT);
+    let kings = board.get_piece_bitboard(piece::WHITE, piece::KING) | board.get_piece_bitboard(piece::BLACK, piece::KING);
+
+    // a pawn attacks the square if a pawn of the opposite color standing on the square would attack the pawn
+    let pawns = (pawn_structure::get_pawn_attacks(square_bb, piece::BLACK) & board.get_piece_bitboard(piece::WHITE, piece::PAWN))
+        | (pawn_structure::get_pawn_attacks(square_bb, piece::WHITE) & board.get_piece_bitboard(piece::BLACK, piece::PAWN));
+
+    let attackers = pawns
+        | (precomputed_data::SQUARE_DATA.get_bb_for_knight(square) & knights)
+        | (precomputed_data::SQUARE_DATA.get_bb_for_king(square) & kings);
+
+    (attackers & all_occ) | get_slider_attackers_to(board, square, all_occ)
+}
+
+/// Returns the type and the bitboard of the least valuable piece among the attackers of the provided color
+fn get_least_valuable_attacker(board: &Board, color: usize, color_attackers: u64) -> (usize, u64) {
+    for piece_type in EXCHANGE_ORDER {
+        let piece_attackers = color_attackers & board.get_piece_bitboard(color, piece_type);
+
+        if piece_attackers != 0 {
+            return (piece_type, piece_attackers & piece_attackers.wrapping_neg());
+        }
+    }
+
+    (piece::NONE, 0)
+}
+
+/// Position right after the provided move was made, as seen by the exchange
+struct ExchangeStart {
+    color: usize,
+    /// Material won by the move itself, promotion included
+    first_gain: i32,
+    /// Value of the piece standing on the target square after the move
+    piece_on_target_value: i32,
+    all_occ: u64,
+}
+
+fn get_exchange_start(board: &Board, mov: &Move) -> ExchangeStart {
+    let (color, moving_piece_type) = board.get_piece_on_square(mov.start_square);
+    let (_, captured_piece_type) = board.get_piece_on_square(mov.capture_square);
+
+    let promotion_piece_type = get_promotion_piece_type(mov.move_type);
+    let (first_gain, piece_on_target_value) = if promotion_piece_type != piece::NONE {
+        let promotion_gain = get_see_value(promotion_piece_type) - get_see_value(piece::PAWN);
+        (get_see_value(captured_piece_type) + promotion_gain, get_see_value(promotion_piece_type))
+    } else {
+        (get_see_value(captured_piece_type), get_see_value(moving_piece_type))
+    };
+
+    // the capture square differs from the target square for en passant
+    let all_occ = (board.get_all_occupied_squares()
+        & !bitboards::get_bit_from_square(mov.start_square)
+        & !bitboards::get_bit_from_square(mov.capture_square))
+        | bitboards::get_bit_from_square(mov.target_square);
+
+    ExchangeStart {
+        color,
+        first_gain,
+        piece_on_target_value,
+        all_occ,
+    }
+}
+
+/// Returns the material balance (in centipawns, from the side making the move)
+/// after every profitable recapture on the target square of the provided move
+///
+/// Castling always returns 0 since it can't win or lose anything by itself
+pub fn see(board: &Board, mov: &Move) -> i32 {
+    if mov.is_castling() {
+        return 0;
+    }
+
+    let target_square = mov.target_square;
+    let ExchangeStart { color, first_gain, mut piece_on_target_value, mut all_occ } = get_exchange_start(board, mov);
+
+    // gains[depth] is the balance for the side making the capture at that depth if the exchange stops right after it
+    let mut gains = [0; MAX_EXCHANGE_LENGTH];
+    gains[0] = first_gain;
+    let mut depth = 0;
+
+    let mut attackers = get_attackers_to(board, target_square, all_occ);
+    let mut side = 1 - color;
+
+    while depth < MAX_EXCHANGE_LENGTH - 1 {
+        let side_attackers = attackers & board.get_all_occupied_squares_for_color(side);
+        if side_attackers == 0 {
+            break;
+        }
+
+        let (attacker_type, attacker_bb) = get_least_valuable_attacker(board, side, side_attackers);
+        // the king can't capture on a defended square
+        if attacker_type == piece::KING && attackers & board.get_all_occupied_squares_for_color(1 - side) != 0 {
+            break;
+        }
+
+        depth += 1;
+        gains[depth] = piece_on_target_value - gains[depth - 1];
+        piece_on_target_value = get_see_value(attacker_type);
+
+        all_occ &= !attacker_bb;
+        attackers = (attackers | get_slider_attackers_to(board, target_square, all_occ)) & all_occ;
+        side = 1 - side;
+    }
+
+    // every side may refuse to continue the exchange, so going backwards
+    // each side picks the best between stopping and capturing
+    while depth > 0 {
+        gains[depth - 1] = -(-gains[depth - 1]).max(gains[depth]);
+        depth -= 1;
+    }
+
+    gains[0]
+}
+
+/// Returns true if the static exchange evaluation of the move is at least the provided threshold
+///
+/// Gives the same answer as comparing *see* with the threshold, but stops as soon as the answer is known
+pub fn see_ge(board: &Board, mov: &Move, threshold: i32) -> bool {
+    if mov.is_castling() {
+        return threshold <= 0;
+    }
+
+    let target_square = mov.target_square;
+    let ExchangeStart { color, first_gain, piece_on_target_value, mut all_occ } = get_exchange_start(board, mov);
+
+    // the balance left to reach the threshold, always from the side that is to capture next
+    let mut swap = first_gain - threshold;
+    if swap < 0 {
+        return false; // even if nothing recaptures the threshold isn't reached
+    }
+
+    swap = piece_on_target_value - swap;
+    if swap <= 0 {
+        return true; // even losing the piece right away keeps the threshold
+    }
+
+    let mut attackers = get_attackers_to(board, target_square, all_occ);
+    let mut side = color;
+    // whether the side that made the move reaches the threshold if the exchange stops now
+    let mut result = true;
+
+    loop {
+        side = 1 - side;
+        attackers &= all_occ;
+
+        let side_attackers = attackers & board.get_all_occupied_squares_for_color(side);
+        if side_attackers == 0 {
+            break;
+        }
+
+        result = !result;
+
+        let (attacker_type, attacker_bb) = get_least_valuable_attacker(board, side, side_attackers);
+        if attacker_type == piece::KING {
+            // the king capture only counts if the opponent can't recapture
+            let opposite_attackers = attackers & !board.get_all_occupied_squares_for_color(side);
+            return if opposite_attackers != 0 { !result } else { result };
+        }
+
+        swap = get_see_value(attacker_type) - swap;
+        if swap < result as i32 {
+            break;
+        }
+
+        all_occ &= !attacker_bb;
+        attackers |= get_slider_attackers_to(board, target_square, all_occ);
+    }
+
+    result
+}
+
+/// Returns true if the move captures something and most likely loses material in the following exchange
+pub fn is_losing_capture(board: &Board, mov: &Move) -> bool {
+    move_ordering::is_capture(board, mov) && !see_ge(board, mov, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_see(fen: &str, uci_move: &str, expected: i32) {
+        let board = Board::from_fen(fen);
+        let mov = board.find_move(uci_move);
+
+        assert_eq!(see(&board, &mov), expected, "{} {}", fen, uci_move);
+        assert!(see_ge(&board, &mov, expected), "{} {}: see_ge fails at the value", fen, uci_move);
+        assert!(!see_ge(&board, &mov, expected + 1), "{} {}: see_ge passes above the value", fen, uci_move);
+    }
+
+    #[test]
+    fn undefended_pawn_capture() {
+        assert_see("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1e5", 100);
+    }
+
+    #[test]
+    fn defended_pawn_capture_with_x_rays() {
+        assert_see("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1", "d3e5", -220);
+    }
+}
@@ -0,0 +1,127 @@
+/*Skill levels making the engine play weaker on purpose.
+
+Below the max level the search is limited in depth and nodes, the evaluation gets noisy and the move is
+picked among the best MultiPV lines instead of always being the best one. The weaker the level, the more often
+a worse line wins the pick, but the lines far behind the best one are almost never picked, so the mistakes
+stay human-like instead of random*/
+
+use rand::Rng;
+
+use crate::r#move::Move;
+
+use super::{search_limits::SearchLimits, search_result::PvLine};
+
+// constants
+pub const MAX_SKILL_LEVEL: u8 = 20;
+/// Elo of the weakest level, the levels are spread evenly up to *MAX_ELO*.
+/// Only a rough estimate, the levels were never rated against other engines
+pub const MIN_ELO: u32 = 800;
+pub const MAX_ELO: u32 = 2800;
+/// Amount of lines the move is picked from below the max level
+const SKILL_MULTI_PV: usize = 4;
+/// Nodes the weakest level may search, doubled every 2 levels
+const MIN_LEVEL_NODES: u64 = 1000;
+/// Evaluation noise in centipawns added for every level below the max one
+const EVAL_NOISE_PER_LEVEL: i32 = 15;
+/// Cap of the score gap between the best and the worst picked line used to scale the random part of the pick
+const MAX_PICK_DELTA: i32 = 100;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SkillLevel {
+    level: u8,
+}
+
+impl SkillLevel {
+    /// Levels above *MAX_SKILL_LEVEL* are clamped to it
+    pub fn new(level: u8) -> Self {
+        Self {
+            level: level.min(MAX_SKILL_LEVEL),
+        }
+    }
+
+    /// The level closest to the provided Elo
+    pub fn from_elo(elo: u32) -> Self {
+        let elo = elo.clamp(MIN_ELO, MAX_ELO);
+        let level = ((elo - MIN_ELO) * MAX_SKILL_LEVEL as u32 + (MAX_ELO - MIN_ELO) / 2) / (MAX_ELO - MIN_ELO);
+
+        Self::new(level as u8)
+    }
+
+    pub fn max() -> Self {
+        Self::new(MAX_SKILL_LEVEL)
+    }
+
+    pub fn get_level(&self) -> u8 {
+        self.level
+    }
+
+    pub fn get_elo(&self) -> u32 {
+        MIN_ELO + (MAX_ELO - MIN_ELO) * self.level as u32 / MAX_SKILL_LEVEL as u32
+    }
+
+    pub fn is_max(&self) -> bool {
+        self.level == MAX_SKILL_LEVEL
+    }
+
+    /// Max depth of the search, *None* at the max level
+    pub fn get_depth_limit(&self) -> Option<u8> {
+        (!self.is_max()).then_some(self.level / 2 + 1)
+    }
+
+    /// Max nodes of the search, *None* at the max level
+    pub fn get_node_limit(&self) -> Option<u64> {
+        (!self.is_max()).then_some(MIN_LEVEL_NODES << (self.level / 2))
+    }
+
+    /// Max offset in centipawns of the static evaluation
+    pub fn get_eval_noise(&self) -> i32 {
+        (MAX_SKILL_LEVEL - self.level) as i32 * EVAL_NOISE_PER_LEVEL
+    }
+
+    /// Amount of best lines the move is picked from
+    pub fn get_multi_pv(&self) -> usize {
+        if self.is_max() { 1 } else { SKILL_MULTI_PV }
+    }
+
+    /// Tightens the provided limits to the ones of the level
+    pub fn limit(&self, limits: &SearchLimits) -> SearchLimits {
+        let tighten = |limit: Option<u64>, skill_limit: Option<u64>| match (limit, skill_limit) {
+            (Some(limit), Some(skill_limit)) => Some(limit.min(skill_limit)),
+            (limit, skill_limit) => limit.or(skill_limit),
+        };
+
+        SearchLimits {
+            depth: tighten(limits.depth.map(u64::from), self.get_depth_limit().map(u64::from)).map(|depth| depth as u8),
+            nodes: tighten(limits.nodes, self.get_node_limit()),
+            infinite: limits.infinite && self.is_max(),
+            ..limits.clone()
+        }
+    }
+
+    /// Picks the move among the lines ranked from the best to the worst: every line gets a bonus made
+    /// of a part growing with its gap to the best line and a random part, the line with the best sum wins
+    pub fn pick_move(&self, lines: &[PvLine], rng: &mut impl Rng) -> Option<Move> {
+        let best_score = lines.first()?.score;
+        if self.is_max() {
+            return lines[0].pv.first().copied();
+        }
+
+        let weakness = 120 - 2 * self.level as i32;
+        let delta = (best_score - lines.last()?.score).min(MAX_PICK_DELTA);
+
+        lines.iter()
+            .filter(|line| !line.pv.is_empty())
+            .map(|line| {
+                let bonus = (weakness * (best_score - line.score) + delta * rng.random_range(0..weakness)) / 128;
+                (line.score + bonus, line.pv[0])
+            })
+            .reduce(|picked, candidate| if candidate.0 > picked.0 { candidate } else { picked })
+            .map(|(_, mov)| mov)
+    }
+}
+
+impl Default for SkillLevel {
+    fn default() -> Self {
+        Self::max()
+    }
+}
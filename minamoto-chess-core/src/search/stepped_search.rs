@@ -0,0 +1,143 @@
+/*Iterative deepening split into steps limited by nodes or time.
+
+The WASM build runs the search on the thread of the page (or of a single worker), so a long search would block it.
+A stepped search returns after every step and continues where it stopped on the next one, letting the caller
+render or handle input in between.
+
+Everything the iterations need is kept between the steps: the depth, the best lines, the move ordering heuristics
+and the transposition table. An iteration cut by the end of a step keeps a frame for every node on the path
+to the interrupted one (its move picker, window, best score...), the next step goes down that path and continues
+every node right where it stopped, so no node is searched twice*/
+
+use crate::board::Board;
+
+use super::{searcher::{Searcher, MAX_PLY}, search_limits::SearchLimits, search_result::SearchResult};
+
+pub struct SteppedSearch {
+    searcher: Searcher,
+    board: Board,
+    max_depth: u8,
+    /// Limits of the whole search over all the steps
+    node_limit: Option<u64>,
+    time_limit_ms: Option<u64>,
+    /// Depth of the iteration searched by the next step
+    next_depth: u8,
+    /// Result of the last completed iteration, with the nodes and the time of all the steps
+    result: SearchResult,
+    nodes: u64,
+    time_ms: u64,
+    is_started: bool,
+    is_finished: bool,
+}
+
+impl SteppedSearch {
+    /// Prepares the search of the position, nothing is searched until the first step
+    ///
+    /// The depth, nodes and movetime limits apply to the whole search, the time spent between the steps isn't counted.
+    /// The clock limits are ignored since the steps are timed by the caller
+    pub fn new(searcher: Searcher, board: Board, limits: &SearchLimits) -> Self {
+        let max_depth = match limits.depth {
+            Some(depth) if !limits.infinite => depth.clamp(1, (MAX_PLY - 1) as u8),
+            _ => (MAX_PLY - 1) as u8,
+        };
+
+        Self {
+            searcher,
+            board,
+            max_depth,
+            node_limit: limits.nodes.filter(|_| !limits.infinite),
+            time_limit_ms: limits.movetime.filter(|_| !limits.infinite),
+            next_depth: 1,
+            result: SearchResult::default(),
+            nodes: 0,
+            time_ms: 0,
+            is_started: false,
+            is_finished: false,
+        }
+    }
+
+    /// Searches until the step runs out of nodes or time (*None* means no limit) and calls the provided function
+    /// with the result of every iteration completed in the meantime. Returns true once the search is finished
+    ///
+    /// The node limit of the step is exact. There is no move to play until the first iteration completes,
+    /// which may take several steps with small limits
+    pub fn step<F: FnMut(&SearchResult)>(&mut self, max_nodes: Option<u64>, max_time_ms: Option<u64>, mut on_iteration: F) -> bool {
+        if self.is_finished {
+            return true;
+        }
+
+        if !self.is_started {
+            self.is_started = true;
+            self.searcher.begin_stepped_search();
+
+            if let Some(result) = self.searcher.search_tablebase_root(&mut self.board, &mut on_iteration) {
+                self.result = result;
+                self.is_finished = true;
+                return true;
+            }
+        }
+
+        // every step searches at least a node, otherwise the search would never get anywhere
+        let step_nodes = min_limit(max_nodes, self.node_limit.map(|limit| limit.saturating_sub(self.nodes)))
+            .map(|nodes| nodes.max(1));
+        let step_time_ms = min_limit(max_time_ms, self.time_limit_ms.map(|limit| limit.saturating_sub(self.time_ms)));
+        self.searcher.begin_step(step_nodes, step_time_ms);
+
+        while self.next_depth <= self.max_depth {
+            let is_completed = self.searcher.search_iteration(&mut self.board, self.next_depth, &mut self.result);
+            if !is_completed {
+                break;
+            }
+
+            let (step_nodes, step_time_ms) = self.searcher.get_step_usage();
+            self.result.nodes = self.nodes + step_nodes;
+            self.result.time_ms = self.time_ms + step_time_ms;
+            self.next_depth += 1;
+            on_iteration(&self.result);
+
+            if self.result.best_move.is_none() {
+                self.is_finished = true; // checkmate or stalemate in the root position, nothing to search
+                break;
+            }
+        }
+
+        let (step_nodes, step_time_ms) = self.searcher.get_step_usage();
+        self.searcher.end_step();
+        self.nodes += step_nodes;
+        self.time_ms += step_time_ms;
+
+        let is_node_limit_reached = self.node_limit.is_some_and(|limit| self.nodes >= limit);
+        let is_time_over = self.time_limit_ms.is_some_and(|limit| self.time_ms >= limit);
+        if self.next_depth > self.max_depth || is_node_limit_reached || is_time_over {
+            self.is_finished = true;
+        }
+
+        self.is_finished
+    }
+
+    /// Finishes the search early, the result of the last completed iteration stays available
+    pub fn cancel(&mut self) {
+        self.is_finished = true;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.is_finished
+    }
+
+    /// Result of the last completed iteration, with the nodes and the time of all the steps so far
+    pub fn get_result(&self) -> &SearchResult {
+        &self.result
+    }
+
+    /// The searched position
+    pub fn get_board(&self) -> &Board {
+        &self.board
+    }
+}
+
+fn min_limit(limit: Option<u64>, other_limit: Option<u64>) -> Option<u64> {
+    match (limit, other_limit) {
+        (Some(limit), Some(other_limit)) => Some(limit.min(other_limit)),
+        (limit, other_limit) => limit.or(other_limit),
+    }
+}
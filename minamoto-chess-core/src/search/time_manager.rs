@@ -0,0 +1,142 @@
+use super::search_limits::SearchLimits;
+
+// constants
+/// Time kept in reserve for the communication with the GUI (or JS) so the engine never flags
+const MOVE_OVERHEAD_MS: u64 = 30;
+/// Amount of moves the remaining time is spread over when *movestogo* isn't provided
+const DEFAULT_MOVES_TO_GO: u64 = 30;
+/// How many times the search may exceed the soft limit while finishing an iteration
+const HARD_LIMIT_MULTIPLIER: u64 = 4;
+
+/// Measures the time passed since its creation
+///
+/// *std::time::Instant* isn't available in the browser, so the WASM build uses
+/// *performance.now()* (or *Date.now()* where there is no *performance*)
+#[cfg(not(target_arch = "wasm32"))]
+struct Stopwatch {
+    start: std::time::Instant,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Stopwatch {
+    fn start() -> Self {
+        Self { start: std::time::Instant::now() }
+    }
+
+    fn get_elapsed_ms(&self) -> u64 {
+        self.start.elapsed().as_millis() as u64
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+struct Stopwatch {
+    start_ms: f64,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Stopwatch {
+    fn start() -> Self {
+        Self { start_ms: Self::now_ms() }
+    }
+
+    fn get_elapsed_ms(&self) -> u64 {
+        (Self::now_ms() - self.start_ms).max(0.0) as u64
+    }
+
+    fn now_ms() -> f64 {
+        use js_sys::{wasm_bindgen::JsCast, Function, Reflect};
+
+        if let Ok(performance) = Reflect::get(&js_sys::global(), &"performance".into())
+            && !performance.is_undefined()
+            && let Ok(now) = Reflect::get(&performance, &"now".into())
+            && let Ok(now) = now.dyn_into::<Function>()
+            && let Ok(now_ms) = now.call0(&performance)
+            && let Some(now_ms) = now_ms.as_f64() {
+            return now_ms;
+        }
+
+        js_sys::Date::now()
+    }
+}
+
+/// Decides how long the search may take based on the limits
+///
+/// The soft limit is checked between the iterations: a new iteration isn't started once it's reached,
+/// since it most likely won't finish in time anyway. The hard limit is checked periodically
+/// during the search and interrupts it right away
+pub struct TimeManager {
+    stopwatch: Stopwatch,
+    soft_limit_ms: Option<u64>,
+    hard_limit_ms: Option<u64>,
+}
+
+impl TimeManager {
+    /// Starts measuring the time of the search for the provided side to move
+    pub fn new(limits: &SearchLimits, color: usize) -> Self {
+        let (soft_limit_ms, hard_limit_ms) = Self::allocate_time(limits, color);
+
+        Self {
+            stopwatch: Stopwatch::start(),
+            soft_limit_ms,
+            hard_limit_ms,
+        }
+    }
+
+    /// Interrupts the search after exactly the provided time (or never if it's *None*), nothing is kept in reserve.
+    /// Used for the steps of a stepped search, which the caller continues right away
+    pub fn with_hard_limit(limit_ms: Option<u64>) -> Self {
+        Self {
+            stopwatch: Stopwatch::start(),
+            soft_limit_ms: None,
+            hard_limit_ms: limit_ms,
+        }
+    }
+
+    fn allocate_time(limits: &SearchLimits, color: usize) -> (Option<u64>, Option<u64>) {
+        if limits.infinite {
+            return (None, None);
+        }
+
+        if let Some(movetime) = limits.movetime {
+            let budget = movetime.saturating_sub(MOVE_OVERHEAD_MS).max(1);
+            return (Some(budget), Some(budget));
+        }
+
+        let Some(time) = limits.time[color] else {
+            return (None, None);
+        };
+        let increment = limits.increment[color].unwrap_or(0);
+        let moves_to_go = limits.movestogo.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+
+        let available = time.saturating_sub(MOVE_OVERHEAD_MS);
+        // never more than half of the clock for a single move, even with a big increment
+        let max_budget = available / 2;
+
+        let soft_limit = (available / moves_to_go + increment * 3 / 4).min(max_budget).max(1);
+        let hard_limit = (soft_limit * HARD_LIMIT_MULTIPLIER).min(max_budget).max(soft_limit);
+
+        (Some(soft_limit), Some(hard_limit))
+    }
+
+    pub fn get_elapsed_ms(&self) -> u64 {
+        self.stopwatch.get_elapsed_ms()
+    }
+
+    pub fn get_soft_limit_ms(&self) -> Option<u64> {
+        self.soft_limit_ms
+    }
+
+    pub fn get_hard_limit_ms(&self) -> Option<u64> {
+        self.hard_limit_ms
+    }
+
+    /// Returns true if there is no time for another iteration
+    pub fn is_soft_limit_reached(&self) -> bool {
+        self.soft_limit_ms.is_some_and(|limit| self.get_elapsed_ms() >= limit)
+    }
+
+    /// Returns true if the search must be interrupted right away
+    pub fn is_hard_limit_reached(&self) -> bool {
+        self.hard_limit_ms.is_some_and(|limit| self.get_elapsed_ms() >= limit)
+    }
+}
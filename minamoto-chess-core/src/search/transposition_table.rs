@@ -0,0 +1,298 @@
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+use crate::r#move::Move;
+
+use super::searcher::TB_WIN_THRESHOLD;
+
+// constants
+pub const DEFAULT_SIZE_MB: usize = 16;
+pub const MAX_SIZE_MB: usize = 4096;
+const BYTES_IN_MB: usize = 1024 * 1024;
+/// Marks the absence of the best move since no real move packs into 0
+const NO_MOVE: u32 = 0;
+
+// bit layout of the packed entry data
+const MOVE_MASK: u64 = 0x1F_FFFF;
+const SCORE_SHIFT: u64 = 21;
+const DEPTH_SHIFT: u64 = 37;
+const BOUND_SHIFT: u64 = 45;
+const AGE_SHIFT: u64 = 47;
+
+/// Tells how the stored score relates to the real score of the position
+///
+/// Starts from 1 so the data of a stored entry is never 0, which marks an empty slot
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Bound {
+    /// The score is exact (every move was searched and the score landed inside the window)
+    Exact = 1,
+    /// The real score is at least the stored one (the search failed high)
+    Lower = 2,
+    /// The real score is at most the stored one (the search failed low)
+    Upper = 3,
+}
+
+impl Bound {
+    fn from_bits(bits: u64) -> Self {
+        match bits {
+            1 => Bound::Exact,
+            2 => Bound::Lower,
+            _ => Bound::Upper,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct TtEntry {
+    key: u64,
+    packed_move: u32,
+    score: i16,
+    depth: u8,
+    bound: Bound,
+    /// Search generation the entry was written in. Entries from older searches are replaced first
+    age: u8,
+}
+
+impl TtEntry {
+    pub fn get_best_move(&self) -> Option<Move> {
+        if self.packed_move == NO_MOVE {
+            None
+        } else {
+            Some(Move::unpack(self.packed_move))
+        }
+    }
+
+    /// Returns the stored score. Mate scores are relative to the stored position, see *score_from_tt*
+    pub fn get_score(&self) -> i32 {
+        self.score as i32
+    }
+
+    pub fn get_depth(&self) -> u8 {
+        self.depth
+    }
+
+    pub fn get_bound(&self) -> Bound {
+        self.bound
+    }
+
+    /// Packs everything except the key into 55 bits
+    fn pack_data(&self) -> u64 {
+        self.packed_move as u64
+            | (self.score as u16 as u64) << SCORE_SHIFT
+            | (self.depth as u64) << DEPTH_SHIFT
+            | (self.bound as u64) << BOUND_SHIFT
+            | (self.age as u64) << AGE_SHIFT
+    }
+
+    fn unpack(key: u64, data: u64) -> Self {
+        Self {
+            key,
+            packed_move: (data & MOVE_MASK) as u32,
+            score: (data >> SCORE_SHIFT) as u16 as i16,
+            depth: (data >> DEPTH_SHIFT) as u8,
+            bound: Bound::from_bits((data >> BOUND_SHIFT) & 0b11),
+            age: (data >> AGE_SHIFT) as u8,
+        }
+    }
+}
+
+/// Entry slot that can be read and written by several threads at once without locks
+///
+/// The key is stored xored with the data, so if another thread overwrote one of the halves
+/// in the middle of the read, the key doesn't match and the torn entry is ignored
+struct AtomicEntry {
+    key_xor_data: AtomicU64,
+    data: AtomicU64,
+}
+
+impl AtomicEntry {
+    fn new() -> Self {
+        Self {
+            key_xor_data: AtomicU64::new(0),
+            data: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns *None* for an empty slot
+    fn load(&self) -> Option<TtEntry> {
+        let data = self.data.load(Ordering::Relaxed);
+        if data == 0 {
+            return None;
+        }
+        let key = self.key_xor_data.load(Ordering::Relaxed) ^ data;
+
+        Some(TtEntry::unpack(key, data))
+    }
+
+    fn store(&self, entry: &TtEntry) {
+        let data = entry.pack_data();
+        self.key_xor_data.store(entry.key ^ data, Ordering::Relaxed);
+        self.data.store(data, Ordering::Relaxed);
+    }
+
+    fn clear(&self) {
+        self.key_xor_data.store(0, Ordering::Relaxed);
+        self.data.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Two entries sharing the same index
+///
+/// The first one is depth-preferred and keeps expensive results,
+/// the second one is always replaced and keeps the most recent ones
+struct Bucket {
+    entries: [AtomicEntry; 2],
+}
+
+impl Bucket {
+    fn new() -> Self {
+        Self { entries: [AtomicEntry::new(), AtomicEntry::new()] }
+    }
+}
+
+/// Fixed-size hash table of already searched positions indexed by their Zobrist hashes
+///
+/// Probing and storing only need a shared reference, so the table can be shared between search threads
+pub struct TranspositionTable {
+    buckets: Vec<Bucket>,
+    /// *buckets.len() - 1*, the length is always a power of two
+    index_mask: u64,
+    age: AtomicU8,
+}
+
+impl TranspositionTable {
+    /// Creates a table taking at most the provided amount of megabytes
+    pub fn new(size_mb: usize) -> Self {
+        let mut tt = Self {
+            buckets: Vec::new(),
+            index_mask: 0,
+            age: AtomicU8::new(0),
+        };
+        tt.resize(size_mb);
+
+        tt
+    }
+
+    /// Reallocates the table to take at most the provided amount of megabytes. Every stored entry is lost
+    pub fn resize(&mut self, size_mb: usize) {
+        let size_mb = size_mb.clamp(1, MAX_SIZE_MB);
+        let max_buckets = size_mb * BYTES_IN_MB / std::mem::size_of::<Bucket>();
+        // rounding down to a power of two so the index is just a mask of the hash
+        let bucket_count = 1usize << max_buckets.ilog2();
+
+        // the old table is dropped before allocating the new one so both never take the memory at once
+        self.buckets = Vec::new();
+        self.buckets = (0..bucket_count).map(|_| Bucket::new()).collect();
+        self.index_mask = bucket_count as u64 - 1;
+        *self.age.get_mut() = 0;
+    }
+
+    /// Removes every stored entry, keeping the size
+    pub fn clear(&self) {
+        for bucket in &self.buckets {
+            for entry in &bucket.entries {
+                entry.clear();
+            }
+        }
+        self.age.store(0, Ordering::Relaxed);
+    }
+
+    /// Must be called before every new search so the entries of the previous ones get replaced first
+    pub fn new_search(&self) {
+        self.age.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn get_size_mb(&self) -> usize {
+        self.buckets.len() * std::mem::size_of::<Bucket>() / BYTES_IN_MB
+    }
+
+    fn get_bucket(&self, hash: u64) -> &Bucket {
+        unsafe {
+            self.buckets.get_unchecked((hash & self.index_mask) as usize)
+        }
+    }
+
+    /// Returns the entry stored for the position with the provided hash if there is one
+    pub fn probe(&self, hash: u64) -> Option<TtEntry> {
+        self.get_bucket(hash).entries.iter()
+            .filter_map(AtomicEntry::load)
+            .find(|entry| entry.key == hash)
+    }
+
+    /// Stores the search result of the position with the provided hash
+    ///
+    /// The score must already be converted with *score_to_tt*
+    pub fn store(&self, hash: u64, depth: u8, bound: Bound, score: i32, best_move: Option<Move>) {
+        let age = self.age.load(Ordering::Relaxed);
+        let [depth_preferred_slot, always_replace_slot] = &self.get_bucket(hash).entries;
+
+        let depth_preferred = depth_preferred_slot.load();
+        let replace_depth_preferred = depth_preferred.is_none_or(|entry| {
+            entry.key == hash || entry.age != age || depth >= entry.depth
+        });
+
+        let (target_slot, target) = if replace_depth_preferred {
+            (depth_preferred_slot, depth_preferred)
+        } else {
+            (always_replace_slot, always_replace_slot.load())
+        };
+
+        // A search without a best move (fail low) shouldn't erase the move found for the same position earlier
+        let packed_move = match (best_move, target) {
+            (Some(mov), _) => mov.pack(),
+            (None, Some(target)) if target.key == hash => target.packed_move,
+            (None, _) => NO_MOVE,
+        };
+
+        target_slot.store(&TtEntry {
+            key: hash,
+            packed_move,
+            score: score as i16,
+            depth,
+            bound,
+            age,
+        });
+    }
+
+    /// Returns how full the table is in permille, sampling the first thousand buckets
+    pub fn get_hashfull(&self) -> usize {
+        let age = self.age.load(Ordering::Relaxed);
+        let sample = self.buckets.len().min(1000);
+        let used_entries: usize = self.buckets[..sample].iter()
+            .map(|bucket| bucket.entries.iter()
+                .filter_map(AtomicEntry::load)
+                .filter(|entry| entry.age == age)
+                .count())
+            .sum();
+
+        used_entries * 1000 / (sample * 2)
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_SIZE_MB)
+    }
+}
+
+/// Converts a score relative to the root into a score relative to the current position,
+/// so a mate (or a tablebase win) stored at one ply stays correct when the position is found at a different one
+pub fn score_to_tt(score: i32, ply: usize) -> i32 {
+    if score >= TB_WIN_THRESHOLD {
+        score + ply as i32
+    } else if score <= -TB_WIN_THRESHOLD {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+/// Reverts *score_to_tt*
+pub fn score_from_tt(score: i32, ply: usize) -> i32 {
+    if score >= TB_WIN_THRESHOLD {
+        score - ply as i32
+    } else if score <= -TB_WIN_THRESHOLD {
+        score + ply as i32
+    } else {
+        score
+    }
+}
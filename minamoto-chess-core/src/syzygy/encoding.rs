@@ -0,0 +1,178 @@
+/*Tables used to turn a position into the index of its value inside a tablebase file.
+
+Everything here uses the square numbering of the tablebases: a1 is 0, b1 is 1 and h8 is 63,
+which is the mirror of the board numbering (h1 is 0) along the vertical axis*/
+
+use once_cell::sync::Lazy;
+
+// constants
+/// Max amount of pieces (kings included) in a tablebase
+pub const MAX_PIECES: usize = 7;
+/// Max amount of pawns of the leading color
+const MAX_LEAD_PAWNS: usize = 5;
+
+/// Converts a board square (h1 is 0) to a tablebase square (a1 is 0)
+pub fn to_tb_square(square: usize) -> usize {
+    square ^ 7
+}
+
+pub fn get_file(square: usize) -> usize {
+    square & 7
+}
+
+pub fn get_rank(square: usize) -> usize {
+    square >> 3
+}
+
+/// Negative below the a1-h8 diagonal, 0 on it and positive above it
+pub fn get_diagonal_offset(square: usize) -> i32 {
+    get_rank(square) as i32 - get_file(square) as i32
+}
+
+/// The tables only store the positions with the leading pawn on the files a-d, the rest is mirrored
+pub fn map_to_queen_side(file: usize) -> usize {
+    file.min(7 - file)
+}
+
+fn are_kings_touching(square1: usize, square2: usize) -> bool {
+    let file_distance = get_file(square1).abs_diff(get_file(square2));
+    let rank_distance = get_rank(square1).abs_diff(get_rank(square2));
+
+    file_distance <= 1 && rank_distance <= 1
+}
+
+pub struct EncodingTables {
+    /// Maps the squares of the a1-d1-d4 triangle to 0..10, the diagonal squares go last
+    pub map_a1d1d4: [usize; 64],
+    /// Maps the squares below the a1-h8 diagonal to 0..28
+    pub map_b1h1h7: [usize; 64],
+    /// Index of every legal placement of two kings with the first one in the a1-d1-d4 triangle (462 in total)
+    pub map_kk: [[usize; 64]; 10],
+    /// *binomial[k][n]* is the amount of ways to choose k squares out of n
+    pub binomial: [[u64; 64]; MAX_PIECES],
+    /// Amount of squares available to the other leading pawns when the leading pawn is on the square,
+    /// the leading pawn is the one with the highest value
+    pub map_pawns: [usize; 64],
+    /// Index of the leading pawn group with the leading pawn on the square, by the amount of leading pawns
+    pub lead_pawn_idx: [[u64; 64]; MAX_LEAD_PAWNS + 1],
+    /// Amount of leading pawn placements for every file of the leading pawn, by the amount of leading pawns
+    pub lead_pawns_size: [[u64; 4]; MAX_LEAD_PAWNS + 1],
+}
+
+impl EncodingTables {
+    fn new() -> Self {
+        let mut tables = Self {
+            map_a1d1d4: [0; 64],
+            map_b1h1h7: [0; 64],
+            map_kk: [[0; 64]; 10],
+            binomial: [[0; 64]; MAX_PIECES],
+            map_pawns: [0; 64],
+            lead_pawn_idx: [[0; 64]; MAX_LEAD_PAWNS + 1],
+            lead_pawns_size: [[0; 4]; MAX_LEAD_PAWNS + 1],
+        };
+
+        let mut code = 0;
+        for square in 0..64 {
+            if get_diagonal_offset(square) < 0 {
+                tables.map_b1h1h7[square] = code;
+                code += 1;
+            }
+        }
+
+        // the triangle squares go from a1 to d4, the ones on the diagonal get the last codes
+        let mut diagonal = Vec::new();
+        code = 0;
+        for square in 0..=27 {
+            if get_file(square) > 3 {
+                continue;
+            }
+
+            match get_diagonal_offset(square) {
+                offset if offset < 0 => {
+                    tables.map_a1d1d4[square] = code;
+                    code += 1;
+                }
+                0 => diagonal.push(square),
+                _ => (),
+            }
+        }
+        for square in diagonal {
+            tables.map_a1d1d4[square] = code;
+            code += 1;
+        }
+
+        // If the first king is on the diagonal, the second one can't be above it.
+        // The placements with both kings on the diagonal get the last codes
+        let mut both_on_diagonal = Vec::new();
+        code = 0;
+        for idx in 0..10 {
+            for square1 in 0..=27 {
+                // b1 is the only square of the triangle mapped to 0
+                if tables.map_a1d1d4[square1] != idx || (idx == 0 && square1 != 1) {
+                    continue;
+                }
+
+                for square2 in 0..64 {
+                    if are_kings_touching(square1, square2) {
+                        continue;
+                    }
+
+                    let offset1 = get_diagonal_offset(square1);
+                    let offset2 = get_diagonal_offset(square2);
+                    if offset1 == 0 && offset2 > 0 {
+                        continue;
+                    }
+
+                    if offset1 == 0 && offset2 == 0 {
+                        both_on_diagonal.push((idx, square2));
+                    } else {
+                        tables.map_kk[idx][square2] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (idx, square2) in both_on_diagonal {
+            tables.map_kk[idx][square2] = code;
+            code += 1;
+        }
+
+        // Pascal's triangle
+        tables.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..MAX_PIECES.min(n + 1) {
+                let with_square = if k > 0 { tables.binomial[k - 1][n - 1] } else { 0 };
+                let without_square = if k < n { tables.binomial[k][n - 1] } else { 0 };
+                tables.binomial[k][n] = with_square + without_square;
+            }
+        }
+
+        // 47 squares are left for the other pawns when the leading pawn is on a2,
+        // every rank further removes 2 squares since the pawns of both edges are mirrored
+        let mut available_squares = 47;
+        for lead_pawns_count in 1..=MAX_LEAD_PAWNS {
+            for file in 0..4 {
+                let mut idx = 0;
+
+                for rank in 1..=6 {
+                    let square = rank * 8 + file;
+
+                    if lead_pawns_count == 1 {
+                        tables.map_pawns[square] = available_squares;
+                        tables.map_pawns[square ^ 7] = available_squares - 1;
+                        available_squares -= 2;
+                    }
+
+                    tables.lead_pawn_idx[lead_pawns_count][square] = idx;
+                    idx += tables.binomial[lead_pawns_count - 1][tables.map_pawns[square]];
+                }
+
+                tables.lead_pawns_size[lead_pawns_count][file] = idx;
+            }
+        }
+
+        tables
+    }
+}
+
+pub static ENCODING_TABLES: Lazy<EncodingTables> = Lazy::new(EncodingTables::new);
@@ -0,0 +1,573 @@
+/*Syzygy endgame tablebases.
+
+WDL tables (.rtbw) store whether a position is won, drawn or lost with perfect play, and DTZ tables (.rtbz)
+store the distance (in plies) to the next zeroing move (capture or pawn move) that keeps the result.
+Both are probed the same way as in the original implementation by Ronald de Man:
+
+- The tables don't store the positions where a capture is the best move, so the captures are searched
+  first and the table is only trusted when no capture does better
+- The DTZ tables only store one side to move, the other one is found with a 1-ply search
+
+The positions with castling rights are never in the tables.
+
+Cursed wins and blessed losses are the results that would flip if it wasn't for the 50 moves rule*/
+
+pub mod encoding;
+mod table;
+
+use std::{fmt, path::PathBuf, sync::OnceLock};
+
+use rustc_hash::FxHashMap;
+
+use crate::{
+    board::Board,
+    castling,
+    r#move::Move,
+    move_generation::{attack_calculator::AttackCalculator, move_gen},
+    piece,
+};
+
+use table::{Table, TableKind, TableProbe};
+
+// constants
+pub const WDL_EXTENSION: &str = "rtbw";
+pub const DTZ_EXTENSION: &str = "rtbz";
+/// The position is drawn by the 50 moves rule when the counter reaches this value
+const RULE50_PLIES: i32 = 100;
+/// Base of the ranks of the root moves, bigger than any DTZ
+const MAX_DTZ: i32 = 1 << 18;
+/// Pieces of a table name in the order the material key is built in
+const NAME_PIECES: [(char, usize); 6] = [
+    ('K', piece::KING),
+    ('P', piece::PAWN),
+    ('N', piece::KNIGHT),
+    ('B', piece::BISHOP),
+    ('R', piece::ROOK),
+    ('Q', piece::QUEEN),
+];
+
+/// Win/draw/loss from the perspective of the side to move
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Wdl {
+    Loss = -2,
+    /// Lost, but the opponent can't win before the 50 moves rule draws the game
+    BlessedLoss = -1,
+    Draw = 0,
+    /// Won, but not before the 50 moves rule draws the game
+    CursedWin = 1,
+    Win = 2,
+}
+
+impl Wdl {
+    fn from_value(value: i32) -> Self {
+        match value {
+            ..=-2 => Wdl::Loss,
+            -1 => Wdl::BlessedLoss,
+            0 => Wdl::Draw,
+            1 => Wdl::CursedWin,
+            _ => Wdl::Win,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TablebaseError {
+    Io(std::io::Error),
+    /// The file name isn't a table name like *KRPvKR.rtbw*
+    InvalidName(String),
+    InvalidMagic,
+    /// The file is truncated or doesn't match its name
+    Corrupted,
+}
+
+impl fmt::Display for TablebaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TablebaseError::Io(error) => write!(f, "Failed to read the tablebase: {}", error),
+            TablebaseError::InvalidName(name) => write!(f, "Not a tablebase file name: {}", name),
+            TablebaseError::InvalidMagic => write!(f, "Not a tablebase file"),
+            TablebaseError::Corrupted => write!(f, "Tablebase file is corrupted"),
+        }
+    }
+}
+
+impl std::error::Error for TablebaseError {}
+
+impl From<std::io::Error> for TablebaseError {
+    fn from(error: std::io::Error) -> Self {
+        TablebaseError::Io(error)
+    }
+}
+
+/// Packs the amount of every piece type (apart from the kings) of both colors into 4 bits each,
+/// the white pieces first
+fn get_material_key_from_counts(counts: &[[usize; 7]; 2]) -> u64 {
+    let mut key = 0;
+    for (color, color_counts) in counts.iter().enumerate() {
+        for (piece_type, &count) in color_counts.iter().enumerate().skip(piece::PAWN) {
+            key |= (count as u64) << ((color * 5 + piece_type - piece::PAWN) * 4);
+        }
+    }
+
+    key
+}
+
+fn get_material_key(board: &Board) -> u64 {
+    let mut counts = [[0; 7]; 2];
+    for (color, color_counts) in counts.iter_mut().enumerate() {
+        for (piece_type, count) in color_counts.iter_mut().enumerate().skip(piece::PAWN) {
+            *count = board.get_piece_bitboard(color, piece_type).count_ones() as usize;
+        }
+    }
+
+    get_material_key_from_counts(&counts)
+}
+
+/// Material of a table, taken from its name
+struct TableInfo {
+    /// Material key with the first side of the name being white
+    key: u64,
+    /// Material key with the first side of the name being black
+    key2: u64,
+    piece_count: usize,
+    has_pawns: bool,
+    /// True if any piece type (apart from the king) of any side appears exactly once
+    has_unique_pieces: bool,
+    /// Pawns of the leading color (the side with less pawns, but at least one) followed by the other pawns
+    pawn_count: [usize; 2],
+}
+
+impl TableInfo {
+    /// Parses a table name like *KRPvKR*
+    fn from_name(name: &str) -> Option<Self> {
+        let (white_name, black_name) = name.split_once('v')?;
+        let mut counts = [[0; 7]; 2];
+
+        for (color_counts, side_name) in counts.iter_mut().zip([white_name, black_name]) {
+            if !side_name.starts_with('K') {
+                return None;
+            }
+
+            for letter in side_name.chars() {
+                let &(_, piece_type) = NAME_PIECES.iter().find(|(piece_letter, _)| *piece_letter == letter)?;
+                color_counts[piece_type] += 1;
+            }
+
+            if color_counts[piece::KING] != 1 {
+                return None;
+            }
+        }
+
+        let piece_count = counts.iter().flatten().sum::<usize>();
+        if piece_count > encoding::MAX_PIECES {
+            return None;
+        }
+
+        let [white_pawns, black_pawns] = [counts[piece::WHITE][piece::PAWN], counts[piece::BLACK][piece::PAWN]];
+        // the side with less pawns leads, it gives a better compression
+        let is_white_leading = black_pawns == 0 || (white_pawns > 0 && black_pawns >= white_pawns);
+
+        Some(Self {
+            key: get_material_key_from_counts(&counts),
+            key2: get_material_key_from_counts(&[counts[1], counts[0]]),
+            piece_count,
+            has_pawns: white_pawns + black_pawns > 0,
+            has_unique_pieces: (piece::PAWN..=piece::QUEEN).any(|piece_type| counts.iter().any(|color_counts| color_counts[piece_type] == 1)),
+            pawn_count: if is_white_leading { [white_pawns, black_pawns] } else { [black_pawns, white_pawns] },
+        })
+    }
+}
+
+/// A table that is either already parsed or read from its file on the first probe
+#[derive(Default)]
+struct TableSlot {
+    path: Option<PathBuf>,
+    table: OnceLock<Option<Table>>,
+}
+
+impl TableSlot {
+    fn get(&self, kind: TableKind, info: &TableInfo) -> Option<&Table> {
+        self.table.get_or_init(|| {
+            let data = std::fs::read(self.path.as_ref()?).ok()?;
+            Table::parse(data, kind, info).ok()
+        }).as_ref()
+    }
+}
+
+struct TableEntry {
+    info: TableInfo,
+    wdl: TableSlot,
+    dtz: TableSlot,
+}
+
+/// Result of probing a position, from the perspective of the side to move
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TablebaseProbe {
+    pub wdl: Wdl,
+    /// Plies to the next zeroing move with the best play (negative when losing, 0 for draws),
+    /// *None* if the DTZ table isn't available
+    pub dtz: Option<i32>,
+}
+
+/// Root move with its tablebase result, from the perspective of the side to move in the root position
+#[derive(Clone, Copy)]
+pub struct RankedRootMove {
+    pub mov: Move,
+    pub wdl: Wdl,
+    /// Plies to the next zeroing move after playing the move (counting the move itself)
+    pub dtz: i32,
+    /// Higher is better: the wins first (the fastest to zero the counter first),
+    /// then the cursed wins, the draws, the blessed losses and the losses
+    pub rank: i32,
+}
+
+fn is_zeroing(board: &Board, mov: &Move) -> bool {
+    board.get_piece_on_square(mov.capture_square).1 != piece::NONE
+        || board.get_piece_on_square(mov.start_square).1 == piece::PAWN
+}
+
+fn is_capture(board: &Board, mov: &Move) -> bool {
+    board.get_piece_on_square(mov.capture_square).1 != piece::NONE
+}
+
+fn generate_legal_moves(board: &Board) -> (Vec<Move>, bool) {
+    let attack_calc = AttackCalculator::new(board);
+    let mut moves = move_gen::create_empty_move_buffer();
+    move_gen::generate_moves(&mut moves, board, &attack_calc);
+
+    (moves, attack_calc.in_check())
+}
+
+fn is_checkmate(board: &Board) -> bool {
+    let (moves, in_check) = generate_legal_moves(board);
+    in_check && moves.is_empty()
+}
+
+/// DTZ of the move right before a zeroing move
+fn get_dtz_before_zeroing(wdl: i32) -> i32 {
+    match wdl {
+        2 => 1,
+        1 => RULE50_PLIES + 1,
+        -1 => -(RULE50_PLIES + 1),
+        -2 => -1,
+        _ => 0,
+    }
+}
+
+/// Set of Syzygy tables
+///
+/// The tables found in a directory are only read when they are probed for the first time.
+/// It is safe to share between the search threads
+#[derive(Default)]
+pub struct Tablebase {
+    entries: Vec<TableEntry>,
+    /// Index of the entry by both material keys of the table
+    keys: FxHashMap<u64, usize>,
+    max_pieces: usize,
+}
+
+impl Tablebase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers every table of the directory. Not available in the browser, where the files come from JS
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn add_directory(&mut self, path: impl AsRef<std::path::Path>) -> Result<(), TablebaseError> {
+        for dir_entry in std::fs::read_dir(path)? {
+            let path = dir_entry?.path();
+            let (Some(name), Some(extension)) = (path.file_stem().and_then(|name| name.to_str()), path.extension().and_then(|extension| extension.to_str())) else {
+                continue;
+            };
+
+            let kind = match extension {
+                WDL_EXTENSION => TableKind::Wdl,
+                DTZ_EXTENSION => TableKind::Dtz,
+                _ => continue,
+            };
+            let Some(info) = TableInfo::from_name(name) else {
+                continue; // other files are allowed in the directory
+            };
+
+            let slot = TableSlot { path: Some(path.clone()), table: OnceLock::new() };
+            self.insert(info, kind, slot);
+        }
+
+        Ok(())
+    }
+
+    /// Parses the table from the bytes of the file, the name of the file (e.g. *KRvK.rtbw*)
+    /// tells the material and the type of the table
+    pub fn add_file(&mut self, file_name: &str, data: Vec<u8>) -> Result<(), TablebaseError> {
+        let invalid_name = || TablebaseError::InvalidName(file_name.to_string());
+        let (name, extension) = file_name.rsplit_once('.').ok_or_else(invalid_name)?;
+
+        let kind = match extension {
+            WDL_EXTENSION => TableKind::Wdl,
+            DTZ_EXTENSION => TableKind::Dtz,
+            _ => return Err(invalid_name()),
+        };
+        let info = TableInfo::from_name(name).ok_or_else(invalid_name)?;
+
+        let table = Table::parse(data, kind, &info)?;
+        self.insert(info, kind, TableSlot { path: None, table: OnceLock::from(Some(table)) });
+
+        Ok(())
+    }
+
+    fn insert(&mut self, info: TableInfo, kind: TableKind, slot: TableSlot) {
+        let index = match self.keys.get(&info.key) {
+            Some(&index) => index,
+            None => {
+                self.keys.insert(info.key, self.entries.len());
+                self.keys.insert(info.key2, self.entries.len());
+                self.entries.push(TableEntry { info, wdl: TableSlot::default(), dtz: TableSlot::default() });
+                self.entries.len() - 1
+            }
+        };
+
+        let entry = &mut self.entries[index];
+        match kind {
+            TableKind::Wdl => {
+                entry.wdl = slot;
+                self.max_pieces = self.max_pieces.max(entry.info.piece_count);
+            }
+            TableKind::Dtz => entry.dtz = slot,
+        }
+    }
+
+    /// Amount of pieces (kings included) of the biggest WDL table, 0 if there are none
+    pub fn get_max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.max_pieces == 0
+    }
+
+    /// Returns true if the position may be in the tables: few enough pieces and no castling rights
+    pub fn can_probe(&self, board: &Board) -> bool {
+        board.get_all_occupied_squares().count_ones() as usize <= self.max_pieces
+            && !castling::can_any(board.get_castling_state(piece::WHITE))
+            && !castling::can_any(board.get_castling_state(piece::BLACK))
+    }
+
+    fn probe_table(&self, board: &Board, kind: TableKind, wdl: i32) -> Option<TableProbe> {
+        // there are no tables for two bare kings
+        if board.get_all_occupied_squares().count_ones() == 2 {
+            return Some(TableProbe::Value(0));
+        }
+
+        let entry = &self.entries[*self.keys.get(&get_material_key(board))?];
+        let table = match kind {
+            TableKind::Wdl => entry.wdl.get(kind, &entry.info)?,
+            TableKind::Dtz => entry.dtz.get(kind, &entry.info)?,
+        };
+
+        Some(table.probe(&entry.info, board, wdl))
+    }
+
+    /// Searches the captures (and the pawn moves if *check_zeroing_moves* is set) before trusting the table,
+    /// since it stores a "don't care" value when a capture is the best move
+    ///
+    /// Returns the WDL value and true if the best move is one of the searched zeroing moves
+    fn search(&self, board: &mut Board, check_zeroing_moves: bool) -> Option<(i32, bool)> {
+        let (moves, _) = generate_legal_moves(board);
+        let mut best_value = Wdl::Loss as i32;
+        let mut moves_searched = 0;
+
+        for mov in &moves {
+            let is_searched = if check_zeroing_moves { is_zeroing(board, mov) } else { is_capture(board, mov) };
+            if !is_searched {
+                continue;
+            }
+            moves_searched += 1;
+
+            let move_record = board.make_move(*mov);
+            let result = self.search(board, false);
+            board.undo_move(move_record);
+
+            let value = -result?.0;
+            if value > best_value {
+                best_value = value;
+
+                if value >= Wdl::Win as i32 {
+                    return Some((value, true));
+                }
+            }
+        }
+
+        // with every move searched the table isn't needed, and it could even be wrong in that case
+        let is_everything_searched = moves_searched != 0 && moves_searched == moves.len();
+        let value = if is_everything_searched {
+            best_value
+        } else {
+            match self.probe_table(board, TableKind::Wdl, 0)? {
+                TableProbe::Value(value) => value,
+                TableProbe::ChangeSideToMove => return None,
+            }
+        };
+
+        if best_value >= value {
+            return Some((best_value, best_value > Wdl::Draw as i32 || is_everything_searched));
+        }
+
+        Some((value, false))
+    }
+
+    fn probe_wdl_value(&self, board: &mut Board) -> Option<i32> {
+        self.search(board, false).map(|(wdl, _)| wdl)
+    }
+
+    fn probe_dtz_value(&self, board: &mut Board) -> Option<i32> {
+        let (wdl, is_zeroing_best) = self.search(board, true)?;
+
+        // the DTZ tables don't store the draws
+        if wdl == Wdl::Draw as i32 {
+            return Some(0);
+        }
+
+        // the table stores a "don't care" value when a zeroing move is the best one
+        if is_zeroing_best {
+            return Some(get_dtz_before_zeroing(wdl));
+        }
+
+        if let TableProbe::Value(dtz) = self.probe_table(board, TableKind::Dtz, wdl)? {
+            let is_cursed = wdl == Wdl::CursedWin as i32 || wdl == Wdl::BlessedLoss as i32;
+            return Some((dtz + RULE50_PLIES * is_cursed as i32) * wdl.signum());
+        }
+
+        // The table stores the other side to move, so the DTZ is found with a 1-ply search:
+        // the winning side takes the fastest move, the losing side the slowest one
+        let (moves, _) = generate_legal_moves(board);
+        let mut min_dtz = i32::MAX;
+
+        for mov in moves {
+            let is_zeroing_move = is_zeroing(board, &mov);
+            let move_record = board.make_move(mov);
+
+            // the zeroing moves start a new DTZ sequence, so the DTZ before the move is taken from the WDL after it
+            let dtz = if is_zeroing_move {
+                self.probe_wdl_value(board).map(|wdl| -get_dtz_before_zeroing(wdl))
+            } else {
+                self.probe_dtz_value(board).map(|dtz| -dtz)
+            };
+            let is_mating = dtz == Some(1) && is_checkmate(board);
+            board.undo_move(move_record);
+
+            let mut dtz = dtz?;
+            if is_mating {
+                min_dtz = 1;
+            }
+
+            if !is_zeroing_move {
+                dtz += dtz.signum();
+            }
+
+            if dtz < min_dtz && dtz.signum() == wdl.signum() {
+                min_dtz = dtz;
+            }
+        }
+
+        // no legal moves means checkmate
+        Some(if min_dtz == i32::MAX { -1 } else { min_dtz })
+    }
+
+    /// Probes the WDL tables. Doesn't take the 50 moves counter into account,
+    /// returns *None* if the position isn't in the tables
+    pub fn probe_wdl(&self, board: &mut Board) -> Option<Wdl> {
+        if !self.can_probe(board) {
+            return None;
+        }
+
+        self.probe_wdl_value(board).map(Wdl::from_value)
+    }
+
+    /// Probes the DTZ tables (the WDL ones are needed too), returns *None* if the position isn't in the tables
+    ///
+    /// The DTZ may be off by one ply for the tables storing the distance in moves
+    pub fn probe_dtz(&self, board: &mut Board) -> Option<i32> {
+        if !self.can_probe(board) {
+            return None;
+        }
+
+        self.probe_dtz_value(board)
+    }
+
+    /// Probes both tables and adjusts the result to the 50 moves counter of the board:
+    /// a win that can't zero the counter before it runs out is only a cursed win
+    pub fn probe(&self, board: &mut Board) -> Option<TablebaseProbe> {
+        let mut wdl = self.probe_wdl(board)?;
+        let dtz = self.probe_dtz(board);
+
+        if let Some(dtz) = dtz && dtz.abs() + board.rule50_count() as i32 > RULE50_PLIES {
+            wdl = match wdl {
+                Wdl::Win => Wdl::CursedWin,
+                Wdl::Loss => Wdl::BlessedLoss,
+                wdl => wdl,
+            };
+        }
+
+        Some(TablebaseProbe { wdl, dtz })
+    }
+
+    /// Ranks every legal move of the root position with the DTZ tables, taking the 50 moves counter
+    /// and the repetitions into account. The moves are sorted from the best to the worst
+    ///
+    /// Returns *None* if any of the positions isn't in the tables
+    pub fn rank_root_moves(&self, board: &mut Board) -> Option<Vec<RankedRootMove>> {
+        if !self.can_probe(board) {
+            return None;
+        }
+
+        let rule50 = board.rule50_count() as i32;
+        // after a repetition the opponent may go for the draw, so the wins aren't ranked by their distance
+        let has_repeated = board.get_position_history().values().any(|&count| count > 1);
+        let bound = MAX_DTZ / 2 - RULE50_PLIES;
+
+        let (moves, _) = generate_legal_moves(board);
+        let mut ranked_moves = Vec::with_capacity(moves.len());
+
+        for mov in moves {
+            let move_record = board.make_move(mov);
+
+            let dtz = if board.rule50_count() == 0 {
+                self.probe_wdl_value(board).map(|wdl| get_dtz_before_zeroing(-wdl))
+            } else if board.is_draw_by_repetition() {
+                Some(0)
+            } else {
+                // counting the move itself
+                self.probe_dtz_value(board).map(|dtz| -dtz - dtz.signum())
+            };
+            let is_mating = dtz == Some(2) && is_checkmate(board);
+            board.undo_move(move_record);
+
+            let dtz = if is_mating { 1 } else { dtz? };
+
+            let rank = if dtz > 0 {
+                if dtz + rule50 < RULE50_PLIES && !has_repeated { MAX_DTZ - dtz } else { MAX_DTZ / 2 - (dtz + rule50) }
+            } else if dtz < 0 {
+                if -dtz * 2 + rule50 < RULE50_PLIES { -MAX_DTZ - dtz } else { -MAX_DTZ / 2 + (-dtz + rule50) }
+            } else {
+                0
+            };
+
+            let wdl = if rank >= bound {
+                Wdl::Win
+            } else if rank > 0 {
+                Wdl::CursedWin
+            } else if rank == 0 {
+                Wdl::Draw
+            } else if rank > -bound {
+                Wdl::BlessedLoss
+            } else {
+                Wdl::Loss
+            };
+
+            ranked_moves.push(RankedRootMove { mov, wdl, dtz, rank });
+        }
+
+        ranked_moves.sort_by_key(|ranked_move| std::cmp::Reverse(ranked_move.rank));
+        Some(ranked_moves)
+    }
+}
@@ -0,0 +1,707 @@
+/*Parsing and decompression of a single Syzygy file (.rtbw or .rtbz).
+
+A file stores one or more subtables: one per side to move (WDL only, when the sides have different material)
+and one per file of the leading pawn (a-d, when there are pawns). Every subtable is a sequence of values
+indexed by the position encoding, compressed with Recursive Pairing followed by canonical Huffman codes
+and split into blocks, so only a single block has to be decoded to get a value*/
+
+use crate::{bitboards, board::Board, piece};
+
+use super::{encoding::{self, ENCODING_TABLES, MAX_PIECES}, TablebaseError, TableInfo};
+
+// constants
+const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+
+/// File header flag
+const HAS_PAWNS_FLAG: u8 = 2;
+
+/// Subtable flags
+const STM_FLAG: u8 = 1;
+const MAPPED_FLAG: u8 = 2;
+const WIN_PLIES_FLAG: u8 = 4;
+const LOSS_PLIES_FLAG: u8 = 8;
+const WIDE_FLAG: u8 = 16;
+const SINGLE_VALUE_FLAG: u8 = 128;
+
+/// Size of an entry of the sparse index: u32 block and u16 offset within the block
+const SPARSE_ENTRY_SIZE: usize = 6;
+/// Size of a node of the pairing tree: two 12 bit symbols
+const TREE_NODE_SIZE: usize = 3;
+/// Right symbol of the leaf nodes of the pairing tree
+const LEAF_SYMBOL: u16 = 0xFFF;
+/// Index into *map_idx* of the DTZ value map for every WDL value (loss, blessed loss, draw, cursed win, win)
+const WDL_TO_MAP_INDEX: [usize; 5] = [1, 3, 0, 2, 0];
+
+/// Pieces as they are stored in the files: pawn 1, knight 2, bishop 3, rook 4, queen 5, king 6, +8 for black
+const TB_PIECE_TYPES: [u8; 7] = [0, 6, 1, 2, 3, 4, 5];
+const TB_BLACK: u8 = 8;
+
+fn get_tb_piece(color: usize, piece_type: usize) -> u8 {
+    TB_PIECE_TYPES[piece_type] + if color == piece::BLACK { TB_BLACK } else { 0 }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+fn read_u32_be(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+fn read_u64_be(data: &[u8], offset: usize) -> u64 {
+    ((read_u32_be(data, offset) as u64) << 32) | read_u32_be(data, offset + 4) as u64
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TableKind {
+    Wdl,
+    Dtz,
+}
+
+/// Result of a lookup in a table
+pub enum TableProbe {
+    Value(i32),
+    /// DTZ tables only store a single side to move, the other one has to be found with a search
+    ChangeSideToMove,
+}
+
+/// Indexing and decompression data of a single subtable, the offsets point into the file data
+#[derive(Default, Clone)]
+struct PairsData {
+    flags: u8,
+    block_size: usize,
+    /// About every *span* values there is an entry of the sparse index
+    span: usize,
+    num_blocks: usize,
+    min_sym_len: usize,
+    /// Offset of the lowest symbol of every length
+    lowest_sym: usize,
+    /// Offset of the pairing tree, every symbol expands into its left and right symbol
+    btree: usize,
+    /// Offset of the amount of values (minus one) stored in every block
+    block_length: usize,
+    block_length_size: usize,
+    /// Offset of the entries pointing into *block_length*
+    sparse_index: usize,
+    sparse_index_size: usize,
+    /// Offset of the compressed data
+    data: usize,
+    /// Lowest symbol of every length, left aligned to 64 bits
+    base64: Vec<u64>,
+    /// Amount of values (minus one) every symbol expands into
+    symlen: Vec<u8>,
+    /// Pieces in the order they are encoded in
+    pieces: [u8; MAX_PIECES],
+    /// Multiplier of the index of every group of pieces encoded together, the last one is the table size
+    group_idx: [u64; MAX_PIECES + 1],
+    /// Amount of pieces in every group, zero terminated
+    group_len: [usize; MAX_PIECES + 1],
+    /// Offsets (into the DTZ map) of the value maps for win, loss, cursed win and blessed loss
+    map_idx: [usize; 4],
+}
+
+impl PairsData {
+    fn get_tree_symbols(&self, data: &[u8], sym: u16) -> (u16, u16) {
+        let node = self.btree + sym as usize * TREE_NODE_SIZE;
+        let (byte0, byte1, byte2) = (data[node] as u16, data[node + 1] as u16, data[node + 2] as u16);
+
+        (((byte1 & 0xF) << 8) | byte0, (byte2 << 4) | (byte1 >> 4))
+    }
+
+    fn get_block_length(&self, data: &[u8], block: usize) -> i64 {
+        read_u16(data, self.block_length + block * 2) as i64
+    }
+}
+
+/// Parsed Syzygy file, keeps the file data it points into
+pub struct Table {
+    kind: TableKind,
+    data: Vec<u8>,
+    /// Subtables by side to move and file of the leading pawn
+    items: [[PairsData; 4]; 2],
+    /// Offset of the DTZ value maps
+    map: usize,
+}
+
+/// Bounds-checked reader used while parsing so a broken file gives an error instead of a panic
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn ensure(&self, size: usize) -> Result<(), TablebaseError> {
+        if self.position + size > self.data.len() {
+            return Err(TablebaseError::Corrupted);
+        }
+        Ok(())
+    }
+
+    fn read_u8(&mut self) -> Result<u8, TablebaseError> {
+        self.ensure(1)?;
+        self.position += 1;
+        Ok(self.data[self.position - 1])
+    }
+
+    fn read_u16(&mut self) -> Result<u16, TablebaseError> {
+        self.ensure(2)?;
+        self.position += 2;
+        Ok(read_u16(self.data, self.position - 2))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, TablebaseError> {
+        self.ensure(4)?;
+        self.position += 4;
+        Ok(read_u32(self.data, self.position - 4))
+    }
+
+    fn skip(&mut self, size: usize) -> Result<(), TablebaseError> {
+        self.ensure(size)?;
+        self.position += size;
+        Ok(())
+    }
+
+    fn align(&mut self, alignment: usize) {
+        self.position = self.position.next_multiple_of(alignment);
+    }
+}
+
+/// Splits the pieces into the groups encoded together. A group is made of the pieces of the same type and color,
+/// except for the leading group: the leading pawns, the first three pieces if there is a unique piece
+/// (one of a kind, apart from the kings) or the two kings otherwise
+fn set_groups(info: &TableInfo, d: &mut PairsData, order: [u8; 2], file: usize) {
+    let mut n = 0;
+    let mut first_len: i32 = if info.has_pawns { 0 } else if info.has_unique_pieces { 3 } else { 2 };
+    d.group_len[n] = 1;
+
+    for i in 1..info.piece_count {
+        first_len -= 1;
+        if first_len > 0 || d.pieces[i] == d.pieces[i - 1] {
+            d.group_len[n] += 1;
+        } else {
+            n += 1;
+            d.group_len[n] = 1;
+        }
+    }
+    n += 1;
+    d.group_len[n] = 0;
+
+    // The groups are encoded in a per-table order: the leading group is at order[0]
+    // and the remaining pawns (when both sides have pawns) at order[1]
+    let both_sides_have_pawns = info.has_pawns && info.pawn_count[1] > 0;
+    let mut next = if both_sides_have_pawns { 2 } else { 1 };
+    let mut free_squares = 64 - d.group_len[0] - if both_sides_have_pawns { d.group_len[1] } else { 0 };
+    let mut idx: u64 = 1;
+
+    let mut k = 0;
+    while next < n || k == order[0] as usize || k == order[1] as usize {
+        if k == order[0] as usize {
+            d.group_idx[0] = idx;
+            idx *= if info.has_pawns {
+                ENCODING_TABLES.lead_pawns_size[d.group_len[0]][file]
+            } else if info.has_unique_pieces {
+                31_332
+            } else {
+                462
+            };
+        } else if k == order[1] as usize {
+            d.group_idx[1] = idx;
+            idx *= ENCODING_TABLES.binomial[d.group_len[1]][48 - d.group_len[0]];
+        } else {
+            d.group_idx[next] = idx;
+            idx *= ENCODING_TABLES.binomial[d.group_len[next]][free_squares];
+            free_squares -= d.group_len[next];
+            next += 1;
+        }
+        k += 1;
+    }
+
+    d.group_idx[n] = idx;
+}
+
+/// Returns the amount of values (minus one) the symbol expands into through the pairing tree
+fn set_symlen(d: &mut PairsData, data: &[u8], sym: u16, visited: &mut [bool]) -> u8 {
+    visited[sym as usize] = true; // the tree is acyclic
+    let (left, right) = d.get_tree_symbols(data, sym);
+
+    if right == LEAF_SYMBOL {
+        return 0;
+    }
+
+    for child in [left, right] {
+        if (child as usize) < visited.len() && !visited[child as usize] {
+            d.symlen[child as usize] = set_symlen(d, data, child, visited);
+        }
+    }
+
+    let left_len = d.symlen.get(left as usize).copied().unwrap_or(0);
+    let right_len = d.symlen.get(right as usize).copied().unwrap_or(0);
+    left_len.wrapping_add(right_len).wrapping_add(1)
+}
+
+/// Reads the sizes and the Huffman code of the subtable
+fn set_sizes(d: &mut PairsData, reader: &mut Reader) -> Result<(), TablebaseError> {
+    d.flags = reader.read_u8()?;
+
+    if d.flags & SINGLE_VALUE_FLAG != 0 {
+        // every position of the subtable has the same value, stored in place of the symbol length
+        d.min_sym_len = reader.read_u8()? as usize;
+        return Ok(());
+    }
+
+    let group_count = d.group_len.iter().position(|&len| len == 0).unwrap_or(MAX_PIECES);
+    let table_size = d.group_idx[group_count];
+
+    d.block_size = 1 << reader.read_u8()?.min(31);
+    d.span = 1 << reader.read_u8()?.min(31);
+    d.sparse_index_size = table_size.div_ceil(d.span as u64) as usize;
+    let padding = reader.read_u8()? as usize;
+    d.num_blocks = reader.read_u32()? as usize;
+    // padded so the sparse index never points out of range
+    d.block_length_size = d.num_blocks + padding;
+    let max_sym_len = reader.read_u8()? as usize;
+    d.min_sym_len = reader.read_u8()? as usize;
+
+    if d.min_sym_len == 0 || max_sym_len < d.min_sym_len || max_sym_len > 64 {
+        return Err(TablebaseError::Corrupted);
+    }
+
+    d.lowest_sym = reader.position;
+    let sym_len_count = max_sym_len - d.min_sym_len + 1;
+    reader.skip(sym_len_count * 2)?;
+    let data = reader.data;
+    let get_lowest_sym = |len: usize| read_u16(data, d.lowest_sym + len * 2) as u64;
+
+    // The longer codes have lower values, so the lowest symbol of every length
+    // left aligned to 64 bits gives the ranges to find the length of a code
+    d.base64 = vec![0; sym_len_count];
+    for i in (0..sym_len_count - 1).rev() {
+        d.base64[i] = d.base64[i + 1].wrapping_add(get_lowest_sym(i)).wrapping_sub(get_lowest_sym(i + 1)) / 2;
+    }
+    for (i, base) in d.base64.iter_mut().enumerate() {
+        *base = base.checked_shl((64 - i - d.min_sym_len) as u32).unwrap_or(0);
+    }
+
+    let symbol_count = reader.read_u16()? as usize;
+    d.btree = reader.position;
+    reader.skip(symbol_count * TREE_NODE_SIZE + (symbol_count & 1))?;
+
+    d.symlen = vec![0; symbol_count];
+    let mut visited = vec![false; symbol_count];
+    for sym in 0..symbol_count {
+        if !visited[sym] {
+            d.symlen[sym] = set_symlen(d, data, sym as u16, &mut visited);
+        }
+    }
+
+    Ok(())
+}
+
+impl Table {
+    /// Parses the file data, the material of the table has to match the file
+    pub fn parse(data: Vec<u8>, kind: TableKind, info: &TableInfo) -> Result<Self, TablebaseError> {
+        let magic = match kind {
+            TableKind::Wdl => WDL_MAGIC,
+            TableKind::Dtz => DTZ_MAGIC,
+        };
+        if data.len() < 5 || data[..4] != magic {
+            return Err(TablebaseError::InvalidMagic);
+        }
+
+        let mut items: [[PairsData; 4]; 2] = Default::default();
+        let mut reader = Reader { data: &data, position: 4 };
+
+        let flags = reader.read_u8()?;
+        if (flags & HAS_PAWNS_FLAG != 0) != info.has_pawns {
+            return Err(TablebaseError::Corrupted);
+        }
+
+        let sides = if kind == TableKind::Wdl && info.key != info.key2 { 2 } else { 1 };
+        let max_file = if info.has_pawns { 3 } else { 0 };
+        let both_sides_have_pawns = info.has_pawns && info.pawn_count[1] > 0;
+
+        for file in 0..=max_file {
+            let order_byte = reader.read_u8()?;
+            let pawn_order_byte = if both_sides_have_pawns { reader.read_u8()? } else { 0xFF };
+            let order = [
+                [order_byte & 0xF, pawn_order_byte & 0xF],
+                [order_byte >> 4, pawn_order_byte >> 4],
+            ];
+
+            for k in 0..info.piece_count {
+                let pieces_byte = reader.read_u8()?;
+                for (side, side_items) in items.iter_mut().enumerate().take(sides) {
+                    side_items[file].pieces[k] = if side == 1 { pieces_byte >> 4 } else { pieces_byte & 0xF };
+                }
+            }
+
+            for (side, side_items) in items.iter_mut().enumerate().take(sides) {
+                set_groups(info, &mut side_items[file], order[side], file);
+            }
+        }
+        reader.align(2);
+
+        for file in 0..=max_file {
+            for side_items in items.iter_mut().take(sides) {
+                set_sizes(&mut side_items[file], &mut reader)?;
+            }
+        }
+
+        let map = reader.position;
+        if kind == TableKind::Dtz {
+            for d in items[0].iter_mut().take(max_file + 1) {
+                if d.flags & MAPPED_FLAG == 0 {
+                    continue;
+                }
+
+                if d.flags & WIDE_FLAG != 0 {
+                    reader.align(2);
+                    for i in 0..4 {
+                        d.map_idx[i] = (reader.position - map) / 2 + 1;
+                        let length = reader.read_u16()? as usize;
+                        reader.skip(length * 2)?;
+                    }
+                } else {
+                    for i in 0..4 {
+                        d.map_idx[i] = reader.position - map + 1;
+                        let length = reader.read_u8()? as usize;
+                        reader.skip(length)?;
+                    }
+                }
+            }
+            reader.align(2);
+        }
+
+        for file in 0..=max_file {
+            for side_items in items.iter_mut().take(sides) {
+                side_items[file].sparse_index = reader.position;
+                reader.skip(side_items[file].sparse_index_size * SPARSE_ENTRY_SIZE)?;
+            }
+        }
+
+        for file in 0..=max_file {
+            for side_items in items.iter_mut().take(sides) {
+                side_items[file].block_length = reader.position;
+                reader.skip(side_items[file].block_length_size * 2)?;
+            }
+        }
+
+        for file in 0..=max_file {
+            for side_items in items.iter_mut().take(sides) {
+                reader.align(64);
+                side_items[file].data = reader.position;
+                reader.skip(side_items[file].num_blocks * side_items[file].block_size)?;
+            }
+        }
+
+        Ok(Self { kind, data, items, map })
+    }
+
+    fn get(&self, side_to_move: usize, file: usize) -> &PairsData {
+        let sides = if self.kind == TableKind::Wdl { 2 } else { 1 };
+        &self.items[side_to_move % sides][file]
+    }
+
+    /// Returns the value stored at the provided index of the subtable
+    fn decompress_pairs(&self, d: &PairsData, idx: u64) -> i32 {
+        if d.flags & SINGLE_VALUE_FLAG != 0 {
+            return d.min_sym_len as i32;
+        }
+
+        let data = &self.data[..];
+
+        // The sparse index points to the block containing the value about every span values,
+        // the right block is found by walking the blocks from there
+        let k = (idx / d.span as u64) as usize;
+        let sparse_entry = d.sparse_index + k * SPARSE_ENTRY_SIZE;
+        let mut block = read_u32(data, sparse_entry) as i64;
+        let mut offset = read_u16(data, sparse_entry + 4) as i64;
+
+        offset += (idx % d.span as u64) as i64 - (d.span / 2) as i64;
+
+        while offset < 0 {
+            block -= 1;
+            offset += d.get_block_length(data, block as usize) + 1;
+        }
+        while offset > d.get_block_length(data, block as usize) {
+            offset -= d.get_block_length(data, block as usize) + 1;
+            block += 1;
+        }
+
+        // The block is a sequence of Huffman codes, each of them standing for a symbol
+        // that expands into *symlen + 1* values
+        let mut ptr = d.data + block as usize * d.block_size;
+        let mut buf64 = read_u64_be(data, ptr);
+        ptr += 8;
+        let mut buf64_size = 64;
+        let mut sym;
+
+        loop {
+            let mut len = 0;
+            while buf64 < d.base64[len] {
+                len += 1;
+            }
+
+            sym = ((buf64 - d.base64[len]) >> (64 - len - d.min_sym_len)) as u16;
+            sym = sym.wrapping_add(read_u16(data, d.lowest_sym + len * 2));
+
+            let values = d.symlen[sym as usize] as i64 + 1;
+            if offset < values {
+                break;
+            }
+
+            offset -= values;
+            len += d.min_sym_len;
+            buf64 = buf64.checked_shl(len as u32).unwrap_or(0);
+            buf64_size -= len;
+
+            if buf64_size <= 32 {
+                buf64_size += 32;
+                buf64 |= (read_u32_be(data, ptr) as u64) << (64 - buf64_size);
+                ptr += 4;
+            }
+        }
+
+        // the values of a symbol are the values of its left symbol followed by the ones of its right symbol
+        while d.symlen[sym as usize] != 0 {
+            let (left, right) = d.get_tree_symbols(data, sym);
+            let left_values = d.symlen[left as usize] as i64 + 1;
+
+            if offset < left_values {
+                sym = left;
+            } else {
+                offset -= left_values;
+                sym = right;
+            }
+        }
+
+        d.get_tree_symbols(data, sym).0 as i32
+    }
+
+    /// DTZ values are stored by frequency, the map gives the real value
+    /// (in moves or plies depending on the flags, always returned in plies)
+    fn map_dtz_score(&self, file: usize, value: i32, wdl: i32) -> i32 {
+        let d = self.get(0, file);
+        let mut value = value;
+
+        if d.flags & MAPPED_FLAG != 0 {
+            let map_idx = d.map_idx[WDL_TO_MAP_INDEX[(wdl + 2) as usize]];
+            value = if d.flags & WIDE_FLAG != 0 {
+                read_u16(&self.data, self.map + (map_idx + value as usize) * 2) as i32
+            } else {
+                self.data[self.map + map_idx + value as usize] as i32
+            };
+        }
+
+        let is_in_plies = match wdl {
+            2 => d.flags & WIN_PLIES_FLAG != 0,
+            -2 => d.flags & LOSS_PLIES_FLAG != 0,
+            0 => true,
+            _ => false,
+        };
+        if !is_in_plies {
+            value *= 2;
+        }
+
+        value + 1
+    }
+
+    /// Looks the position up, *wdl* is the WDL value of the position (only used by DTZ tables)
+    ///
+    /// The material of the board must match the table
+    pub fn probe(&self, info: &TableInfo, board: &Board, wdl: i32) -> TableProbe {
+        let Some((side_to_move, tb_file, idx)) = self.get_index(info, board) else {
+            return TableProbe::ChangeSideToMove;
+        };
+
+        let value = self.decompress_pairs(self.get(side_to_move, tb_file), idx);
+
+        match self.kind {
+            TableKind::Wdl => TableProbe::Value(value - 2),
+            TableKind::Dtz => TableProbe::Value(self.map_dtz_score(tb_file, value, wdl)),
+        }
+    }
+
+    /// Returns the side to move and the file of the leading pawn of the subtable storing the position,
+    /// and the index of the position in it
+    ///
+    /// Returns *None* if the position is in the DTZ table of the other side to move
+    fn get_index(&self, info: &TableInfo, board: &Board) -> Option<(usize, usize, u64)> {
+        let tables = &*ENCODING_TABLES;
+        let color_to_move = board.get_current_color();
+
+        // The tables are stored with the stronger side (first in the name) being white, and the symmetric ones
+        // only with white to move. Otherwise the colors are swapped and the board is flipped vertically
+        let is_symmetric_black_to_move = info.key == info.key2 && color_to_move == piece::BLACK;
+        let is_black_stronger = super::get_material_key(board) != info.key;
+        let flip = is_symmetric_black_to_move || is_black_stronger;
+        let flip_color = if flip { TB_BLACK } else { 0 };
+        let flip_squares = if flip { 56 } else { 0 };
+        let side_to_move = flip as usize ^ color_to_move;
+
+        let mut squares = [0usize; MAX_PIECES];
+        let mut pieces = [0u8; MAX_PIECES];
+        let mut size = 0;
+        let mut lead_pawns_count = 0;
+        let mut lead_pawns_bb = 0;
+        let mut tb_file = 0;
+
+        // The leading pawn is the one closest to the edge and then to the first rank,
+        // the subtable depends on its file
+        if info.has_pawns {
+            let lead_piece = self.get(0, 0).pieces[0] ^ flip_color;
+            let lead_color = if lead_piece & TB_BLACK != 0 { piece::BLACK } else { piece::WHITE };
+
+            lead_pawns_bb = board.get_piece_bitboard(lead_color, piece::PAWN);
+            let mut pawns_bb = lead_pawns_bb;
+            while pawns_bb != 0 {
+                let square = bitboards::get_ls1b(pawns_bb);
+                pawns_bb &= pawns_bb - 1;
+
+                squares[size] = encoding::to_tb_square(square) ^ flip_squares;
+                size += 1;
+            }
+            lead_pawns_count = size;
+
+            let lead_index = (0..lead_pawns_count)
+                .fold(0, |best, i| if tables.map_pawns[squares[i]] > tables.map_pawns[squares[best]] { i } else { best });
+            squares.swap(0, lead_index);
+
+            tb_file = encoding::map_to_queen_side(encoding::get_file(squares[0]));
+        }
+
+        if self.kind == TableKind::Dtz {
+            let flags = self.get(side_to_move, tb_file).flags;
+            let is_stored = (flags & STM_FLAG) as usize == side_to_move || (info.key == info.key2 && !info.has_pawns);
+            if !is_stored {
+                return None;
+            }
+        }
+
+        let mut pieces_bb = board.get_all_occupied_squares() ^ lead_pawns_bb;
+        while pieces_bb != 0 {
+            let square = bitboards::get_ls1b(pieces_bb);
+            pieces_bb &= pieces_bb - 1;
+
+            let (color, piece_type) = board.get_piece_on_square(square);
+            squares[size] = encoding::to_tb_square(square) ^ flip_squares;
+            pieces[size] = get_tb_piece(color, piece_type) ^ flip_color;
+            size += 1;
+        }
+
+        let d = self.get(side_to_move, tb_file);
+
+        // reordering the pieces to the order they are encoded in
+        for i in lead_pawns_count..size.saturating_sub(1) {
+            if let Some(j) = (i + 1..size).find(|&j| pieces[j] == d.pieces[i]) {
+                pieces.swap(i, j);
+                squares.swap(i, j);
+            }
+        }
+
+        // the leading piece is always mapped to the files a-d
+        if encoding::get_file(squares[0]) > 3 {
+            for square in squares.iter_mut().take(size) {
+                *square ^= 7;
+            }
+        }
+
+        let mut idx = if info.has_pawns {
+            let mut idx = tables.lead_pawn_idx[lead_pawns_count][squares[0]];
+
+            squares[1..lead_pawns_count].sort_by_key(|&square| tables.map_pawns[square]);
+            for (i, &square) in squares.iter().enumerate().take(lead_pawns_count).skip(1) {
+                idx += tables.binomial[i][tables.map_pawns[square]];
+            }
+
+            idx
+        } else {
+            self.encode_leading_pieces(d, info, &mut squares[..size])
+        };
+
+        // The remaining groups are encoded by their squares in ascending order, skipping the squares
+        // taken by the previous groups (and the first and last ranks for the remaining pawns)
+        idx *= d.group_idx[0];
+        let mut group_start = d.group_len[0];
+        let mut are_remaining_pawns = info.has_pawns && info.pawn_count[1] > 0;
+        let mut next = 1;
+
+        while d.group_len[next] != 0 {
+            let group_len = d.group_len[next];
+            squares[group_start..group_start + group_len].sort_unstable();
+
+            let mut n = 0;
+            for i in 0..group_len {
+                let square = squares[group_start + i];
+                let adjust = squares[..group_start].iter().filter(|&&previous| square > previous).count();
+                n += tables.binomial[i + 1][square - adjust - 8 * are_remaining_pawns as usize];
+            }
+
+            are_remaining_pawns = false;
+            idx += n * d.group_idx[next];
+            group_start += group_len;
+            next += 1;
+        }
+
+        Some((side_to_move, tb_file, idx))
+    }
+
+    /// Encodes the leading group of a pawnless table: the three first pieces if there is a unique piece,
+    /// the kings otherwise. The board symmetries are used to put the first piece into the a1-d1-d4 triangle
+    fn encode_leading_pieces(&self, d: &PairsData, info: &TableInfo, squares: &mut [usize]) -> u64 {
+        let tables = &*ENCODING_TABLES;
+
+        if encoding::get_rank(squares[0]) > 3 {
+            for square in squares.iter_mut() {
+                *square ^= 56;
+            }
+        }
+
+        // the first piece of the leading group that is off the a1-h8 diagonal is mapped below it
+        for i in 0..d.group_len[0] {
+            let offset = encoding::get_diagonal_offset(squares[i]);
+            if offset == 0 {
+                continue;
+            }
+
+            if offset > 0 {
+                for square in squares[i..].iter_mut() {
+                    *square = ((*square >> 3) | (*square << 3)) & 63;
+                }
+            }
+            break;
+        }
+
+        if !info.has_unique_pieces {
+            return tables.map_kk[tables.map_a1d1d4[squares[0]]][squares[1]] as u64;
+        }
+
+        let (square0, square1, square2) = (squares[0], squares[1], squares[2]);
+        let adjust1 = (square1 > square0) as usize;
+        let adjust2 = (square2 > square0) as usize + (square2 > square1) as usize;
+        let rank0 = encoding::get_rank(square0);
+
+        let idx = if encoding::get_diagonal_offset(square0) != 0 {
+            (tables.map_a1d1d4[square0] * 63 + (square1 - adjust1)) * 62 + square2 - adjust2
+        } else if encoding::get_diagonal_offset(square1) != 0 {
+            (6 * 63 + rank0 * 28 + tables.map_b1h1h7[square1]) * 62 + square2 - adjust2
+        } else if encoding::get_diagonal_offset(square2) != 0 {
+            6 * 63 * 62 + 4 * 28 * 62
+                + rank0 * 7 * 28
+                + (encoding::get_rank(square1) - adjust1) * 28
+                + tables.map_b1h1h7[square2]
+        } else {
+            6 * 63 * 62 + 4 * 28 * 62 + 4 * 7 * 28
+                + rank0 * 7 * 6
+                + (encoding::get_rank(square1) - adjust1) * 6
+                + (encoding::get_rank(square2) - adjust2)
+        };
+
+        idx as u64
+    }
+}
@@ -0,0 +1,92 @@
+/*Builds a Polyglot opening book out of PGN files.
+
+Usage: minamoto-book [--max-ply <plies>] [--min-games <games>] <book.bin> <games.pgn>...
+
+Every finished game is replayed up to the ply limit, the moves played in fewer games than the minimum are left out.
+Unfinished games and games with a malformed FEN tag or an illegal or ambiguous move are skipped*/
+
+use std::{env, fs, process};
+
+use minamoto_chess::pgn;
+use minamoto_chess_core::book::builder::BookBuilder;
+use minamoto_chess_engine::cli;
+
+// constants
+const DEFAULT_MAX_PLY: usize = 20;
+const DEFAULT_MIN_GAMES: u32 = 3;
+const USAGE: &str = "Usage: minamoto-book [--max-ply <plies>] [--min-games <games>] <book.bin> <games.pgn>...";
+
+fn main() {
+    let mut max_ply = DEFAULT_MAX_PLY;
+    let mut min_games = DEFAULT_MIN_GAMES;
+    let mut paths = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--max-ply" => max_ply = cli::parse_value("--max-ply", args.next(), USAGE),
+            "--min-games" => min_games = cli::parse_value("--min-games", args.next(), USAGE),
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ => paths.push(arg),
+        }
+    }
+
+    let Some((book_path, pgn_paths)) = paths.split_first() else {
+        cli::exit_with_usage("Missing the book path", USAGE);
+    };
+    if pgn_paths.is_empty() {
+        cli::exit_with_usage("Missing the PGN files", USAGE);
+    }
+
+    let mut builder = BookBuilder::new(max_ply);
+    let mut skipped_games = 0;
+
+    for pgn_path in pgn_paths {
+        let text = match fs::read(pgn_path) {
+            // Old PGN files often come in Latin-1, the moves are ASCII anyway
+            Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            Err(error) => {
+                eprintln!("{}: {}", pgn_path, error);
+                continue;
+            }
+        };
+
+        for (game_index, game) in pgn::parse_games(&text).iter().enumerate() {
+            let Some(result) = game.get_result() else {
+                skipped_games += 1;
+                continue;
+            };
+
+            let replay = game.get_start_board()
+                .and_then(|start_board| game.get_mainline_moves().map(|moves| (start_board, moves)));
+            let (mut start_board, moves) = match replay {
+                Ok(start_and_moves) => start_and_moves,
+                Err(error) => {
+                    eprintln!("{}, game {}: {}", pgn_path, game_index + 1, error);
+                    skipped_games += 1;
+                    continue;
+                }
+            };
+
+            builder.add_game(&mut start_board, &moves, result);
+        }
+    }
+
+    let book = builder.build(min_games);
+    if let Err(error) = book.to_file(book_path) {
+        eprintln!("{}: {}", book_path, error);
+        process::exit(1);
+    }
+
+    println!(
+        "Wrote {} entries from {} positions of {} games ({} games skipped) to {}",
+        book.len(),
+        builder.get_position_count(),
+        builder.get_game_count(),
+        skipped_games,
+        book_path
+    );
+}
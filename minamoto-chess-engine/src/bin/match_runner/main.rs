@@ -0,0 +1,211 @@
+/*Plays a match between two configurations of the engine to check whether a change makes it stronger.
+
+Usage: minamoto-match --openings <file> [--engine1 <options>] [--engine2 <options>] [--games <games>]
+    [--depth <depth> | --nodes <nodes> | --movetime <ms>] [--concurrency <threads>] [--sprt <elo0> <elo1> [--alpha <alpha>] [--beta <beta>]]
+
+Every opening (a FEN or an EPD line) is played twice with the colors swapped, so an unbalanced opening
+doesn't favor either engine. The games are played until *GameState* reports a mate or a draw.
+The engine options are comma separated "key=value" pairs, see *PlayerConfig*.
+
+With the SPRT bounds the match stops as soon as the result is clear, the game count becomes the maximum*/
+
+mod player;
+mod stats;
+
+use std::{env, fs, process, sync::{atomic::{AtomicBool, AtomicUsize, Ordering}, mpsc}, thread};
+
+use minamoto_chess::{fen_api, game::GameState};
+use minamoto_chess_core::{move_generation::{attack_calculator::AttackCalculator, move_gen}, search::search_limits::SearchLimits};
+use minamoto_chess_engine::cli;
+
+use player::{Player, PlayerConfig};
+use stats::{GameOutcome, MatchStats, Sprt, SprtResult};
+
+// constants
+const DEFAULT_GAMES: usize = 1000;
+const DEFAULT_MOVETIME_MS: u64 = 100;
+const DEFAULT_SPRT_ALPHA: f64 = 0.05;
+const DEFAULT_SPRT_BETA: f64 = 0.05;
+/// Every how many games the standings are printed
+const REPORT_INTERVAL: u32 = 10;
+const USAGE: &str = "Usage: minamoto-match --openings <file> [--engine1 <options>] [--engine2 <options>] [--games <games>] \
+    [--depth <depth> | --nodes <nodes> | --movetime <ms>] [--concurrency <threads>] [--sprt <elo0> <elo1> [--alpha <alpha>] [--beta <beta>]]";
+
+fn parse_player(default_name: &str, value: Option<String>) -> PlayerConfig {
+    PlayerConfig::parse(default_name, &value.unwrap_or_default()).unwrap_or_else(|error| cli::exit_with_usage(&error, USAGE))
+}
+
+/// Takes the FEN out of a line of the openings file, the EPD opcodes after the first 4 fields are dropped
+fn parse_opening(line: &str) -> Option<String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let counter_count = tokens.iter().skip(4).take(2).take_while(|token| token.parse::<u16>().is_ok()).count();
+    let fen = tokens[..tokens.len().min(4 + counter_count)].join(" ");
+
+    fen_api::is_valid_fen(&fen).then_some(fen)
+}
+
+/// Plays the game from the opening to the end, returns the outcome for the first engine
+fn play_game(first: &mut Player, second: &mut Player, opening: &str, first_is_white: bool, limits: &SearchLimits) -> GameOutcome {
+    first.new_game(opening);
+    second.new_game(opening);
+
+    loop {
+        let board = first.get_board();
+        let attack_calc = AttackCalculator::new(board);
+        let mut legal_moves = move_gen::create_empty_move_buffer();
+        move_gen::generate_moves(&mut legal_moves, board, &attack_calc);
+
+        let first_to_move = board.is_white_to_move() == first_is_white;
+        match GameState::from_current_state(board, &legal_moves, &attack_calc) {
+            GameState::WhiteWon => return if first_is_white { GameOutcome::Win } else { GameOutcome::Loss },
+            GameState::BlackWon => return if first_is_white { GameOutcome::Loss } else { GameOutcome::Win },
+            GameState::Draw => return GameOutcome::Draw,
+            GameState::WhiteToMove | GameState::BlackToMove => {}
+        }
+
+        let mover = if first_to_move { &mut *first } else { &mut *second };
+        let mov = mover.search(limits).expect("The search always finds a move when there are legal ones");
+
+        first.make_move(mov);
+        second.make_move(mov);
+    }
+}
+
+fn print_standings(stats: &MatchStats, sprt: Option<&Sprt>) {
+    let mut standings = format!(
+        "Games {}: +{} ={} -{}, Elo {:.1} +/- {:.1}",
+        stats.get_game_count(),
+        stats.wins,
+        stats.draws,
+        stats.losses,
+        stats.get_elo(),
+        stats.get_elo_error()
+    );
+
+    if let Some(sprt) = sprt {
+        let (lower_bound, upper_bound) = sprt.get_bounds();
+        standings.push_str(&format!(", LLR {:.2} ({:.2}, {:.2})", sprt.get_llr(stats), lower_bound, upper_bound));
+    }
+
+    println!("{}", standings);
+}
+
+fn main() {
+    let mut first_config = PlayerConfig::parse("engine1", "").unwrap();
+    let mut second_config = PlayerConfig::parse("engine2", "").unwrap();
+    let mut openings_path = None;
+    let mut game_count = DEFAULT_GAMES;
+    let mut limits = SearchLimits::from_movetime(DEFAULT_MOVETIME_MS);
+    let mut concurrency = 1;
+    let mut sprt_elos: Option<(f64, f64)> = None;
+    let mut alpha = DEFAULT_SPRT_ALPHA;
+    let mut beta = DEFAULT_SPRT_BETA;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--engine1" => first_config = parse_player("engine1", args.next()),
+            "--engine2" => second_config = parse_player("engine2", args.next()),
+            "--openings" => openings_path = args.next(),
+            "--games" => game_count = cli::parse_value("--games", args.next(), USAGE),
+            "--depth" => limits = SearchLimits::from_depth(cli::parse_value("--depth", args.next(), USAGE)),
+            "--nodes" => limits = SearchLimits::from_nodes(cli::parse_value("--nodes", args.next(), USAGE)),
+            "--movetime" => limits = SearchLimits::from_movetime(cli::parse_value("--movetime", args.next(), USAGE)),
+            "--concurrency" => concurrency = cli::parse_value::<usize>("--concurrency", args.next(), USAGE).max(1),
+            "--sprt" => sprt_elos = Some((cli::parse_value("--sprt", args.next(), USAGE), cli::parse_value("--sprt", args.next(), USAGE))),
+            "--alpha" => alpha = cli::parse_value("--alpha", args.next(), USAGE),
+            "--beta" => beta = cli::parse_value("--beta", args.next(), USAGE),
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ => cli::exit_with_usage(&format!("Unknown argument: {}", arg), USAGE),
+        }
+    }
+
+    let Some(openings_path) = openings_path else {
+        cli::exit_with_usage("Missing the openings file", USAGE);
+    };
+    let openings: Vec<String> = match fs::read_to_string(&openings_path) {
+        Ok(text) => text.lines().filter_map(parse_opening).collect(),
+        Err(error) => {
+            eprintln!("{}: {}", openings_path, error);
+            process::exit(1);
+        }
+    };
+    if openings.is_empty() {
+        cli::exit_with_usage("No valid openings in the openings file", USAGE);
+    }
+
+    // every opening is played by both colors
+    let game_count = game_count.next_multiple_of(2);
+    let sprt = sprt_elos.map(|(elo0, elo1)| Sprt::new(elo0, elo1, alpha, beta));
+
+    println!("{} vs {}: up to {} games from {} openings", first_config.name, second_config.name, game_count, openings.len());
+
+    let next_game = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let (sender, receiver) = mpsc::channel();
+    let mut stats = MatchStats::default();
+    let mut sprt_result = SprtResult::Continue;
+
+    thread::scope(|scope| {
+        for _ in 0..concurrency {
+            let sender = sender.clone();
+            let (first_config, second_config, openings, limits, next_game, stop) =
+                (&first_config, &second_config, &openings, &limits, &next_game, &stop);
+
+            scope.spawn(move || {
+                let mut first = Player::new(first_config);
+                let mut second = Player::new(second_config);
+
+                while !stop.load(Ordering::Relaxed) {
+                    let game_index = next_game.fetch_add(1, Ordering::Relaxed);
+                    if game_index >= game_count {
+                        break;
+                    }
+
+                    let opening = &openings[(game_index / 2) % openings.len()];
+                    let outcome = play_game(&mut first, &mut second, opening, game_index % 2 == 0, limits);
+                    if sender.send(outcome).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        for outcome in receiver.iter() {
+            stats.add(outcome);
+
+            if let Some(sprt) = &sprt {
+                sprt_result = sprt.get_result(&stats);
+            }
+
+            if stats.get_game_count() % REPORT_INTERVAL == 0 {
+                print_standings(&stats, sprt.as_ref());
+            }
+
+            if !matches!(sprt_result, SprtResult::Continue) {
+                // the games still being played are left out of the result
+                stop.store(true, Ordering::Relaxed);
+                break;
+            }
+        }
+    });
+
+    println!();
+    println!("Final result of {} vs {}:", first_config.name, second_config.name);
+    print_standings(&stats, sprt.as_ref());
+    match sprt_result {
+        SprtResult::H1Accepted => println!("SPRT: H1 accepted"),
+        SprtResult::H0Accepted => println!("SPRT: H0 accepted"),
+        SprtResult::Continue if sprt.is_some() => println!("SPRT: inconclusive after the maximum amount of games"),
+        SprtResult::Continue => {}
+    }
+}
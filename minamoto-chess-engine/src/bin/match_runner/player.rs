@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use minamoto_chess::fen_api::FenApi;
+use minamoto_chess_core::{board::Board, eval::nnue::Network, r#move::Move, search::{search_limits::SearchLimits, search_options::SearchOptions, searcher::{self, Searcher}, transposition_table}};
+
+// constants
+/// Hash size of the players, smaller than the UCI default since many games run at once
+const DEFAULT_PLAYER_HASH_MB: usize = 16;
+
+/// Settings of one side of the match, parsed from comma separated "key=value" pairs:
+/// *name*, *hash* (MB), *threads*, *eval_file* (NNUE path) and the fields of *SearchOptions* set to true or false
+#[derive(Clone)]
+pub struct PlayerConfig {
+    pub name: String,
+    pub hash_mb: usize,
+    pub threads: usize,
+    pub options: SearchOptions,
+    pub network: Option<Arc<Network>>,
+}
+
+impl PlayerConfig {
+    pub fn parse(default_name: &str, text: &str) -> Result<Self, String> {
+        let mut config = Self {
+            name: default_name.to_string(),
+            hash_mb: DEFAULT_PLAYER_HASH_MB,
+            threads: 1,
+            options: SearchOptions::default(),
+            network: None,
+        };
+
+        for pair in text.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').ok_or_else(|| format!("Expected key=value, got \"{}\"", pair))?;
+            let invalid_value = || format!("Invalid {} value: {}", key, value);
+            let parse_flag = || value.parse::<bool>().map_err(|_| invalid_value());
+
+            match key {
+                "name" => config.name = value.to_string(),
+                "hash" => config.hash_mb = value.parse::<usize>().ok()
+                    .filter(|size_mb| (1..=transposition_table::MAX_SIZE_MB).contains(size_mb))
+                    .ok_or_else(invalid_value)?,
+                "threads" => config.threads = value.parse::<usize>().ok()
+                    .filter(|threads| (1..=searcher::MAX_THREADS).contains(threads))
+                    .ok_or_else(invalid_value)?,
+                "eval_file" => config.network = Some(Arc::new(Network::from_file(value).map_err(|error| error.to_string())?)),
+                "null_move_pruning" => config.options.null_move_pruning = parse_flag()?,
+                "late_move_reductions" => config.options.late_move_reductions = parse_flag()?,
+                "aspiration_windows" => config.options.aspiration_windows = parse_flag()?,
+                "check_extensions" => config.options.check_extensions = parse_flag()?,
+                "futility_pruning" => config.options.futility_pruning = parse_flag()?,
+                _ => return Err(format!("Unknown player option: {}", key)),
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+/// One side of a game, keeps its own board so every player evaluates with its own network
+pub struct Player {
+    board: Board,
+    searcher: Searcher,
+    network: Option<Arc<Network>>,
+}
+
+impl Player {
+    pub fn new(config: &PlayerConfig) -> Self {
+        let mut searcher = Searcher::with_hash_size(config.hash_mb);
+        searcher.set_options(config.options);
+        searcher.set_thread_count(config.threads);
+
+        Self {
+            board: Board::empty(),
+            searcher,
+            network: config.network.clone(),
+        }
+    }
+
+    /// Sets up the position and forgets everything learned in the previous game
+    pub fn new_game(&mut self, fen: &str) {
+        self.board = Board::from_fen(fen);
+        self.board.set_network(self.network.clone());
+        self.searcher.clear();
+    }
+
+    pub fn get_board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn search(&mut self, limits: &SearchLimits) -> Option<Move> {
+        self.searcher.search_with_limits(&mut self.board, limits, |_| {}).best_move
+    }
+
+    /// Plays a move of either side on the board of the player
+    pub fn make_move(&mut self, mov: Move) {
+        self.board.make_move(mov);
+    }
+}
@@ -0,0 +1,142 @@
+// constants
+/// Two-sided 95% quantile of the normal distribution, used for the error bars
+const CONFIDENCE_QUANTILE: f64 = 1.959964;
+
+pub enum GameOutcome {
+    Win,
+    Draw,
+    Loss,
+}
+
+/// Games of the match from the perspective of the first engine
+#[derive(Clone, Copy, Default, Debug)]
+pub struct MatchStats {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MatchStats {
+    pub fn add(&mut self, outcome: GameOutcome) {
+        match outcome {
+            GameOutcome::Win => self.wins += 1,
+            GameOutcome::Draw => self.draws += 1,
+            GameOutcome::Loss => self.losses += 1,
+        }
+    }
+
+    pub fn get_game_count(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Average points per game, from 0 to 1
+    pub fn get_score(&self) -> f64 {
+        let game_count = self.get_game_count();
+        if game_count == 0 {
+            return 0.5;
+        }
+
+        (self.wins as f64 + self.draws as f64 / 2.0) / game_count as f64
+    }
+
+    /// Variance of the points of a single game
+    fn get_variance(&self) -> f64 {
+        let game_count = self.get_game_count();
+        if game_count == 0 {
+            return 0.0;
+        }
+
+        let score = self.get_score();
+        let total = self.wins as f64 * (1.0 - score).powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2)
+            + self.losses as f64 * score.powi(2);
+
+        total / game_count as f64
+    }
+
+    /// Elo difference the score corresponds to, infinite if one side scored every point
+    pub fn get_elo(&self) -> f64 {
+        score_to_elo(self.get_score())
+    }
+
+    /// Half of the 95% confidence interval of the Elo difference
+    pub fn get_elo_error(&self) -> f64 {
+        let game_count = self.get_game_count();
+        if game_count == 0 {
+            return f64::INFINITY;
+        }
+
+        let score = self.get_score();
+        let score_error = CONFIDENCE_QUANTILE * (self.get_variance() / game_count as f64).sqrt();
+
+        (score_to_elo(score + score_error) - score_to_elo(score - score_error)) / 2.0
+    }
+}
+
+pub enum SprtResult {
+    /// The first engine is better by at least *elo1*
+    H1Accepted,
+    /// The first engine isn't better by more than *elo0*
+    H0Accepted,
+    Continue,
+}
+
+/// Sequential probability ratio test of the hypotheses "the Elo difference is *elo0*" (H0)
+/// against "the Elo difference is *elo1*" (H1), which stops the match as soon as the games favor one of them enough
+#[derive(Clone, Copy, Debug)]
+pub struct Sprt {
+    elo0: f64,
+    elo1: f64,
+    /// Chance to accept H1 when H0 is true
+    alpha: f64,
+    /// Chance to accept H0 when H1 is true
+    beta: f64,
+}
+
+impl Sprt {
+    pub fn new(elo0: f64, elo1: f64, alpha: f64, beta: f64) -> Self {
+        Self { elo0, elo1, alpha, beta }
+    }
+
+    /// Log-likelihood ratio bounds, H0 is accepted below the first one and H1 above the second one
+    pub fn get_bounds(&self) -> (f64, f64) {
+        ((self.beta / (1.0 - self.alpha)).ln(), ((1.0 - self.beta) / self.alpha).ln())
+    }
+
+    /// Log-likelihood ratio of H1 over H0, approximated with the normal distribution of the game scores
+    pub fn get_llr(&self, stats: &MatchStats) -> f64 {
+        let variance = stats.get_variance();
+        if variance == 0.0 {
+            // no information yet, every game ended the same way
+            return 0.0;
+        }
+
+        let score0 = elo_to_score(self.elo0);
+        let score1 = elo_to_score(self.elo1);
+        let game_count = stats.get_game_count() as f64;
+
+        game_count * (score1 - score0) * (2.0 * stats.get_score() - score0 - score1) / (2.0 * variance)
+    }
+
+    pub fn get_result(&self, stats: &MatchStats) -> SprtResult {
+        let llr = self.get_llr(stats);
+        let (lower_bound, upper_bound) = self.get_bounds();
+
+        if llr >= upper_bound {
+            SprtResult::H1Accepted
+        } else if llr <= lower_bound {
+            SprtResult::H0Accepted
+        } else {
+            SprtResult::Continue
+        }
+    }
+}
+
+fn score_to_elo(score: f64) -> f64 {
+    let score = score.clamp(0.0, 1.0);
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+fn elo_to_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
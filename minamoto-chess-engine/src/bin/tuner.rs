@@ -0,0 +1,110 @@
+/*Tunes the weights of the handcrafted evaluation on positions labelled with the results of their games.
+
+Usage: minamoto-tune [--epochs <epochs>] [--learning-rate <rate>] [--format rust|json] [--output <file>] <positions>...
+
+Every line of the position files is a FEN followed by the result, either in square brackets ("[0.5]", "[1-0]"),
+as the EPD c9 opcode ("c9 \"1/2-1/2\";") or as the last word. The tuned weights are printed as Rust constants
+(or JSON) to the output file, or to stdout when there is none*/
+
+use std::{env, fs, process};
+
+use minamoto_chess::fen_api::{self, FenApi};
+use minamoto_chess_core::{board::Board, eval::tuning::{self, params::EvalParams, Tuner}};
+use minamoto_chess_engine::cli;
+
+// constants
+const DEFAULT_EPOCHS: u32 = 1000;
+const DEFAULT_LEARNING_RATE: f64 = 1.0;
+/// Every how many epochs the error is reported
+const REPORT_INTERVAL: u32 = 50;
+const USAGE: &str = "Usage: minamoto-tune [--epochs <epochs>] [--learning-rate <rate>] [--format rust|json] [--output <file>] <positions>...";
+
+enum OutputFormat {
+    Rust,
+    Json,
+}
+
+fn main() {
+    let mut epochs = DEFAULT_EPOCHS;
+    let mut learning_rate = DEFAULT_LEARNING_RATE;
+    let mut format = OutputFormat::Rust;
+    let mut output_path = None;
+    let mut position_paths = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--epochs" => epochs = cli::parse_value("--epochs", args.next(), USAGE),
+            "--learning-rate" => learning_rate = cli::parse_value("--learning-rate", args.next(), USAGE),
+            "--format" => format = match args.next().as_deref() {
+                Some("rust") => OutputFormat::Rust,
+                Some("json") => OutputFormat::Json,
+                _ => cli::exit_with_usage("Invalid --format value", USAGE),
+            },
+            "--output" => output_path = Some(args.next().unwrap_or_else(|| cli::exit_with_usage("Missing the output path", USAGE))),
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ => position_paths.push(arg),
+        }
+    }
+
+    if position_paths.is_empty() {
+        cli::exit_with_usage("Missing the position files", USAGE);
+    }
+
+    let mut tuner = Tuner::new(&EvalParams::current());
+    let mut skipped_positions = 0;
+
+    for path in &position_paths {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(error) => {
+                eprintln!("{}: {}", path, error);
+                continue;
+            }
+        };
+
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let Some((fen, result)) = tuning::parse_labelled_position(line).filter(|(fen, _)| fen_api::is_valid_fen(fen)) else {
+                skipped_positions += 1;
+                continue;
+            };
+
+            if !tuner.add_position(&Board::from_fen(fen), result) {
+                skipped_positions += 1;
+            }
+        }
+    }
+
+    if tuner.get_position_count() == 0 {
+        eprintln!("No positions to tune on");
+        process::exit(1);
+    }
+
+    // progress goes to stderr so the weights can be piped from stdout
+    eprintln!("Loaded {} positions ({} skipped)", tuner.get_position_count(), skipped_positions);
+    eprintln!("Scaling {:.4}, error {:.6}", tuner.fit_scaling(), tuner.get_error());
+
+    for epoch in 1..=epochs {
+        let error = tuner.run_epoch(learning_rate);
+        if epoch % REPORT_INTERVAL == 0 || epoch == epochs {
+            eprintln!("Epoch {}: error {:.6}", epoch, error);
+        }
+    }
+
+    let params = tuner.get_params();
+    let output = match format {
+        OutputFormat::Rust => params.to_rust_source(),
+        OutputFormat::Json => params.to_json(),
+    };
+
+    match output_path {
+        Some(path) => if let Err(error) = fs::write(&path, output) {
+            eprintln!("{}: {}", path, error);
+            process::exit(1);
+        },
+        None => print!("{}", output),
+    }
+}
@@ -0,0 +1,14 @@
+use std::{process, str::FromStr};
+
+/// Prints the error followed by the usage of the tool and exits with a failure code
+pub fn exit_with_usage(message: &str, usage: &str) -> ! {
+    eprintln!("{}", message);
+    eprintln!("{}", usage);
+    process::exit(1);
+}
+
+/// Parses the value following an argument, exits with the usage if it is missing or malformed
+pub fn parse_value<T: FromStr>(name: &str, value: Option<String>, usage: &str) -> T {
+    value.and_then(|value| value.parse().ok())
+        .unwrap_or_else(|| exit_with_usage(&format!("Invalid {} value", name), usage))
+}
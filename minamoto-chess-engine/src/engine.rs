@@ -0,0 +1,340 @@
+use std::{sync::{atomic::{AtomicBool, Ordering}, Arc}, thread::{self, JoinHandle}};
+
+use minamoto_chess::{board_representation, fen_api::{self, FenApi}, move_extensions::{self, MoveExtensions}, uci_move::{UciMove, UciMoveCreationResult}};
+use minamoto_chess_core::{board::Board, book::Book, eval::nnue::Network, r#move::Move, move_generation::{attack_calculator::AttackCalculator, move_gen}, search::{search_options::SearchOptions, search_result::SearchResult, searcher::{self, Searcher}, transposition_table}, syzygy::Tablebase};
+
+use crate::go_command;
+
+// constants
+const ENGINE_NAME: &str = "Minamoto";
+const ENGINE_AUTHOR: &str = "Rostislavs Berjoza";
+/// Check options toggling the techniques of the search, meant for testing
+const SEARCH_OPTION_NAMES: [&str; 5] = ["NullMovePruning", "LateMoveReductions", "AspirationWindows", "CheckExtensions", "FutilityPruning"];
+/// Separates the directories of the *SyzygyPath* option, same as in the PATH variable of the system
+const SYZYGY_PATH_SEPARATOR: char = if cfg!(windows) { ';' } else { ':' };
+
+/// Everything the search needs, moved into the search thread while it runs
+struct SearchState {
+    board: Board,
+    searcher: Searcher,
+}
+
+/// UCI engine state shared between the commands
+///
+/// The search runs on its own thread so the engine keeps reading commands (like *stop*) in the meantime
+pub struct Engine {
+    /// *None* while the search thread owns the state
+    state: Option<SearchState>,
+    search_thread: Option<JoinHandle<SearchState>>,
+    /// Shared with the searcher, raised by *stop* to interrupt the search
+    stop_flag: Arc<AtomicBool>,
+    /// Set by the *EvalFile* option, every new position is evaluated with it
+    network: Option<Arc<Network>>,
+    /// Set by the *BookFile* option, only played from if *OwnBook* is on
+    book: Option<Book>,
+    own_book: bool,
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let mut searcher = Searcher::new();
+        searcher.set_stop_flag(Arc::clone(&stop_flag));
+
+        Self {
+            state: Some(SearchState {
+                board: Board::from_fen(board_representation::DEFAULT_FEN),
+                searcher,
+            }),
+            search_thread: None,
+            stop_flag,
+            network: None,
+            book: None,
+            own_book: false,
+        }
+    }
+
+    pub fn print_id(&self) {
+        println!("id name {}", ENGINE_NAME);
+        println!("id author {}", ENGINE_AUTHOR);
+        println!(
+            "option name Hash type spin default {} min 1 max {}",
+            transposition_table::DEFAULT_SIZE_MB,
+            transposition_table::MAX_SIZE_MB
+        );
+        println!("option name Clear Hash type button");
+        println!("option name Threads type spin default 1 min 1 max {}", searcher::MAX_THREADS);
+        println!("option name MultiPV type spin default 1 min 1 max {}", move_gen::MAX_MOVES_PER_POS);
+        println!("option name EvalFile type string default <empty>");
+        println!("option name SyzygyPath type string default <empty>");
+        println!("option name OwnBook type check default false");
+        println!("option name BookFile type string default <empty>");
+        for name in SEARCH_OPTION_NAMES {
+            println!("option name {} type check default true", name);
+        }
+        println!("uciok");
+    }
+
+    pub fn new_game(&mut self) {
+        let mut board = Board::from_fen(board_representation::DEFAULT_FEN);
+        board.set_network(self.network.clone());
+
+        let state = self.get_idle_state();
+        state.board = board;
+        state.searcher.clear();
+    }
+
+    /// Handles *position [startpos | fen \<fen>] [moves \<move> ...]*
+    pub fn set_position(&mut self, args: &[&str]) {
+        let moves_index = args.iter().position(|&token| token == "moves").unwrap_or(args.len());
+        let (position_args, move_args) = args.split_at(moves_index);
+
+        let mut board = match position_args.split_first() {
+            Some((&"startpos", _)) => Board::from_fen(board_representation::DEFAULT_FEN),
+            Some((&"fen", fen_args)) if !fen_args.is_empty() => {
+                // a malformed FEN would bring the engine down, the previous position is kept instead
+                let fen = fen_args.join(" ");
+                if !fen_api::is_valid_fen(&fen) {
+                    println!("info string Invalid FEN");
+                    return;
+                }
+                Board::from_fen(&fen)
+            }
+            _ => {
+                println!("info string Invalid position command");
+                return;
+            }
+        };
+        board.set_network(self.network.clone());
+
+        for &uci_move_str in move_args.iter().skip(1) {
+            match get_legal_move(&board, uci_move_str) {
+                Some(mov) => {
+                    board.make_move(mov);
+                }
+                None => {
+                    println!("info string Illegal move: {}", uci_move_str);
+                    break;
+                }
+            }
+        }
+
+        self.get_idle_state().board = board;
+    }
+
+    pub fn go(&mut self, args: &[&str]) {
+        let mut state = self.take_idle_state();
+        let limits = go_command::parse_go_command(args);
+
+        // the analysis shouldn't stop at a book move
+        if self.own_book && !limits.infinite && let Some(book_move) = self.book.as_ref().and_then(|book| book.get_weighted_random_move(&state.board, &mut rand::rng())) {
+            println!("bestmove {}", move_extensions::move_to_string(&book_move));
+            self.state = Some(state);
+            return;
+        }
+
+        self.stop_flag.store(false, Ordering::Relaxed);
+        let stop_flag = Arc::clone(&self.stop_flag);
+
+        self.search_thread = Some(thread::spawn(move || {
+            let SearchState { board, searcher } = &mut state;
+
+            let result = searcher.search_with_limits(board, &limits, print_info);
+
+            // The infinite search may end by itself (on a mate, a tablebase root or the max depth),
+            // but UCI only allows the best move after *stop*. *stop* unparks the thread
+            if limits.infinite {
+                while !stop_flag.load(Ordering::Relaxed) {
+                    thread::park();
+                }
+            }
+
+            match result.best_move {
+                Some(best_move) => println!("bestmove {}", move_extensions::move_to_string(&best_move)),
+                None => println!("bestmove 0000"),
+            }
+
+            state
+        }));
+    }
+
+    /// Stops the running search (if there is one) and waits for it to print the best move
+    pub fn stop(&mut self) {
+        self.stop_flag.store(true, Ordering::Relaxed);
+        if let Some(search_thread) = &self.search_thread {
+            search_thread.thread().unpark();
+        }
+        self.wait_for_search();
+    }
+
+    /// Handles *setoption name \<name> [value \<value>]*
+    pub fn set_option(&mut self, args: &[&str]) {
+        let value_index = args.iter().position(|&token| token == "value").unwrap_or(args.len());
+        let name = args[..value_index].iter()
+            .skip_while(|&&token| token == "name")
+            .copied()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let value = args.get(value_index + 1..).map(|value_args| value_args.join(" "));
+
+        match (name.to_lowercase().as_str(), value) {
+            ("hash", Some(value)) => match value.parse::<usize>() {
+                Ok(size_mb) => self.get_idle_state().searcher.resize_transposition_table(size_mb),
+                Err(_) => println!("info string Invalid Hash value: {}", value),
+            },
+            ("clear hash", _) => self.get_idle_state().searcher.clear(),
+            ("threads", Some(value)) => match value.parse::<usize>() {
+                Ok(thread_count) => self.get_idle_state().searcher.set_thread_count(thread_count),
+                Err(_) => println!("info string Invalid Threads value: {}", value),
+            },
+            ("multipv", Some(value)) => match value.parse::<usize>() {
+                Ok(multi_pv) => self.get_idle_state().searcher.set_multi_pv(multi_pv),
+                Err(_) => println!("info string Invalid MultiPV value: {}", value),
+            },
+            ("evalfile", Some(value)) => self.set_eval_file(&value),
+            ("syzygypath", Some(value)) => self.set_syzygy_path(&value),
+            ("ownbook", Some(value)) => match value.to_lowercase().parse::<bool>() {
+                Ok(own_book) => self.own_book = own_book,
+                Err(_) => println!("info string Invalid OwnBook value: {}", value),
+            },
+            ("bookfile", Some(value)) => self.set_book_file(&value),
+            ("nullmovepruning", Some(value)) => self.set_search_option(&name, &value, |options, enabled| options.null_move_pruning = enabled),
+            ("latemovereductions", Some(value)) => self.set_search_option(&name, &value, |options, enabled| options.late_move_reductions = enabled),
+            ("aspirationwindows", Some(value)) => self.set_search_option(&name, &value, |options, enabled| options.aspiration_windows = enabled),
+            ("checkextensions", Some(value)) => self.set_search_option(&name, &value, |options, enabled| options.check_extensions = enabled),
+            ("futilitypruning", Some(value)) => self.set_search_option(&name, &value, |options, enabled| options.futility_pruning = enabled),
+            _ => println!("info string Unknown option: {}", name),
+        }
+    }
+
+    /// Loads the network from the provided file, *<empty>* goes back to the handcrafted evaluation
+    fn set_eval_file(&mut self, path: &str) {
+        if path.is_empty() || path == "<empty>" {
+            self.network = None;
+        } else {
+            match Network::from_file(path) {
+                Ok(network) => self.network = Some(Arc::new(network)),
+                Err(error) => {
+                    println!("info string {}", error);
+                    return;
+                }
+            }
+        }
+
+        let network = self.network.clone();
+        self.get_idle_state().board.set_network(network);
+    }
+
+    /// Registers the tables of every directory of the path, *<empty>* disables the tablebases
+    fn set_syzygy_path(&mut self, path: &str) {
+        let mut tablebase = Tablebase::new();
+
+        if !path.is_empty() && path != "<empty>" {
+            for directory in path.split(SYZYGY_PATH_SEPARATOR).filter(|directory| !directory.is_empty()) {
+                if let Err(error) = tablebase.add_directory(directory) {
+                    println!("info string {}: {}", directory, error);
+                }
+            }
+
+            println!("info string Found tablebases for up to {} pieces", tablebase.get_max_pieces());
+        }
+
+        let tablebase = (!tablebase.is_empty()).then(|| Arc::new(tablebase));
+        self.get_idle_state().searcher.set_tablebase(tablebase);
+    }
+
+    /// Loads the Polyglot book from the provided file, *<empty>* unloads it
+    fn set_book_file(&mut self, path: &str) {
+        if path.is_empty() || path == "<empty>" {
+            self.book = None;
+            return;
+        }
+
+        match Book::from_file(path) {
+            Ok(book) => self.book = Some(book),
+            Err(error) => println!("info string {}", error),
+        }
+    }
+
+    /// Parses the value of a check option and applies it to the search options
+    fn set_search_option(&mut self, name: &str, value: &str, apply: impl FnOnce(&mut SearchOptions, bool)) {
+        let Ok(enabled) = value.to_lowercase().parse::<bool>() else {
+            println!("info string Invalid {} value: {}", name, value);
+            return;
+        };
+
+        let searcher = &mut self.get_idle_state().searcher;
+        let mut options = searcher.get_options();
+        apply(&mut options, enabled);
+        searcher.set_options(options);
+    }
+
+    /// Waits for the running search to finish. The search must be stopped first unless it is limited
+    fn wait_for_search(&mut self) {
+        if let Some(search_thread) = self.search_thread.take() {
+            self.state = Some(search_thread.join().expect("Search thread panicked"));
+        }
+    }
+
+    /// Stops the running search and returns the state it was using.
+    /// Commands changing the state while searching would otherwise be lost
+    fn get_idle_state(&mut self) -> &mut SearchState {
+        self.stop();
+        self.state.as_mut().expect("Engine state is missing")
+    }
+
+    fn take_idle_state(&mut self) -> SearchState {
+        self.stop();
+        self.state.take().expect("Engine state is missing")
+    }
+}
+
+/// Parses the UCI move and returns it only if it is legal in the provided position
+fn get_legal_move(board: &Board, uci_move_str: &str) -> Option<Move> {
+    let UciMoveCreationResult::Success(uci_move) = UciMove::from_str(uci_move_str) else {
+        return None;
+    };
+
+    let attack_calc = AttackCalculator::new(board);
+    let mut legal_moves = move_gen::create_empty_move_buffer();
+    move_gen::generate_moves(&mut legal_moves, board, &attack_calc);
+
+    let mov = Move::from_uci(uci_move, board);
+
+    legal_moves.into_iter().find(|legal_move| {
+        legal_move.start_square == mov.start_square
+            && legal_move.target_square == mov.target_square
+            && legal_move.move_type == mov.move_type
+    })
+}
+
+/// Prints an *info* line for every MultiPV line of the completed iteration
+fn print_info(result: &SearchResult) {
+    let nps = result.nodes * 1000 / result.time_ms.max(1);
+
+    if result.lines.is_empty() {
+        // no legal moves in the root position
+        println!("info depth {} seldepth {} score {} nodes {} nps {} time {}", result.depth, result.seldepth, format_score(result.score), result.nodes, nps, result.time_ms);
+        return;
+    }
+
+    for (index, line) in result.lines.iter().enumerate() {
+        let pv = line.pv.iter()
+            .map(move_extensions::move_to_string)
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        println!(
+            "info depth {} seldepth {} multipv {} score {} nodes {} nps {} time {} hashfull {} pv {}",
+            line.depth, result.seldepth, index + 1, format_score(line.score), result.nodes, nps, result.time_ms, result.hashfull, pv
+        );
+    }
+}
+
+fn format_score(score: i32) -> String {
+    if searcher::is_mate_score(score) {
+        format!("mate {}", searcher::get_mate_distance(score))
+    } else {
+        format!("cp {}", score)
+    }
+}
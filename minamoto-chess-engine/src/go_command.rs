@@ -0,0 +1,26 @@
+use minamoto_chess_core::{piece, search::search_limits::SearchLimits};
+
+/// Parses the arguments following the UCI *go* command. Unknown and malformed parameters are skipped
+pub fn parse_go_command(args: &[&str]) -> SearchLimits {
+    let mut limits = SearchLimits::default();
+    let mut tokens = args.iter();
+
+    while let Some(&token) = tokens.next() {
+        let mut next_number = || tokens.next().and_then(|value| value.parse::<u64>().ok());
+
+        match token {
+            "depth" => limits.depth = next_number().map(|depth| depth.min(u8::MAX as u64) as u8),
+            "nodes" => limits.nodes = next_number(),
+            "movetime" => limits.movetime = next_number(),
+            "wtime" => limits.time[piece::WHITE] = next_number(),
+            "btime" => limits.time[piece::BLACK] = next_number(),
+            "winc" => limits.increment[piece::WHITE] = next_number(),
+            "binc" => limits.increment[piece::BLACK] = next_number(),
+            "movestogo" => limits.movestogo = next_number(),
+            "infinite" => limits.infinite = true,
+            _ => (),
+        }
+    }
+
+    limits
+}
@@ -0,0 +1,3 @@
+/*Code shared by the command line tools of the engine*/
+
+pub mod cli;
@@ -0,0 +1,35 @@
+mod engine;
+mod go_command;
+
+use std::io::{self, BufRead};
+
+use engine::Engine;
+
+fn main() {
+    let mut engine = Engine::new();
+
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let Some((&command, args)) = tokens.split_first() else {
+            continue;
+        };
+
+        match command {
+            "uci" => engine.print_id(),
+            "isready" => println!("readyok"),
+            "ucinewgame" => engine.new_game(),
+            "position" => engine.set_position(args),
+            "go" => engine.go(args),
+            "stop" => engine.stop(),
+            "setoption" => engine.set_option(args),
+            "quit" => break,
+            _ => println!("info string Unknown command: {}", command),
+        }
+    }
+
+    engine.stop();
+}
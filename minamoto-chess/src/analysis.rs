@@ -0,0 +1,32 @@
+use minamoto_chess_core::{piece, search::{search_result::PvLine, searcher}};
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+
+use crate::uci_move::UciMove;
+
+/// One of the best lines found by the analysis
+#[derive(Tsify, Serialize, Deserialize)]
+#[tsify(into_wasm_abi)]
+pub struct AnalysisLine {
+    /// Score in centipawns, positive if white is better and negative if black is better
+    pub score: i32,
+    /// Full moves until mate, positive if white mates and negative if black mates. *None* if there is no forced mate
+    pub mate: Option<i32>,
+    pub depth: u8,
+    /// Moves of the line starting from the analyzed position
+    pub pv: Vec<UciMove>,
+}
+
+impl AnalysisLine {
+    /// Converts the search line into the line seen from white's perspective
+    pub fn from_pv_line(line: &PvLine, color_to_move: usize) -> Self {
+        let sign = if color_to_move == piece::WHITE { 1 } else { -1 };
+
+        Self {
+            score: line.score * sign,
+            mate: searcher::is_mate_score(line.score).then(|| searcher::get_mate_distance(line.score) * sign),
+            depth: line.depth,
+            pv: line.pv.iter().copied().map(UciMove::from_move).collect(),
+        }
+    }
+}
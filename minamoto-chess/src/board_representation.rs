@@ -87,6 +87,10 @@ pub fn get_square_from_name(square_name: &str) -> usize {
         }
     }
 }
+/// Same as *get_square_from_name*, but returns *None* instead of panicking on a wrong name
+pub fn find_square_from_name(square_name: &str) -> Option<usize> {
+    NAME_TO_SQUARE.get(square_name).copied()
+}
 
 const PIECE_NAMES: [[char; 6]; 2] = [
     [ 'K', 'P', 'N', 'B', 'R', 'Q' ],
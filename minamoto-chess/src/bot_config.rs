@@ -0,0 +1,49 @@
+use minamoto_chess_core::search::{search_limits::SearchLimits, skill::SkillLevel};
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+
+// constants
+/// Thinking time of the bot when the config doesn't set one
+pub const DEFAULT_BOT_MOVETIME_MS: u32 = 1000;
+
+/// How the bot plays in the games against a player
+///
+/// Every field can be left out, the default bot plays at full strength from the opening book
+#[derive(Tsify, Serialize, Deserialize, Clone, Debug)]
+#[tsify(into_wasm_abi, from_wasm_abi)]
+#[serde(default)]
+pub struct BotConfig {
+    /// From 0 (weakest) to 20 (full strength). Ignored when *elo* is set
+    pub skill_level: Option<u8>,
+    /// Approximate strength from 800 to 2800, converted to the closest skill level
+    pub elo: Option<u32>,
+    /// Thinking time for every move in milliseconds, the weak levels usually stop much earlier
+    pub movetime_ms: Option<u32>,
+    /// Play the moves of the opening book (if one is loaded) while the position is in it
+    pub use_book: bool,
+}
+
+impl BotConfig {
+    pub fn get_skill_level(&self) -> SkillLevel {
+        match (self.elo, self.skill_level) {
+            (Some(elo), _) => SkillLevel::from_elo(elo),
+            (None, Some(level)) => SkillLevel::new(level),
+            (None, None) => SkillLevel::max(),
+        }
+    }
+
+    pub fn get_search_limits(&self) -> SearchLimits {
+        SearchLimits::from_movetime(self.movetime_ms.unwrap_or(DEFAULT_BOT_MOVETIME_MS) as u64)
+    }
+}
+
+impl Default for BotConfig {
+    fn default() -> Self {
+        Self {
+            skill_level: None,
+            elo: None,
+            movetime_ms: None,
+            use_book: true,
+        }
+    }
+}
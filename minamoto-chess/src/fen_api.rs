@@ -38,7 +38,7 @@ impl FenApi for Board {
         // Set en passant state if available
         if fen_data.len() > 3 && fen_data[3] != "-" {
             let square = board_representation::get_square_from_name(fen_data[3]);
-            let pawn_offset = if board.get_current_color() == WHITE { 8 } else { -8i32 };
+            let pawn_offset = if board.get_current_color() == WHITE { -8 } else { 8i32 };
             let capture_square = square;
             let pawn_square = (square as i32 + pawn_offset) as usize;
             board.update_en_passant_state_public(true, pawn_square, capture_square);
@@ -146,4 +146,94 @@ impl FenApi for Board {
         fen_string.push_str(&self.get_move_counter().to_string());
         fen_string
     }
-}
\ No newline at end of file
+}
+
+/// Returns true if *Board::from_fen* can load the provided FEN string
+///
+/// Checks the piece placement, the side to move and the en passant square, the rest of the fields fall back to the defaults anyway
+pub fn is_valid_fen(fen_string: &str) -> bool {
+    let fen_data: Vec<&str> = fen_string.split_whitespace().collect();
+    let Some(placement) = fen_data.first() else {
+        return false;
+    };
+
+    let ranks: Vec<&str> = placement.split('/').collect();
+    let is_placement_valid = ranks.len() == 8 && ranks.iter().all(|rank| {
+        let mut squares = 0;
+        for symbol in rank.chars() {
+            match symbol {
+                '1'..='8' => squares += symbol as u32 - '0' as u32,
+                'p' | 'n' | 'b' | 'r' | 'q' | 'k' | 'P' | 'N' | 'B' | 'R' | 'Q' | 'K' => squares += 1,
+                _ => return false,
+            }
+        }
+        squares == 8
+    });
+
+    is_placement_valid
+        && placement.matches('K').count() == 1
+        && placement.matches('k').count() == 1
+        && fen_data.get(1).is_none_or(|color| *color == "w" || *color == "b")
+        && fen_data.get(3).is_none_or(|square| *square == "-" || board_representation::find_square_from_name(square).is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use minamoto_chess_core::{board::Board, move_generation::{attack_calculator::AttackCalculator, move_gen}};
+
+    use super::{is_valid_fen, FenApi};
+    use crate::board_representation;
+
+    /// Drops the move counters, only the position is checked after a move
+    fn get_position_fields(fen: &str) -> String {
+        fen.split_whitespace().take(5).collect::<Vec<_>>().join(" ")
+    }
+
+    /// Loads the FEN, checks it is written back unchanged, then takes en passant and checks the resulting position
+    fn assert_en_passant(fen: &str, start_square: &str, target_square: &str, expected_fen: &str) {
+        let mut board = Board::from_fen(fen);
+        assert_eq!(board.to_fen(), fen);
+
+        let mut moves = move_gen::create_empty_move_buffer();
+        move_gen::generate_moves(&mut moves, &board, &AttackCalculator::new(&board));
+        let start_square = board_representation::get_square_from_name(start_square);
+        let target_square = board_representation::get_square_from_name(target_square);
+        let en_passant = moves.into_iter()
+            .find(|mov| mov.start_square == start_square && mov.target_square == target_square)
+            .expect("En passant is not generated");
+
+        board.make_move(en_passant);
+        assert_eq!(get_position_fields(&board.to_fen()), get_position_fields(expected_fen));
+    }
+
+    #[test]
+    fn en_passant_round_trip_white_to_move() {
+        assert_en_passant(
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "e5", "f6",
+            "rnbqkbnr/ppp1p1pp/5P2/3p4/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 3",
+        );
+    }
+
+    #[test]
+    fn en_passant_round_trip_black_to_move() {
+        assert_en_passant(
+            "rnbqkbnr/pppp1ppp/8/8/3Pp3/8/PPP1PPPP/RNBQKBNR b KQkq d3 0 2",
+            "e4", "d3",
+            "rnbqkbnr/pppp1ppp/8/8/8/3p4/PPP1PPPP/RNBQKBNR w KQkq - 0 3",
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_fens() {
+        assert!(is_valid_fen(board_representation::DEFAULT_FEN));
+        assert!(is_valid_fen("4k3/8/8/8/8/8/8/4K3"));
+        assert!(!is_valid_fen(""));
+        assert!(!is_valid_fen("garbage"));
+        assert!(!is_valid_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1"));
+        assert!(!is_valid_fen("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"));
+        assert!(!is_valid_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQ1BNR w kq - 0 1"));
+        assert!(!is_valid_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1"));
+        assert!(!is_valid_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e9 0 1"));
+    }
+}
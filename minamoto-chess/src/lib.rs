@@ -1,7 +1,10 @@
-use minamoto_chess_core::{board::Board, r#move::Move, move_generation::{attack_calculator::AttackCalculator, move_gen::{self}}};
+use std::sync::Arc;
+
+use js_sys::Function;
+use minamoto_chess_core::{board::Board, book::Book, eval::{self, nnue::Network}, r#move::Move, move_generation::{attack_calculator::AttackCalculator, move_gen::{self}}, search::{mate_solver::MateSolver, search_limits::SearchLimits, search_result::SearchResult, searcher::Searcher, see, stepped_search::SteppedSearch}, syzygy::Tablebase};
 use wasm_bindgen::prelude::*;
 
-use crate::{attack_info::AttackInfo, board_extensions::BoardExtensions, fen_api::FenApi, game::GameState, move_extensions::MoveExtensions, piece_dto::{Piece, PieceColor, PiecePlacement, PieceType}, uci_move::{PromotionType, UciMove, UciMoveCreationResult}};
+use crate::{analysis::AnalysisLine, attack_info::AttackInfo, bot_config::BotConfig, mate_search_result::MateSearchResult, board_extensions::BoardExtensions, fen_api::FenApi, game::GameState, pgn::{san, PgnError, PgnErrorReason, PgnGame}, move_extensions::MoveExtensions, piece_dto::{Piece, PieceColor, PiecePlacement, PieceType}, search_progress::SearchProgress, search_task::SearchTask, tablebase_result::TablebaseResult, uci_move::{PromotionType, UciMove, UciMoveCreationResult}};
 
 pub mod fen_api;
 pub mod perft;
@@ -16,6 +19,14 @@ pub mod attack_info;
 pub mod game;
 pub mod move_gen_extensions;
 pub mod piece_dto;
+pub mod analysis;
+pub mod tablebase_result;
+pub mod pgn;
+pub mod bot_config;
+pub mod mate_search_result;
+pub mod puzzle;
+pub mod search_progress;
+pub mod search_task;
 
 #[wasm_bindgen]
 pub struct Game {
@@ -23,6 +34,13 @@ pub struct Game {
     material: i8,
     graveyard: [Vec<Piece>; 2],
     history: Vec<String>,
+    /// Moves played since the starting position, *history* holds the positions after them
+    moves: Vec<Move>,
+    /// Tags written by *toPgn* (except *SetUp*, *FEN* and the result of a finished game, which come from the game itself)
+    pgn_tags: Vec<(String, String)>,
+    searcher: Searcher,
+    book: Option<Book>,
+    bot_config: BotConfig,
 }
 
 #[wasm_bindgen]
@@ -36,8 +54,126 @@ impl Game {
             board, 
             material, 
             graveyard: [Vec::new(), Vec::new()], 
-            history: vec![fen.to_string()] 
+            history: vec![fen.to_string()],
+            moves: Vec::new(),
+            pgn_tags: Vec::new(),
+            searcher: Searcher::new(),
+            book: None,
+            bot_config: BotConfig::default(),
+        }
+    }
+
+    /// Loads a game from PGN text, *gameIndex* picks the game when the text holds several (the first one by default).
+    /// The mainline is played from the position of the *FEN* tag (or the regular starting position),
+    /// so the history and the graveyard are the same as if the moves were made one by one.
+    /// The tags are kept for *toPgn*, the result ending the movetext is the *Result* tag when the game has none
+    ///
+    /// Fails with the ply and the reason if a move is illegal, ambiguous or not SAN at all
+    #[wasm_bindgen(js_name = fromPgn)]
+    pub fn from_pgn(pgn: &str, game_index: Option<usize>) -> Result<Game, String> {
+        let game_index = game_index.unwrap_or(0);
+        let pgn_games = pgn::parse_games(pgn);
+        let Some(pgn_game) = pgn_games.get(game_index) else {
+            return Err(format!("No game {} in the PGN, it has {}", game_index, pgn_games.len()));
+        };
+
+        let start_fen = pgn_game.get_start_fen();
+        if !fen_api::is_valid_fen(start_fen) {
+            return Err(format!("Invalid FEN tag: {}", start_fen));
+        }
+
+        let mut game = Game::from_fen(start_fen);
+        game.pgn_tags = pgn_game.tags.iter()
+            .filter(|(name, _)| name != "SetUp" && name != "FEN")
+            .cloned()
+            .collect();
+        if pgn_game.get_tag("Result").is_none() {
+            game.pgn_tags.push(("Result".to_string(), pgn_game.result.clone()));
+        }
+
+        for (index, san) in pgn_game.moves.iter().enumerate() {
+            let mov = san::move_from_san(&game.board, san)
+                .map_err(|reason| PgnError { ply: index + 1, san: san.clone(), reason: PgnErrorReason::Move(reason) }.to_string())?;
+            game._make_move(UciMove::from_move(mov))?;
+        }
+
+        Ok(game)
+    }
+
+    /// Returns how many games the PGN text holds, the valid *gameIndex* values of *fromPgn* are below it
+    #[wasm_bindgen(js_name = countPgnGames)]
+    pub fn count_pgn_games(pgn: &str) -> usize {
+        pgn::parse_games(pgn).len()
+    }
+
+    /// Writes the game as PGN: the Seven Tag Roster (*?* for the tags that weren't set) and the other tags,
+    /// *SetUp* and *FEN* if the game didn't start from the regular starting position, then the moves in SAN.
+    /// The result is the one of *getCurrentGameState* once the game is over, otherwise the *Result* tag (*\** by default)
+    #[wasm_bindgen(js_name = toPgn)]
+    pub fn to_pgn(&self) -> String {
+        let result = match self.get_current_game_state() {
+            GameState::WhiteWon => "1-0".to_string(),
+            GameState::BlackWon => "0-1".to_string(),
+            GameState::Draw => "1/2-1/2".to_string(),
+            GameState::WhiteToMove | GameState::BlackToMove => self.get_pgn_tag("Result").unwrap_or_else(|| "*".to_string()),
+        };
+
+        let mut tags: Vec<(String, String)> = pgn::SEVEN_TAG_ROSTER.iter()
+            .map(|&(name, default_value)| {
+                let value = match name {
+                    "Result" => result.clone(),
+                    _ => self.get_pgn_tag(name).unwrap_or_else(|| default_value.to_string()),
+                };
+                (name.to_string(), value)
+            })
+            .collect();
+        tags.extend(self.pgn_tags.iter()
+            .filter(|(name, _)| !pgn::SEVEN_TAG_ROSTER.iter().any(|(roster_name, _)| roster_name == name))
+            .cloned());
+
+        let mut board = Board::from_fen(&self.history[0]);
+        let start_fen = board.to_fen();
+        if start_fen != board_representation::DEFAULT_FEN {
+            tags.push(("SetUp".to_string(), "1".to_string()));
+            tags.push(("FEN".to_string(), start_fen));
         }
+
+        let moves = self.moves.iter()
+            .map(|&mov| {
+                let san = san::move_to_san(&board, mov);
+                board.make_move(mov);
+                san
+            })
+            .collect();
+
+        PgnGame { tags, moves, result }.to_pgn()
+    }
+
+    /// Sets a tag written by *toPgn*, e.g. *White* or *Event*. Names are letters, digits and underscores.
+    /// *SetUp* and *FEN* can't be set, they come from the starting position
+    #[wasm_bindgen(js_name = setPgnTag)]
+    pub fn set_pgn_tag(&mut self, name: &str, value: &str) -> Result<(), String> {
+        if name.is_empty() || !name.chars().all(|symbol| symbol.is_ascii_alphanumeric() || symbol == '_') {
+            return Err(format!("Invalid PGN tag name: {}", name));
+        }
+        if name == "SetUp" || name == "FEN" {
+            return Err(format!("The {} tag comes from the starting position", name));
+        }
+
+        match self.pgn_tags.iter_mut().find(|(tag_name, _)| tag_name == name) {
+            Some((_, tag_value)) => *tag_value = value.to_string(),
+            None => self.pgn_tags.push((name.to_string(), value.to_string())),
+        }
+
+        Ok(())
+    }
+
+    /// Returns the value set by *setPgnTag* or read by *fromPgn*, *undefined* if the tag isn't set
+    #[wasm_bindgen(js_name = getPgnTag)]
+    pub fn get_pgn_tag(&self, name: &str) -> Option<String> {
+        self.pgn_tags.iter()
+            .find(|(tag_name, _)| tag_name == name)
+            .map(|(_, value)| value.clone())
     }
 
     #[wasm_bindgen(js_name = toFen)]
@@ -146,11 +282,254 @@ impl Game {
         }
     }
 
+    /// Searches the current position up to the provided depth and returns the move the engine would play.
+    /// *onProgress* is called with a *SearchProgress* after every completed iteration
+    /// 
+    /// Returns *undefined* if there are no legal moves
+    #[wasm_bindgen(js_name = getBestMove)]
+    pub fn get_best_move(&mut self, depth: u8, on_progress: Option<Function>) -> Option<UciMove> {
+        let search_result = self._search_with_progress(&SearchLimits::from_depth(depth), on_progress);
+
+        search_result.best_move.map(UciMove::from_move)
+    }
+
+    /// Searches the current position for about the provided amount of milliseconds
+    /// and returns the move the engine would play. *onProgress* is called with a *SearchProgress* after every completed iteration
+    /// 
+    /// Returns *undefined* if there are no legal moves
+    #[wasm_bindgen(js_name = getBestMoveInTime)]
+    pub fn get_best_move_in_time(&mut self, movetime_ms: u32, on_progress: Option<Function>) -> Option<UciMove> {
+        let limits = SearchLimits::from_movetime(movetime_ms as u64);
+        let search_result = self._search_with_progress(&limits, on_progress);
+
+        search_result.best_move.map(UciMove::from_move)
+    }
+
+    /// Sets how the bot plays from now on, see *getBotMove*
+    #[wasm_bindgen(js_name = setBotConfig)]
+    pub fn set_bot_config(&mut self, config: BotConfig) {
+        self.bot_config = config;
+    }
+
+    #[wasm_bindgen(js_name = getBotConfig)]
+    pub fn get_bot_config(&self) -> BotConfig {
+        self.bot_config.clone()
+    }
+
+    /// Returns the move the bot plays in the current position with its current config:
+    /// a book move while the position is in the book, otherwise a move found by a search
+    /// limited to the skill level of the bot
+    /// 
+    /// Returns *undefined* if there are no legal moves
+    #[wasm_bindgen(js_name = getBotMove)]
+    pub fn get_bot_move(&mut self) -> Option<UciMove> {
+        let mut rng = rand::rng();
+
+        if self.bot_config.use_book
+            && let Some(book_move) = self.book.as_ref().and_then(|book| book.get_weighted_random_move(&self.board, &mut rng))
+        {
+            return Some(UciMove::from_move(book_move));
+        }
+
+        let skill = self.bot_config.get_skill_level();
+        let limits = self.bot_config.get_search_limits();
+
+        self.searcher.search_with_skill(&mut self.board, skill, &limits, &mut rng).map(UciMove::from_move)
+    }
+
+    /// Searches the current position up to the provided depth and returns
+    /// the provided amount of best lines ranked from the best to the worst.
+    /// *onProgress* is called with a *SearchProgress* after every completed iteration
+    /// 
+    /// Returns an empty array if there are no legal moves
+    #[wasm_bindgen(js_name = analyze)]
+    pub fn analyze(&mut self, depth: u8, multi_pv: usize, on_progress: Option<Function>) -> Vec<AnalysisLine> {
+        let previous_multi_pv = self.searcher.get_multi_pv();
+        self.searcher.set_multi_pv(multi_pv);
+
+        let search_result = self._search_with_progress(&SearchLimits::from_depth(depth), on_progress);
+        self.searcher.set_multi_pv(previous_multi_pv);
+
+        let color_to_move = self.board.get_current_color();
+        search_result.lines.iter()
+            .map(|line| AnalysisLine::from_pv_line(line, color_to_move))
+            .collect()
+    }
+
+    /// Starts a search of the current position which runs only when *SearchTask.step* is called,
+    /// so the page doesn't freeze during long searches. Without a depth or time limit it runs until cancelled
+    ///
+    /// The task shares the transposition table with the game, its moves are never played on the game
+    #[wasm_bindgen(js_name = startSearch)]
+    pub fn start_search(&self, depth: Option<u8>, movetime_ms: Option<u32>, multi_pv: Option<usize>) -> SearchTask {
+        let limits = SearchLimits {
+            depth,
+            movetime: movetime_ms.map(u64::from),
+            ..SearchLimits::default()
+        };
+
+        let mut searcher = self.searcher.fork();
+        if let Some(multi_pv) = multi_pv {
+            searcher.set_multi_pv(multi_pv);
+        }
+
+        SearchTask::new(SteppedSearch::new(searcher, self.board.clone(), &limits))
+    }
+
+    /// Looks for the shortest forced mate of the side to move within the provided amount of its moves.
+    /// With *checksOnly* only the checking moves are tried for the side to move, which is much faster
+    /// but misses the mates starting with a quiet move
+    /// 
+    /// The search gives up after *maxNodes* nodes if provided, the result is marked as aborted then
+    #[wasm_bindgen(js_name = findMate)]
+    pub fn find_mate(&mut self, max_moves: u8, checks_only: bool, max_nodes: Option<u32>) -> MateSearchResult {
+        let mut solver = MateSolver::new();
+        solver.set_checks_only(checks_only);
+        solver.set_node_limit(max_nodes.map(u64::from));
+
+        MateSearchResult::from_mate_result(solver.solve(&mut self.board, max_moves))
+    }
+
+    /// Resizes the engine's transposition table to take at most the provided amount of megabytes.
+    /// A running *SearchTask* keeps the old table
+    #[wasm_bindgen(js_name = setHashSize)]
+    pub fn set_hash_size(&mut self, size_mb: usize) {
+        self.searcher.resize_transposition_table(size_mb);
+    }
+
+    /// Makes the engine forget everything it has searched so far
+    #[wasm_bindgen(js_name = clearHash)]
+    pub fn clear_hash(&mut self) {
+        self.searcher.get_transposition_table().clear();
+    }
+
     #[wasm_bindgen(js_name = getMaterialBalance)]
     pub fn get_material_balance(&self) -> i8 {
         self.material
     }
 
+    /// Returns the static evaluation of the current position in centipawns
+    /// 
+    /// Same as *getMaterialBalance*, positive if white is better and negative if black is better
+    #[wasm_bindgen(js_name = getEvaluation)]
+    pub fn get_evaluation(&self) -> i32 {
+        let score = eval::evaluate(&self.board);
+
+        if self.board.is_white_to_move() {
+            score
+        } else {
+            -score
+        }
+    }
+
+    /// Loads the NNUE from the bytes of a network file, the engine and *getEvaluation* use it from now on
+    #[wasm_bindgen(js_name = loadNetwork)]
+    pub fn load_network(&mut self, weights: &[u8]) -> Result<(), String> {
+        let network = Network::from_bytes(weights).map_err(|error| error.to_string())?;
+        self.board.set_network(Some(Arc::new(network)));
+
+        Ok(())
+    }
+
+    /// Goes back to the handcrafted evaluation
+    #[wasm_bindgen(js_name = unloadNetwork)]
+    pub fn unload_network(&mut self) {
+        self.board.set_network(None);
+    }
+
+    #[wasm_bindgen(js_name = isNetworkLoaded)]
+    pub fn is_network_loaded(&self) -> bool {
+        self.board.get_accumulator().is_some()
+    }
+
+    /// Adds a Syzygy table from the bytes of its file, the name of the file (e.g. *KRvK.rtbw*) tells which table it is.
+    /// The engine probes the tables from now on. Fails while a *SearchTask* of the game is alive, since it probes the same tables
+    #[wasm_bindgen(js_name = addTablebaseFile)]
+    pub fn add_tablebase_file(&mut self, file_name: &str, data: Vec<u8>) -> Result<(), String> {
+        if self.searcher.get_tablebase().is_none() {
+            self.searcher.set_tablebase(Some(Arc::new(Tablebase::new())));
+        }
+
+        let Some(tablebase) = self.searcher.get_tablebase_mut() else {
+            return Err("A search task is running, free it before adding tables".to_string());
+        };
+        tablebase.add_file(file_name, data).map_err(|error| error.to_string())
+    }
+
+    /// Removes every table added by *addTablebaseFile*
+    #[wasm_bindgen(js_name = clearTablebase)]
+    pub fn clear_tablebase(&mut self) {
+        self.searcher.set_tablebase(None);
+    }
+
+    /// Returns the amount of pieces (kings included) of the biggest WDL table added, 0 if there are none
+    #[wasm_bindgen(js_name = getTablebaseMaxPieces)]
+    pub fn get_tablebase_max_pieces(&self) -> usize {
+        self.searcher.get_tablebase().map_or(0, |tablebase| tablebase.get_max_pieces())
+    }
+
+    /// Probes the current position in the tablebases, taking the 50 moves counter into account
+    ///
+    /// Returns *undefined* if the position isn't in the added tables
+    #[wasm_bindgen(js_name = probeTablebase)]
+    pub fn probe_tablebase(&mut self) -> Option<TablebaseResult> {
+        let probe = self.searcher.get_tablebase()?.probe(&mut self.board)?;
+
+        Some(TablebaseResult::from_probe(&probe, self.board.get_current_color()))
+    }
+
+    /// Loads a Polyglot opening book from the bytes of its file, replacing the previous one
+    #[wasm_bindgen(js_name = loadBook)]
+    pub fn load_book(&mut self, data: &[u8]) -> Result<(), String> {
+        self.book = Some(Book::from_bytes(data).map_err(|error| error.to_string())?);
+
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = unloadBook)]
+    pub fn unload_book(&mut self) {
+        self.book = None;
+    }
+
+    #[wasm_bindgen(js_name = isBookLoaded)]
+    pub fn is_book_loaded(&self) -> bool {
+        self.book.is_some()
+    }
+
+    /// Returns a move of the opening book for the current position, either picked at random
+    /// (the moves with bigger weights more often) or the one with the biggest weight
+    /// 
+    /// Returns *undefined* if no book is loaded or the position isn't in the book
+    #[wasm_bindgen(js_name = getBookMove)]
+    pub fn get_book_move(&self, random: bool) -> Option<UciMove> {
+        let book = self.book.as_ref()?;
+
+        let mov = if random {
+            book.get_weighted_random_move(&self.board, &mut rand::rng())
+        } else {
+            book.get_best_move(&self.board)
+        };
+
+        mov.map(UciMove::from_move)
+    }
+
+    /// Returns the material the side to move wins (or loses if negative) in centipawns
+    /// if both sides keep capturing on the target square of the provided move
+    #[wasm_bindgen(js_name = getStaticExchange)]
+    pub fn get_static_exchange(&self, uci_move_str: &str) -> Result<i32, String> {
+        let mov = self._get_legal_move_from_uci(uci_move_str)?;
+
+        Ok(see::see(&self.board, &mov))
+    }
+
+    /// Returns true if the provided move captures a piece but loses material in the following exchange
+    #[wasm_bindgen(js_name = isLosingCapture)]
+    pub fn is_losing_capture(&self, uci_move_str: &str) -> Result<bool, String> {
+        let mov = self._get_legal_move_from_uci(uci_move_str)?;
+
+        Ok(see::is_losing_capture(&self.board, &mov))
+    }
+
     #[wasm_bindgen(js_name = getGraveyard)]
     pub fn get_graveyard(&self, color: PieceColor) -> Vec<Piece> {
         let color_index = match color {
@@ -165,8 +544,17 @@ impl Game {
         self.history.clone()
     }
 
-    fn _make_move(&mut self, uci_move: UciMove) -> Result<GameState, String> {
-        // Validate move is legal
+    fn _search_with_progress(&mut self, limits: &SearchLimits, on_progress: Option<Function>) -> SearchResult {
+        let color_to_move = self.board.get_current_color();
+
+        self.searcher.search_with_limits(&mut self.board, limits, |result| {
+            if let Some(on_progress) = &on_progress {
+                SearchProgress::from_search_result(result, color_to_move).report(on_progress);
+            }
+        })
+    }
+
+    fn _get_legal_move(&self, uci_move: UciMove) -> Result<Move, String> {
         let mut legal_moves = move_gen::create_empty_move_buffer();
         let attack_calc = AttackCalculator::new(&self.board);
         move_gen::generate_moves(&mut legal_moves, &self.board, &attack_calc);
@@ -183,6 +571,20 @@ impl Game {
             return Err("Illegal move".to_string());
         }
 
+        Ok(mov)
+    }
+
+    fn _get_legal_move_from_uci(&self, uci_move_str: &str) -> Result<Move, String> {
+        match UciMove::from_str(uci_move_str) {
+            UciMoveCreationResult::Success(uci_move) => self._get_legal_move(uci_move),
+            UciMoveCreationResult::Failure => Err("Invalid UCI move format".to_string()),
+        }
+    }
+
+    fn _make_move(&mut self, uci_move: UciMove) -> Result<GameState, String> {
+        // Validate move is legal
+        let mov = self._get_legal_move(uci_move)?;
+
         let opponent_color = self.board.get_opposite_color();
         
         let move_record = self.board.make_move(mov);
@@ -199,9 +601,10 @@ impl Game {
         // Add a FEN string of the new position to history
         let new_fen = self.board.to_fen();
         self.history.push(new_fen);
+        self.moves.push(mov);
 
         let attack_calc = AttackCalculator::new(&self.board);
-        legal_moves.clear();
+        let mut legal_moves = move_gen::create_empty_move_buffer();
         move_gen::generate_moves(&mut legal_moves, &self.board, &attack_calc);
 
         let game_state = GameState::from_current_state(&self.board, &legal_moves, &attack_calc);
@@ -0,0 +1,40 @@
+use minamoto_chess_core::search::mate_solver::MateResult;
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+
+use crate::uci_move::UciMove;
+
+/// Outcome of a search for a forced mate of the side to move
+#[derive(Tsify, Serialize, Deserialize)]
+#[tsify(into_wasm_abi)]
+pub struct MateSearchResult {
+    /// Moves of the side to move until mate, *None* if no mate was found
+    pub mate_in: Option<u8>,
+    /// Moves of both sides from the current position to the mate, the defender playing the longest resistance.
+    /// Empty if no mate was found
+    pub line: Vec<UciMove>,
+    /// True if the node limit stopped the search, so a mate may still exist
+    pub aborted: bool,
+}
+
+impl MateSearchResult {
+    pub fn from_mate_result(result: MateResult) -> Self {
+        match result {
+            MateResult::Mate(line) => Self {
+                mate_in: Some(line.len().div_ceil(2) as u8),
+                line: line.into_iter().map(UciMove::from_move).collect(),
+                aborted: false,
+            },
+            MateResult::NoMate => Self {
+                mate_in: None,
+                line: Vec::new(),
+                aborted: false,
+            },
+            MateResult::Aborted => Self {
+                mate_in: None,
+                line: Vec::new(),
+                aborted: true,
+            },
+        }
+    }
+}
@@ -0,0 +1,434 @@
+/*Reading games in the Portable Game Notation.
+
+A file holds any amount of games, each one made of tag pairs (e.g. [White "Carlsen"]) followed by the movetext:
+moves in SAN with optional move numbers, comments ({...} or ; up to the end of the line), numeric annotation
+glyphs ($1), variations in parentheses (which can be nested) and the result (1-0, 0-1, 1/2-1/2 or *) at the end.
+
+Only the tags and the mainline moves are kept, everything else is skipped. Written games hold the same:
+the tags (the Seven Tag Roster first) and the numbered mainline wrapped to lines of at most 80 characters*/
+
+pub mod san;
+
+use std::fmt;
+
+use minamoto_chess_core::{board::Board, book::builder::GameResult, r#move::Move};
+
+use crate::{board_representation, fen_api::{self, FenApi}};
+
+use san::SanError;
+
+// constants
+const RESULT_TOKENS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+/// Tags every exported game has, in this order, with the values used when they are unknown
+pub const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+const MAX_LINE_LENGTH: usize = 80;
+
+/// Why a game can't be replayed
+#[derive(Debug, PartialEq)]
+pub enum PgnErrorReason {
+    /// The *FEN* tag isn't a position the board can load
+    InvalidStartPosition,
+    Move(SanError),
+}
+
+/// Part of the game that can't be replayed: the starting position or a move of the mainline
+#[derive(Debug)]
+pub struct PgnError {
+    /// Ply of the move counted from 1 in the starting position of the game, 0 for the starting position itself
+    pub ply: usize,
+    /// The move, or the *FEN* tag if the starting position is invalid
+    pub san: String,
+    pub reason: PgnErrorReason,
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.reason {
+            PgnErrorReason::InvalidStartPosition => write!(f, "Invalid FEN tag: {}", self.san),
+            PgnErrorReason::Move(reason) => write!(f, "Ply {} ({}): {}", self.ply, self.san, reason),
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+#[derive(Default, Debug)]
+pub struct PgnGame {
+    /// Tag pairs in the order of the file
+    pub tags: Vec<(String, String)>,
+    /// Mainline moves in SAN
+    pub moves: Vec<String>,
+    /// Result token ending the movetext, *\** if the game has none
+    pub result: String,
+}
+
+impl PgnGame {
+    pub fn get_tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter()
+            .find(|(tag_name, _)| tag_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Position of the *FEN* tag, the regular starting position if there is none
+    pub fn get_start_fen(&self) -> &str {
+        self.get_tag("FEN").unwrap_or(board_representation::DEFAULT_FEN)
+    }
+
+    /// Fails if the *FEN* tag is malformed instead of letting the board panic on it
+    pub fn get_start_board(&self) -> Result<Board, PgnError> {
+        let start_fen = self.get_start_fen();
+        if !fen_api::is_valid_fen(start_fen) {
+            return Err(PgnError {
+                ply: 0,
+                san: start_fen.to_string(),
+                reason: PgnErrorReason::InvalidStartPosition,
+            });
+        }
+
+        Ok(Board::from_fen(start_fen))
+    }
+
+    /// Returns *None* for unfinished games (*\**) and unknown results
+    pub fn get_result(&self) -> Option<GameResult> {
+        let result = if self.result == "*" {
+            self.get_tag("Result").unwrap_or("*")
+        } else {
+            &self.result
+        };
+
+        match result {
+            "1-0" => Some(GameResult::WhiteWon),
+            "0-1" => Some(GameResult::BlackWon),
+            "1/2-1/2" => Some(GameResult::Draw),
+            _ => None,
+        }
+    }
+
+    /// Replays the mainline from the starting position and returns its moves,
+    /// stops at an invalid starting position or at the first move that can't be played
+    pub fn get_mainline_moves(&self) -> Result<Vec<Move>, PgnError> {
+        let mut board = self.get_start_board()?;
+        let mut moves = Vec::with_capacity(self.moves.len());
+
+        for (index, san) in self.moves.iter().enumerate() {
+            let mov = san::move_from_san(&board, san).map_err(|reason| PgnError {
+                ply: index + 1,
+                san: san.clone(),
+                reason: PgnErrorReason::Move(reason),
+            })?;
+
+            board.make_move(mov);
+            moves.push(mov);
+        }
+
+        Ok(moves)
+    }
+
+    /// Writes the game as PGN text: the tags in their order, an empty line and the movetext ending with the result
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = String::new();
+        for (name, value) in &self.tags {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            pgn.push_str(&format!("[{} \"{}\"]\n", name, value));
+        }
+        pgn.push('\n');
+
+        // A malformed FEN tag is written back as it is, the moves are then numbered from the regular start
+        let (mut move_number, mut is_white_to_move) = self.get_start_board()
+            .map_or((1, true), |start_board| (start_board.get_move_counter().max(1), start_board.is_white_to_move()));
+
+        let mut tokens = Vec::with_capacity(self.moves.len() * 3 / 2 + 1);
+        for (index, san) in self.moves.iter().enumerate() {
+            if is_white_to_move {
+                tokens.push(format!("{}.", move_number));
+            } else if index == 0 {
+                tokens.push(format!("{}...", move_number));
+            }
+            tokens.push(san.clone());
+
+            if !is_white_to_move {
+                move_number += 1;
+            }
+            is_white_to_move = !is_white_to_move;
+        }
+        tokens.push(if self.result.is_empty() { "*".to_string() } else { self.result.clone() });
+
+        let mut line_length = 0;
+        for token in tokens {
+            if line_length > 0 && line_length + 1 + token.len() > MAX_LINE_LENGTH {
+                pgn.push('\n');
+                line_length = 0;
+            } else if line_length > 0 {
+                pgn.push(' ');
+                line_length += 1;
+            }
+            line_length += token.len();
+            pgn.push_str(&token);
+        }
+        pgn.push('\n');
+
+        pgn
+    }
+}
+
+/// Reads the value of a tag pair after its opening bracket, returns *None* for a malformed tag
+fn read_tag(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<(String, String)> {
+    let mut name = String::new();
+    while let Some(&symbol) = chars.peek() {
+        if symbol.is_whitespace() || symbol == '"' || symbol == ']' {
+            break;
+        }
+        name.push(symbol);
+        chars.next();
+    }
+
+    while chars.next_if(|symbol| symbol.is_whitespace()).is_some() {}
+
+    let mut value = String::new();
+    if chars.next_if_eq(&'"').is_some() {
+        while let Some(symbol) = chars.next() {
+            match symbol {
+                '\\' => value.extend(chars.next()),
+                '"' => break,
+                _ => value.push(symbol),
+            }
+        }
+    }
+
+    // Anything else before the closing bracket is ignored
+    for symbol in chars.by_ref() {
+        if symbol == ']' {
+            break;
+        }
+    }
+
+    (!name.is_empty()).then_some((name, value))
+}
+
+/// Strips the move number (*12.* or *12...*) glued to the move and returns what is left
+fn strip_move_number(token: &str) -> &str {
+    let without_digits = token.trim_start_matches(|symbol: char| symbol.is_ascii_digit());
+    if without_digits.len() == token.len() || !without_digits.starts_with('.') {
+        return token;
+    }
+
+    without_digits.trim_start_matches('.')
+}
+
+/// Reads every game of the PGN text. Malformed parts are skipped instead of failing the whole file,
+/// the moves themselves are only checked when the game is replayed
+pub fn parse_games(text: &str) -> Vec<PgnGame> {
+    let mut games = Vec::new();
+    let mut game = PgnGame::default();
+    let mut has_movetext = false;
+    let mut variation_depth = 0usize;
+
+    let mut finish_game = |game: &mut PgnGame, has_movetext: &mut bool| {
+        let mut finished_game = std::mem::take(game);
+        if finished_game.result.is_empty() {
+            finished_game.result = "*".to_string();
+        }
+        if !finished_game.tags.is_empty() || !finished_game.moves.is_empty() {
+            games.push(finished_game);
+        }
+        *has_movetext = false;
+    };
+
+    let mut chars = text.chars().peekable();
+    let mut is_line_start = true;
+
+    while let Some(symbol) = chars.next() {
+        let was_line_start = is_line_start;
+        is_line_start = symbol == '\n';
+
+        match symbol {
+            // Escaped line, meant for other programs
+            '%' if was_line_start => {
+                for symbol in chars.by_ref() {
+                    if symbol == '\n' {
+                        is_line_start = true;
+                        break;
+                    }
+                }
+            }
+            '{' => {
+                for symbol in chars.by_ref() {
+                    if symbol == '}' {
+                        break;
+                    }
+                }
+            }
+            ';' => {
+                for symbol in chars.by_ref() {
+                    if symbol == '\n' {
+                        is_line_start = true;
+                        break;
+                    }
+                }
+            }
+            '(' => variation_depth += 1,
+            ')' => variation_depth = variation_depth.saturating_sub(1),
+            '[' if variation_depth == 0 => {
+                // A tag after the movetext starts the next game even if the result is missing
+                if has_movetext {
+                    finish_game(&mut game, &mut has_movetext);
+                }
+                if let Some(tag) = read_tag(&mut chars) {
+                    game.tags.push(tag);
+                }
+            }
+            _ if symbol.is_whitespace() => (),
+            _ => {
+                let mut token = symbol.to_string();
+                while let Some(&next_symbol) = chars.peek() {
+                    if next_symbol.is_whitespace() || "{};()[".contains(next_symbol) {
+                        break;
+                    }
+                    token.push(next_symbol);
+                    chars.next();
+                }
+
+                if variation_depth > 0 || token.starts_with('$') {
+                    continue;
+                }
+
+                if RESULT_TOKENS.contains(&token.as_str()) {
+                    game.result = token;
+                    finish_game(&mut game, &mut has_movetext);
+                    continue;
+                }
+
+                let san = strip_move_number(&token);
+                if !san.is_empty() && !san.chars().all(|symbol| symbol.is_ascii_digit()) {
+                    game.moves.push(san.to_string());
+                }
+                has_movetext = true;
+            }
+        }
+    }
+
+    finish_game(&mut game, &mut has_movetext);
+
+    games
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Game;
+
+    const ANNOTATED_PGN: &str = "[Event \"Casual game\"]
+[White \"Anderssen\"]
+[Black \"Kieseritzky\"]
+
+% escaped line 1. d4
+1. e4 {King's pawn} e5 2. f4 $1 (2. Nf3 Nc6 (2... d6) 3. Bb5) 2... exf4 ; the gambit is accepted
+3. Bc4!? Qh4+ 4. Kf1 1-0
+";
+
+    #[test]
+    fn comments_variations_and_nags_are_skipped() {
+        let games = parse_games(ANNOTATED_PGN);
+        assert_eq!(games.len(), 1);
+
+        let game = &games[0];
+        assert_eq!(game.get_tag("White"), Some("Anderssen"));
+        assert_eq!(game.moves, ["e4", "e5", "f4", "exf4", "Bc4!?", "Qh4+", "Kf1"]);
+        assert_eq!(game.result, "1-0");
+        assert_eq!(game.get_mainline_moves().map(|moves| moves.len()).ok(), Some(7));
+    }
+
+    #[test]
+    fn every_game_of_the_text_is_read() {
+        let games = parse_games("[Round \"1\"]\n\n1. d4 d5 1/2-1/2\n\n[Round \"2\"]\n\n1. c4 *\n\n1. Nf3");
+        let moves: Vec<&[String]> = games.iter().map(|game| game.moves.as_slice()).collect();
+
+        assert_eq!(moves, [&["d4", "d5"][..], &["c4"][..], &["Nf3"][..]]);
+        assert_eq!(games[1].get_result(), None);
+        assert_eq!(games[2].result, "*");
+    }
+
+    #[test]
+    fn illegal_move_error() {
+        let game = &parse_games("1. e4 e5 2. Nf3 Ke6 3. Nxe5 *")[0];
+        let error = game.get_mainline_moves().err().expect("Ke6 is illegal");
+
+        assert_eq!((error.ply, error.san.as_str(), &error.reason), (4, "Ke6", &PgnErrorReason::Move(SanError::Illegal)));
+        assert_eq!(error.to_string(), "Ply 4 (Ke6): illegal move");
+    }
+
+    #[test]
+    fn ambiguous_move_error() {
+        let game = &parse_games("1. e4 e5 2. Nc3 Nc6 3. Ne2 *")[0];
+        let error = game.get_mainline_moves().err().expect("both knights reach e2");
+
+        assert_eq!((error.ply, error.san.as_str(), &error.reason), (5, "Ne2", &PgnErrorReason::Move(SanError::Ambiguous)));
+    }
+
+    #[test]
+    fn invalid_start_position_error() {
+        let game = &parse_games("[SetUp \"1\"]\n[FEN \"8/8/8/8 w - - 0 1\"]\n\n1. e4 *")[0];
+        let error = game.get_mainline_moves().err().expect("the FEN tag is malformed");
+
+        assert_eq!((error.ply, &error.reason), (0, &PgnErrorReason::InvalidStartPosition));
+        assert!(game.get_start_board().is_err());
+    }
+
+    #[test]
+    fn game_is_loaded_from_the_mainline() {
+        let game = Game::from_pgn(ANNOTATED_PGN, None).unwrap_or_else(|error| panic!("{}", error));
+
+        assert_eq!(game.moves.len(), 7);
+        assert_eq!(game.get_pgn_tag("Result").as_deref(), Some("1-0"));
+        assert_eq!(
+            Game::from_pgn("1. e4 e5 2. Nf3 Ke6 *", None).err().as_deref(),
+            Some("Ply 4 (Ke6): illegal move")
+        );
+    }
+
+    #[test]
+    fn export_keeps_the_tags_and_the_mainline() {
+        let game = Game::from_pgn(ANNOTATED_PGN, None).unwrap_or_else(|error| panic!("{}", error));
+        let expected_pgn = "[Event \"Casual game\"]
+[Site \"?\"]
+[Date \"????.??.??\"]
+[Round \"?\"]
+[White \"Anderssen\"]
+[Black \"Kieseritzky\"]
+[Result \"1-0\"]
+
+1. e4 e5 2. f4 exf4 3. Bc4 Qh4+ 4. Kf1 1-0
+";
+        assert_eq!(game.to_pgn(), expected_pgn);
+
+        let reloaded_game = Game::from_pgn(expected_pgn, None).unwrap_or_else(|error| panic!("{}", error));
+        assert_eq!(reloaded_game.to_pgn(), expected_pgn);
+    }
+
+    #[test]
+    fn export_from_a_fen_starts_with_the_side_to_move() {
+        let pgn = "[SetUp \"1\"]\n[FEN \"r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 12\"]\n\n12... O-O-O 13. O-O *";
+        let game = Game::from_pgn(pgn, None).unwrap_or_else(|error| panic!("{}", error));
+        let exported_pgn = game.to_pgn();
+
+        assert!(exported_pgn.contains("[SetUp \"1\"]\n[FEN \"r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 12\"]"), "{}", exported_pgn);
+        assert!(exported_pgn.ends_with("\n12... O-O-O 13. O-O *\n"), "{}", exported_pgn);
+    }
+
+    #[test]
+    fn long_movetext_is_wrapped() {
+        let moves = ["Nf3", "Nf6", "Ng1", "Ng8"].repeat(10).into_iter().map(String::from).collect();
+        let pgn = PgnGame { tags: Vec::new(), moves, result: "*".to_string() }.to_pgn();
+
+        assert!(pgn.lines().all(|line| line.len() <= MAX_LINE_LENGTH), "{}", pgn);
+        assert_eq!(parse_games(&pgn)[0].moves.len(), 40);
+    }
+}
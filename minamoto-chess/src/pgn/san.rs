@@ -0,0 +1,357 @@
+use std::fmt;
+
+use minamoto_chess_core::{board::Board, r#move::{Move, MoveType}, move_generation::{attack_calculator::AttackCalculator, move_gen}, piece};
+
+use crate::board_representation::{find_square_from_name, get_square_name, piece_to_fen_sym};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SanError {
+    /// The text isn't a move in the standard algebraic notation
+    Invalid,
+    /// No legal move matches
+    Illegal,
+    /// Several legal moves match, the move doesn't say which piece moves
+    Ambiguous,
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SanError::Invalid => write!(f, "not a SAN move"),
+            SanError::Illegal => write!(f, "illegal move"),
+            SanError::Ambiguous => write!(f, "ambiguous move"),
+        }
+    }
+}
+
+impl std::error::Error for SanError {}
+
+fn get_piece_type_from_letter(letter: char) -> Option<usize> {
+    match letter {
+        'K' => Some(piece::KING),
+        'Q' => Some(piece::QUEEN),
+        'R' => Some(piece::ROOK),
+        'B' => Some(piece::BISHOP),
+        'N' => Some(piece::KNIGHT),
+        _ => None,
+    }
+}
+
+fn get_promotion_move_type(piece_type: usize) -> Option<MoveType> {
+    match piece_type {
+        piece::QUEEN => Some(MoveType::PromotionQueen),
+        piece::ROOK => Some(MoveType::PromotionRook),
+        piece::BISHOP => Some(MoveType::PromotionBishop),
+        piece::KNIGHT => Some(MoveType::PromotionKnight),
+        _ => None,
+    }
+}
+
+fn get_promotion_piece_type(move_type: MoveType) -> Option<usize> {
+    match move_type {
+        MoveType::PromotionQueen => Some(piece::QUEEN),
+        MoveType::PromotionRook => Some(piece::ROOK),
+        MoveType::PromotionBishop => Some(piece::BISHOP),
+        MoveType::PromotionKnight => Some(piece::KNIGHT),
+        _ => None,
+    }
+}
+
+/// What the SAN says about the move, the legal move is the only one matching all of it
+struct SanMove {
+    piece_type: usize,
+    target_square: usize,
+    /// Board file (h is 0) of the start square, when the move gives it
+    start_file: Option<usize>,
+    start_rank: Option<usize>,
+    promotion: Option<MoveType>,
+}
+
+impl SanMove {
+    fn parse(san: &str) -> Option<Self> {
+        let (san, promotion) = match san.split_once('=') {
+            Some((san, promotion)) => (san, Some(promotion)),
+            // Some programs leave out the '=' of the promotions
+            None => match san.char_indices().last() {
+                Some((index, letter)) if get_piece_type_from_letter(letter).is_some() && index > 0 => (&san[..index], Some(&san[index..])),
+                _ => (san, None),
+            },
+        };
+        let promotion = match promotion {
+            Some(letter) => {
+                let mut letters = letter.chars();
+                let piece_type = letters.next().and_then(get_piece_type_from_letter)?;
+                if letters.next().is_some() {
+                    return None;
+                }
+                Some(get_promotion_move_type(piece_type)?)
+            }
+            None => None,
+        };
+
+        let (piece_type, squares) = match san.chars().next().and_then(get_piece_type_from_letter) {
+            Some(piece_type) => (piece_type, &san[1..]),
+            None => (piece::PAWN, san),
+        };
+        let squares = squares.replace('x', "");
+        if squares.len() < 2 || squares.len() > 4 || !squares.is_ascii() {
+            return None;
+        }
+
+        let (disambiguation, target) = squares.split_at(squares.len() - 2);
+        let target_square = find_square_from_name(target)?;
+
+        let mut start_file = None;
+        let mut start_rank = None;
+        for symbol in disambiguation.chars() {
+            match symbol {
+                'a'..='h' if start_file.is_none() && start_rank.is_none() => start_file = Some(7 - (symbol as usize - 'a' as usize)),
+                '1'..='8' if start_rank.is_none() => start_rank = Some(symbol as usize - '1' as usize),
+                _ => return None,
+            }
+        }
+
+        if promotion.is_some() && piece_type != piece::PAWN {
+            return None;
+        }
+
+        Some(Self {
+            piece_type,
+            target_square,
+            start_file,
+            start_rank,
+            promotion,
+        })
+    }
+
+    fn matches(&self, board: &Board, mov: &Move) -> bool {
+        if mov.is_castling() || mov.target_square != self.target_square {
+            return false;
+        }
+
+        let promotion = mov.is_promotion().then_some(mov.move_type);
+
+        board.get_piece_on_square(mov.start_square).1 == self.piece_type
+        && promotion == self.promotion
+        && self.start_file.is_none_or(|file| mov.start_square & 7 == file)
+        && self.start_rank.is_none_or(|rank| mov.start_square >> 3 == rank)
+    }
+}
+
+/// Finds the legal move written in the standard algebraic notation (e.g. *Nf3*, *exd5*, *O-O*, *e8=Q+*).
+/// Check marks and annotations (*!*, *?*) are ignored
+pub fn move_from_san(board: &Board, san: &str) -> Result<Move, SanError> {
+    let san = san.trim_end_matches(['+', '#', '!', '?']);
+
+    let attack_calc = AttackCalculator::new(board);
+    let mut legal_moves = move_gen::create_empty_move_buffer();
+    move_gen::generate_moves(&mut legal_moves, board, &attack_calc);
+
+    let castling_type = match san {
+        "O-O" | "0-0" => Some(MoveType::CastlingKingSide),
+        "O-O-O" | "0-0-0" => Some(MoveType::CastlingQueenSide),
+        _ => None,
+    };
+
+    let mut candidates: Vec<Move> = match castling_type {
+        Some(castling_type) => legal_moves.into_iter()
+            .filter(|mov| mov.move_type == castling_type)
+            .collect(),
+        None => {
+            let san_move = SanMove::parse(san).ok_or(SanError::Invalid)?;
+            legal_moves.into_iter()
+                .filter(|mov| san_move.matches(board, mov))
+                .collect()
+        }
+    };
+
+    match candidates.len() {
+        0 => Err(SanError::Illegal),
+        1 => Ok(candidates.remove(0)),
+        _ => Err(SanError::Ambiguous),
+    }
+}
+
+/// Writes the legal move in the standard algebraic notation, with the file or the rank of the start square
+/// only when another piece of the same type can reach the target square, and *+* or *#* after checks and mates
+pub fn move_to_san(board: &Board, mov: Move) -> String {
+    let mut san = match mov.move_type {
+        MoveType::CastlingKingSide => "O-O".to_string(),
+        MoveType::CastlingQueenSide => "O-O-O".to_string(),
+        _ => {
+            let piece_type = board.get_piece_on_square(mov.start_square).1;
+            let is_capture = board.get_piece_on_square(mov.capture_square).1 != piece::NONE;
+            let start_name = get_square_name(mov.start_square);
+            let mut san = String::new();
+
+            if piece_type == piece::PAWN {
+                if is_capture {
+                    san.push_str(&start_name[..1]);
+                }
+            } else {
+                san.push(piece_to_fen_sym(piece::WHITE, piece_type));
+
+                let attack_calc = AttackCalculator::new(board);
+                let mut legal_moves = move_gen::create_empty_move_buffer();
+                move_gen::generate_moves(&mut legal_moves, board, &attack_calc);
+
+                let rivals: Vec<&Move> = legal_moves.iter()
+                    .filter(|other| other.target_square == mov.target_square && other.start_square != mov.start_square)
+                    .filter(|other| !other.is_castling() && board.get_piece_on_square(other.start_square).1 == piece_type)
+                    .collect();
+
+                if !rivals.is_empty() {
+                    let shares_file = rivals.iter().any(|other| other.start_square & 7 == mov.start_square & 7);
+                    let shares_rank = rivals.iter().any(|other| other.start_square >> 3 == mov.start_square >> 3);
+
+                    if !shares_file {
+                        san.push_str(&start_name[..1]);
+                    } else if !shares_rank {
+                        san.push_str(&start_name[1..]);
+                    } else {
+                        san.push_str(start_name);
+                    }
+                }
+            }
+
+            if is_capture {
+                san.push('x');
+            }
+            san.push_str(get_square_name(mov.target_square));
+
+            if let Some(promotion_piece_type) = get_promotion_piece_type(mov.move_type) {
+                san.push('=');
+                san.push(piece_to_fen_sym(piece::WHITE, promotion_piece_type));
+            }
+
+            san
+        }
+    };
+
+    let mut board_after_move = board.clone();
+    board_after_move.make_move(mov);
+    let attack_calc = AttackCalculator::new(&board_after_move);
+    if attack_calc.in_check() {
+        let mut legal_moves = move_gen::create_empty_move_buffer();
+        move_gen::generate_moves(&mut legal_moves, &board_after_move, &attack_calc);
+        san.push(if legal_moves.is_empty() { '#' } else { '+' });
+    }
+
+    san
+}
+
+#[cfg(test)]
+mod tests {
+    use minamoto_chess_core::{board::Board, move_generation::{attack_calculator::AttackCalculator, move_gen}};
+
+    use super::{move_from_san, move_to_san, SanError};
+    use crate::{fen_api::FenApi, uci_move::UciMove};
+
+    const KNIGHTS_FEN: &str = "4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1";
+    const ROOKS_FEN: &str = "4k3/8/8/R7/8/8/8/R3K3 w - - 0 1";
+    /// Queens on e4, h4 and h1 all reach e1
+    const QUEENS_FEN: &str = "8/8/1k6/8/4Q2Q/8/8/K6Q w - - 0 1";
+    const PROMOTION_FEN: &str = "8/4P3/8/8/8/8/k7/4K3 w - - 0 1";
+    const CASTLING_FEN: &str = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+
+    fn assert_san(fen: &str, san: &str, expected_uci: &str) {
+        let board = Board::from_fen(fen);
+        match move_from_san(&board, san) {
+            Ok(mov) => assert_eq!(UciMove::from_move(mov).to_string(), expected_uci, "{} in {}", san, fen),
+            Err(error) => panic!("{} in {}: {}", san, fen, error),
+        }
+    }
+
+    fn assert_san_error(fen: &str, san: &str, expected_error: SanError) {
+        let board = Board::from_fen(fen);
+        match move_from_san(&board, san) {
+            Ok(mov) => panic!("{} in {} is {}", san, fen, UciMove::from_move(mov)),
+            Err(error) => assert_eq!(error, expected_error, "{} in {}", san, fen),
+        }
+    }
+
+    /// Writes the legal move given in UCI and checks the SAN reads back as the same move
+    fn assert_written_san(fen: &str, uci: &str, expected_san: &str) {
+        let board = Board::from_fen(fen);
+        let mut legal_moves = move_gen::create_empty_move_buffer();
+        move_gen::generate_moves(&mut legal_moves, &board, &AttackCalculator::new(&board));
+        let mov = legal_moves.into_iter()
+            .find(|mov| UciMove::from_move(*mov).to_string() == uci)
+            .unwrap_or_else(|| panic!("{} is illegal in {}", uci, fen));
+
+        assert_eq!(move_to_san(&board, mov), expected_san, "{} in {}", uci, fen);
+        assert_san(fen, expected_san, uci);
+    }
+
+    #[test]
+    fn disambiguation_by_file() {
+        assert_san(KNIGHTS_FEN, "Nbd2", "b1d2");
+        assert_san(KNIGHTS_FEN, "Nfd2", "f3d2");
+        assert_san_error(KNIGHTS_FEN, "Nd2", SanError::Ambiguous);
+    }
+
+    #[test]
+    fn disambiguation_by_rank() {
+        assert_san(ROOKS_FEN, "R1a3", "a1a3");
+        assert_san(ROOKS_FEN, "R5a3", "a5a3");
+        assert_san_error(ROOKS_FEN, "Ra3", SanError::Ambiguous);
+    }
+
+    #[test]
+    fn disambiguation_by_file_and_rank() {
+        assert_san(QUEENS_FEN, "Qh4e1", "h4e1");
+        assert_san(QUEENS_FEN, "Qee1", "e4e1");
+        assert_san(QUEENS_FEN, "Q1e1", "h1e1");
+        assert_san_error(QUEENS_FEN, "Qhe1", SanError::Ambiguous);
+        assert_san_error(QUEENS_FEN, "Q4e1", SanError::Ambiguous);
+    }
+
+    #[test]
+    fn promotion_with_and_without_equals_sign() {
+        assert_san(PROMOTION_FEN, "e8=Q", "e7e8q");
+        assert_san(PROMOTION_FEN, "e8Q", "e7e8q");
+        assert_san(PROMOTION_FEN, "e8N", "e7e8n");
+        assert_san_error(PROMOTION_FEN, "e8", SanError::Illegal);
+    }
+
+    #[test]
+    fn castling() {
+        assert_san(CASTLING_FEN, "O-O", "e1g1");
+        assert_san(CASTLING_FEN, "O-O-O", "e1c1");
+        assert_san(CASTLING_FEN, "0-0", "e1g1");
+        assert_san(&CASTLING_FEN.replace(" w ", " b "), "O-O-O", "e8c8");
+        assert_san_error("r3k2r/8/8/8/8/8/8/R3K2R w Qkq - 0 1", "O-O", SanError::Illegal);
+    }
+
+    #[test]
+    fn check_marks_and_annotations_are_ignored() {
+        assert_san(KNIGHTS_FEN, "Nfd2!?", "f3d2");
+        assert_san(PROMOTION_FEN, "e8=Q+", "e7e8q");
+        assert_san_error(KNIGHTS_FEN, "Nz9", SanError::Invalid);
+    }
+
+    #[test]
+    fn written_disambiguation_is_minimal() {
+        assert_written_san(KNIGHTS_FEN, "b1d2", "Nbd2");
+        assert_written_san(KNIGHTS_FEN, "b1c3", "Nc3");
+        assert_written_san(ROOKS_FEN, "a5a3", "R5a3");
+        assert_written_san(QUEENS_FEN, "h4e1", "Qh4e1");
+        assert_written_san(QUEENS_FEN, "e4e1", "Qee1");
+        assert_written_san(QUEENS_FEN, "h1e1", "Q1e1");
+    }
+
+    #[test]
+    fn written_special_moves() {
+        assert_written_san(PROMOTION_FEN, "e7e8q", "e8=Q");
+        assert_written_san(PROMOTION_FEN, "e7e8n", "e8=N");
+        assert_written_san(CASTLING_FEN, "e1g1", "O-O");
+        assert_written_san(CASTLING_FEN, "e1c1", "O-O-O");
+        assert_written_san("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3", "e5d6", "exd6");
+    }
+
+    #[test]
+    fn written_checks_and_mates() {
+        assert_written_san(ROOKS_FEN, "a5a8", "Ra8+");
+        assert_written_san("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "a1a8", "Ra8#");
+    }
+}
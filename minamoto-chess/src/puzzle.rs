@@ -0,0 +1,174 @@
+use minamoto_chess_core::{board::Board, r#move::Move, move_generation::{attack_calculator::AttackCalculator, move_gen}};
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use wasm_bindgen::prelude::*;
+
+use crate::{fen_api, uci_move::UciMove, Game};
+
+#[derive(Tsify, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[tsify(into_wasm_abi)]
+pub enum PuzzleStatus {
+    InProgress,
+    Failed,
+    Solved,
+}
+
+/// What happened after the player's move
+#[derive(Tsify, Serialize, Deserialize)]
+#[tsify(into_wasm_abi)]
+pub struct PuzzleProgress {
+    pub status: PuzzleStatus,
+    /// Reply of the opponent, already played on the game. *None* if the puzzle is over
+    pub opponent_move: Option<UciMove>,
+    /// Move of the solution the player should have played, only set when the puzzle is failed
+    pub expected_move: Option<UciMove>,
+    /// Moves of the player found so far
+    pub solved_moves: usize,
+    /// Moves of the player in the whole solution
+    pub total_moves: usize,
+}
+
+/// Tactics puzzle played on a *Game* created by *createGame*
+///
+/// The solution alternates the player's and the opponent's moves, starting with the player's.
+/// Every player's move has to match the solution, except for a mate in one which is always accepted.
+/// A wrong move isn't played on the game and fails the puzzle
+#[wasm_bindgen]
+pub struct Puzzle {
+    fen: String,
+    solution: Vec<Move>,
+    /// Index of the next move of the solution
+    ply: usize,
+    status: PuzzleStatus,
+    /// Hash of the position the game has to be in, so moves played on the game behind the puzzle's back are noticed
+    position_hash: u64,
+}
+
+fn is_checkmate(board: &Board) -> bool {
+    let attack_calc = AttackCalculator::new(board);
+    let mut legal_moves = move_gen::create_empty_move_buffer();
+    move_gen::generate_moves(&mut legal_moves, board, &attack_calc);
+
+    legal_moves.is_empty() && attack_calc.in_check()
+}
+
+fn is_same_move(mov: &Move, other: &Move) -> bool {
+    mov.start_square == other.start_square
+        && mov.target_square == other.target_square
+        && std::mem::discriminant(&mov.move_type) == std::mem::discriminant(&other.move_type)
+}
+
+#[wasm_bindgen]
+impl Puzzle {
+    /// Creates the puzzle from the starting position and the UCI moves of the solution,
+    /// fails if the FEN is malformed or a move of the solution is illegal
+    #[wasm_bindgen(js_name = fromFen)]
+    pub fn from_fen(fen: &str, solution: Vec<String>) -> Result<Puzzle, String> {
+        if !fen_api::is_valid_fen(fen) {
+            return Err(format!("Invalid FEN: {}", fen));
+        }
+        if solution.is_empty() {
+            return Err("The solution is empty".to_string());
+        }
+
+        let mut game = Game::from_fen(fen);
+        let position_hash = game.board.get_zobrist_hash();
+        let mut solution_moves = Vec::with_capacity(solution.len());
+
+        for (index, uci_move_str) in solution.iter().enumerate() {
+            let mov = game._get_legal_move_from_uci(uci_move_str)
+                .map_err(|error| format!("Solution move {} ({}): {}", index + 1, uci_move_str, error))?;
+            game.board.make_move(mov);
+            solution_moves.push(mov);
+        }
+
+        Ok(Puzzle {
+            fen: fen.to_string(),
+            solution: solution_moves,
+            ply: 0,
+            status: PuzzleStatus::InProgress,
+            position_hash,
+        })
+    }
+
+    /// Creates a game in the starting position of the puzzle, the player's moves are played on it by *playMove*
+    #[wasm_bindgen(js_name = createGame)]
+    pub fn create_game(&self) -> Game {
+        Game::from_fen(&self.fen)
+    }
+
+    /// Checks the player's move against the solution. The right move is played on the game
+    /// together with the opponent's reply, a wrong one fails the puzzle and isn't played
+    ///
+    /// Fails if the puzzle is over, the move is illegal or the game isn't in the position of the puzzle
+    #[wasm_bindgen(js_name = playMove)]
+    pub fn play_move(&mut self, game: &mut Game, uci_move_str: &str) -> Result<PuzzleProgress, String> {
+        if self.status != PuzzleStatus::InProgress {
+            return Err("The puzzle is over".to_string());
+        }
+        if game.board.get_zobrist_hash() != self.position_hash {
+            return Err("The game isn't in the position of the puzzle".to_string());
+        }
+
+        let mov = game._get_legal_move_from_uci(uci_move_str)?;
+        let expected_move = self.solution[self.ply];
+
+        let mut board_after_move = game.board.clone();
+        board_after_move.make_move(mov);
+        let is_mate = is_checkmate(&board_after_move);
+
+        if !is_same_move(&mov, &expected_move) && !is_mate {
+            self.status = PuzzleStatus::Failed;
+            return Ok(self.get_progress(None, Some(expected_move)));
+        }
+
+        game._make_move(UciMove::from_move(mov))?;
+        self.ply += 1;
+
+        let mut opponent_move = None;
+        if is_mate || self.ply >= self.solution.len() {
+            // a mate ends the puzzle even if the solution found a longer one
+            self.ply = self.solution.len();
+            self.status = PuzzleStatus::Solved;
+        } else {
+            let reply = self.solution[self.ply];
+            game._make_move(UciMove::from_move(reply))?;
+            self.ply += 1;
+            opponent_move = Some(UciMove::from_move(reply));
+
+            if self.ply >= self.solution.len() {
+                self.status = PuzzleStatus::Solved;
+            }
+        }
+
+        self.position_hash = game.board.get_zobrist_hash();
+        Ok(self.get_progress(opponent_move, None))
+    }
+
+    /// Returns the next move of the solution, *undefined* if the puzzle is over
+    #[wasm_bindgen(js_name = getHint)]
+    pub fn get_hint(&self) -> Option<UciMove> {
+        (self.status == PuzzleStatus::InProgress).then(|| UciMove::from_move(self.solution[self.ply]))
+    }
+
+    #[wasm_bindgen(js_name = getStatus)]
+    pub fn get_status(&self) -> PuzzleStatus {
+        self.status
+    }
+
+    /// Returns every move of the solution, including the opponent's replies
+    #[wasm_bindgen(js_name = getSolution)]
+    pub fn get_solution(&self) -> Vec<UciMove> {
+        self.solution.iter().copied().map(UciMove::from_move).collect()
+    }
+
+    fn get_progress(&self, opponent_move: Option<UciMove>, expected_move: Option<Move>) -> PuzzleProgress {
+        PuzzleProgress {
+            status: self.status,
+            opponent_move,
+            expected_move: expected_move.map(UciMove::from_move),
+            solved_moves: self.ply.div_ceil(2),
+            total_moves: self.solution.len().div_ceil(2),
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use js_sys::Function;
+use minamoto_chess_core::{piece, search::{search_result::SearchResult, searcher}};
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use wasm_bindgen::JsValue;
+
+use crate::{analysis::AnalysisLine, uci_move::UciMove};
+
+/// State of the search after a completed iteration, passed to the progress callbacks
+#[derive(Tsify, Serialize, Deserialize)]
+#[tsify(into_wasm_abi)]
+pub struct SearchProgress {
+    pub depth: u8,
+    /// The deepest ply reached so far, including the captures searched past the depth
+    pub seldepth: u8,
+    pub nodes: u64,
+    /// Nodes per second
+    pub nps: u64,
+    pub time_ms: u64,
+    /// Score in centipawns, positive if white is better and negative if black is better
+    pub score: i32,
+    /// Full moves until mate, positive if white mates and negative if black mates. *None* if there is no forced mate
+    pub mate: Option<i32>,
+    /// Best line starting from the searched position
+    pub pv: Vec<UciMove>,
+    /// Every MultiPV line ranked from the best to the worst, the first one matches the fields above
+    pub lines: Vec<AnalysisLine>,
+}
+
+impl SearchProgress {
+    /// Converts the search result into the progress seen from white's perspective
+    pub fn from_search_result(result: &SearchResult, color_to_move: usize) -> Self {
+        let sign = if color_to_move == piece::WHITE { 1 } else { -1 };
+
+        Self {
+            depth: result.depth,
+            seldepth: result.seldepth,
+            nodes: result.nodes,
+            nps: result.nodes * 1000 / result.time_ms.max(1),
+            time_ms: result.time_ms,
+            score: result.score * sign,
+            mate: searcher::is_mate_score(result.score).then(|| searcher::get_mate_distance(result.score) * sign),
+            pv: result.pv.iter().copied().map(UciMove::from_move).collect(),
+            lines: result.lines.iter().map(|line| AnalysisLine::from_pv_line(line, color_to_move)).collect(),
+        }
+    }
+
+    /// Calls the JS callback with the progress as its only argument
+    ///
+    /// Whatever the callback throws is ignored, a broken progress display shouldn't stop the search
+    pub fn report(&self, callback: &Function) {
+        if let Ok(progress) = serde_wasm_bindgen::to_value(self) {
+            let _ = callback.call1(&JsValue::NULL, &progress);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use js_sys::Function;
+use minamoto_chess_core::search::stepped_search::SteppedSearch;
+use wasm_bindgen::prelude::*;
+
+use crate::{search_progress::SearchProgress, uci_move::UciMove};
+
+// constants
+/// Node budget of a step that sets neither limit, small enough to keep the page responsive
+const DEFAULT_STEP_NODES: u32 = 20_000;
+
+/// Search of a position running in small steps, so the page keeps rendering and handling input in between.
+/// Created by *Game.startSearch*, doesn't change the game
+#[wasm_bindgen]
+pub struct SearchTask {
+    search: SteppedSearch,
+}
+
+impl SearchTask {
+    pub fn new(search: SteppedSearch) -> Self {
+        Self { search }
+    }
+}
+
+#[wasm_bindgen]
+impl SearchTask {
+    /// Searches until the provided amount of nodes or milliseconds is used (20000 nodes if neither is set,
+    /// so a step never runs the whole search at once). *onProgress* is called with a *SearchProgress*
+    /// after every iteration completed during the step
+    ///
+    /// Returns true once the search is finished, the following steps do nothing then
+    #[wasm_bindgen(js_name = step)]
+    pub fn step(&mut self, max_nodes: Option<u32>, max_time_ms: Option<u32>, on_progress: Option<Function>) -> bool {
+        let color_to_move = self.search.get_board().get_current_color();
+        let max_nodes = if max_nodes.is_none() && max_time_ms.is_none() { Some(DEFAULT_STEP_NODES) } else { max_nodes };
+
+        self.search.step(max_nodes.map(u64::from), max_time_ms.map(u64::from), |result| {
+            if let Some(on_progress) = &on_progress {
+                SearchProgress::from_search_result(result, color_to_move).report(on_progress);
+            }
+        })
+    }
+
+    /// Finishes the search early, the best move found so far stays available
+    #[wasm_bindgen(js_name = cancel)]
+    pub fn cancel(&mut self) {
+        self.search.cancel();
+    }
+
+    #[wasm_bindgen(js_name = isFinished)]
+    pub fn is_finished(&self) -> bool {
+        self.search.is_finished()
+    }
+
+    /// Returns the best move of the last completed iteration,
+    /// *undefined* before the first one or if there are no legal moves
+    #[wasm_bindgen(js_name = getBestMove)]
+    pub fn get_best_move(&self) -> Option<UciMove> {
+        self.search.get_result().best_move.map(UciMove::from_move)
+    }
+
+    /// Returns the state of the search after the last completed iteration, *undefined* before the first one
+    #[wasm_bindgen(js_name = getProgress)]
+    pub fn get_progress(&self) -> Option<SearchProgress> {
+        let result = self.search.get_result();
+        let color_to_move = self.search.get_board().get_current_color();
+
+        (result.depth > 0).then(|| SearchProgress::from_search_result(result, color_to_move))
+    }
+}
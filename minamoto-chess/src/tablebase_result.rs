@@ -0,0 +1,27 @@
+use minamoto_chess_core::{piece, syzygy::TablebaseProbe};
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+
+/// Result of the current position in the endgame tablebases
+#[derive(Tsify, Serialize, Deserialize)]
+#[tsify(into_wasm_abi)]
+pub struct TablebaseResult {
+    /// 2 if white wins, 1 if white wins but not before the 50 moves rule draws the game, 0 if it is a draw,
+    /// -1 and -2 are the same for black
+    pub wdl: i8,
+    /// Plies to the next capture or pawn move with the best play, positive if white wins and negative if black wins.
+    /// *None* if the DTZ table isn't loaded
+    pub dtz: Option<i32>,
+}
+
+impl TablebaseResult {
+    /// Converts the probe result into the result seen from white's perspective
+    pub fn from_probe(probe: &TablebaseProbe, color_to_move: usize) -> Self {
+        let sign = if color_to_move == piece::WHITE { 1 } else { -1 };
+
+        Self {
+            wdl: probe.wdl as i8 * sign as i8,
+            dtz: probe.dtz.map(|dtz| dtz * sign),
+        }
+    }
+}